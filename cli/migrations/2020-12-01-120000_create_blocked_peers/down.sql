@@ -0,0 +1 @@
+DROP TABLE IF EXISTS blocked_peers;
\ No newline at end of file
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS blocked_peers (
+    id INTEGER PRIMARY KEY NOT NULL,
+    peer_id VARCHAR(255) NOT NULL UNIQUE
+);
\ No newline at end of file
@@ -0,0 +1,10 @@
+CREATE TABLE users_backup (
+    id INTEGER PRIMARY KEY NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    peer_id VARCHAR(255) NOT NULL,
+    online INTEGER NOT NULL,
+    secret BLOB
+);
+INSERT INTO users_backup SELECT id, name, peer_id, online, secret FROM users;
+DROP TABLE users;
+ALTER TABLE users_backup RENAME TO users;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN last_seen BIGINT NOT NULL DEFAULT 0;
\ No newline at end of file
@@ -1,7 +1,17 @@
 use async_std::io;
 use structopt::StructOpt;
 
-use crate::network::send;
+use crate::event_handler::Latencies;
+use crate::models::{NewBlockedPeer, PeerId, UpdateUser, User};
+use crate::network::{
+    accept_contact, block, decline_contact, mailbox, profile, rendezvous, request_contact, send,
+    unblock,
+};
+use crate::repos::{BlockedPeersRepo, UsersRepo};
+use primitives::PresenceStatus;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tata_core::Node;
 
 #[derive(StructOpt, Debug)]
 enum Opt {
@@ -22,9 +32,93 @@ enum Opt {
         #[structopt(short, long)]
         message: String,
     },
+    /// Block a peer. Messages from blocked peers are refused.
+    Block {
+        #[structopt(short, long)]
+        peer: String,
+    },
+    /// Unblock a peer
+    Unblock {
+        #[structopt(short, long)]
+        peer: String,
+    },
+    /// Ask a peer to become your contact. Messages can be sent only to contacts.
+    AddContact {
+        #[structopt(short, long)]
+        peer: String,
+        #[structopt(short, long, default_value = "")]
+        note: String,
+    },
+    /// Accept a contact request
+    Accept {
+        #[structopt(short, long)]
+        peer: String,
+    },
+    /// Decline a contact request
+    Decline {
+        #[structopt(short, long)]
+        peer: String,
+    },
+    /// Accept messages only from your contacts (`true` or `false`)
+    ContactsOnly {
+        #[structopt(parse(try_from_str))]
+        enabled: bool,
+    },
+    /// Advertise yourself on the local network (`true` or `false`).
+    /// Known contacts can still be reached while invisible.
+    Visible {
+        #[structopt(parse(try_from_str))]
+        enabled: bool,
+    },
+    /// Discover peers on the local network (`true` or `false`)
+    Discovery {
+        #[structopt(parse(try_from_str))]
+        enabled: bool,
+    },
+    /// Relay messages through connected peers (`true` or `false`)
+    Forwarding {
+        #[structopt(parse(try_from_str))]
+        enabled: bool,
+    },
+    /// Use a mailbox node for messages to offline contacts
+    Mailbox {
+        #[structopt(short, long)]
+        peer: String,
+        #[structopt(short, long)]
+        address: String,
+    },
+    /// Fetch messages held by the mailbox
+    Fetch,
+    /// Use a rendezvous server to find peers outside of the local network
+    Rendezvous {
+        #[structopt(short, long)]
+        peer: String,
+        #[structopt(short, long)]
+        address: String,
+    },
+    /// Register in a namespace at the rendezvous server
+    Join { namespace: String },
+    /// Remove the registration in a namespace from the rendezvous server
+    Leave { namespace: String },
+    /// Find peers registered in a namespace without joining it
+    Discover { namespace: String },
+    /// Set your presence: `online`, `away`, `busy` or `offline`
+    Presence { status: PresenceStatus },
+    /// Change your name and status. Connected peers are notified right away.
+    Profile {
+        #[structopt(short, long)]
+        name: String,
+        #[structopt(short, long, default_value = "")]
+        status: String,
+    },
 }
 
-pub async fn start_command_line() {
+pub async fn start_command_line(
+    node: &Node,
+    users_repo: &dyn UsersRepo,
+    blocked_peers_repo: &dyn BlockedPeersRepo,
+    latencies: &Latencies,
+) {
     loop {
         let mut command = String::new();
         io::stdin()
@@ -40,12 +134,166 @@ pub async fn start_command_line() {
             }
         };
         match command {
+            Opt::Me => match node.listen_addresses().await {
+                Ok(addresses) => {
+                    println!("Peer id: {}", node.local_peer_id());
+                    for address in addresses {
+                        println!("Listening on {}", address);
+                    }
+                }
+                Err(e) => println!("Error loading listen addresses: {}", e),
+            },
+            Opt::Peers => match (users_repo.list(), latencies.lock()) {
+                (Ok(users), Ok(latencies)) => print_peers(&users, &latencies),
+                (Err(e), _) => println!("Error loading peers: {}", e),
+                (_, Err(e)) => println!("Error reading latencies: {}", e),
+            },
             Opt::SendMessage { peer, message } => {
-                if !send(peer, message) {
-                    println!("Error sending message to peer");
+                if let Err(e) = send(node, peer, message).await {
+                    println!("Error sending message to peer: {}", e);
+                }
+            }
+            Opt::Block { peer } => {
+                // Only valid peer ids are saved, otherwise restoring the blocklist fails
+                if let Err(e) = block(node, &peer).await {
+                    println!("Error blocking peer: {}", e);
+                    continue;
+                }
+                let blocked_peer = NewBlockedPeer {
+                    peer_id: PeerId::new(peer.clone()),
+                };
+                if let Err(e) = blocked_peers_repo.create(&blocked_peer) {
+                    println!("Error saving blocked peer: {}", e);
+                }
+            }
+            Opt::Unblock { peer } => {
+                if let Err(e) = blocked_peers_repo.delete(&PeerId::new(peer.clone())) {
+                    println!("Error removing blocked peer: {}", e);
+                }
+                if let Err(e) = unblock(node, &peer).await {
+                    println!("Error unblocking peer: {}", e);
                 }
             }
-            _ => (),
+            Opt::AddContact { peer, note } => {
+                if let Err(e) = request_contact(node, &peer, note).await {
+                    println!("Error sending contact request: {}", e);
+                }
+            }
+            Opt::Accept { peer } => {
+                if let Err(e) = accept_contact(node, &peer).await {
+                    println!("Error accepting contact request: {}", e);
+                }
+            }
+            Opt::Decline { peer } => {
+                if let Err(e) = decline_contact(node, &peer).await {
+                    println!("Error declining contact request: {}", e);
+                }
+            }
+            Opt::ContactsOnly { enabled } => {
+                if let Err(e) = node.set_contacts_only(enabled).await {
+                    println!("Error setting contacts only mode: {}", e);
+                }
+            }
+            Opt::Visible { enabled } => {
+                if let Err(e) = node.set_visible(enabled).await {
+                    println!("Error setting visibility: {}", e);
+                }
+            }
+            Opt::Discovery { enabled } => {
+                if let Err(e) = node.set_discovery(enabled).await {
+                    println!("Error setting discovery: {}", e);
+                }
+            }
+            Opt::Forwarding { enabled } => {
+                if let Err(e) = node.set_forwarding(enabled).await {
+                    println!("Error setting forwarding mode: {}", e);
+                }
+            }
+            Opt::Mailbox { peer, address } => {
+                if let Err(e) = mailbox(node, &peer, &address).await {
+                    println!("Error setting mailbox: {}", e);
+                }
+            }
+            Opt::Fetch => {
+                if let Err(e) = node.fetch_mailbox().await {
+                    println!("Error fetching mailbox: {}", e);
+                }
+            }
+            Opt::Rendezvous { peer, address } => {
+                if let Err(e) = rendezvous(node, &peer, &address).await {
+                    println!("Error setting rendezvous server: {}", e);
+                }
+            }
+            Opt::Join { namespace } => {
+                if let Err(e) = node.register_namespace(namespace).await {
+                    println!("Error joining namespace: {}", e);
+                }
+            }
+            Opt::Leave { namespace } => {
+                if let Err(e) = node.unregister_namespace(namespace).await {
+                    println!("Error leaving namespace: {}", e);
+                }
+            }
+            Opt::Discover { namespace } => {
+                if let Err(e) = node.discover_namespace(namespace).await {
+                    println!("Error discovering namespace: {}", e);
+                }
+            }
+            Opt::Presence { status } => {
+                if let Err(e) = node.set_presence(status).await {
+                    println!("Error setting presence: {}", e);
+                }
+            }
+            Opt::Profile { name, status } => {
+                match users_repo.local_users().map(|mut users| users.pop()) {
+                    Ok(Some(user)) => {
+                        let update = UpdateUser {
+                            name: Some(name.clone()),
+                            online: None,
+                            last_seen: None,
+                        };
+                        if let Err(e) = users_repo.update(user.id, &update) {
+                            println!("Error saving profile: {}", e);
+                        }
+                    }
+                    Ok(None) => println!("Local user is not found"),
+                    Err(e) => println!("Error loading local user: {}", e),
+                }
+                if let Err(e) = profile(node, name, status).await {
+                    println!("Error updating profile: {}", e);
+                }
+            }
+            Opt::Logs => println!("Logs aren't stored yet"),
+            Opt::Messages { peer } => println!("Messages of {} aren't stored yet", peer),
         }
     }
 }
+
+/// Prints the known peers with their presence and the last ping round-trip time
+fn print_peers(users: &[User], latencies: &HashMap<String, u64>) {
+    println!(
+        "{:<20} {:<54} {:<8} {:>8} {:>10}",
+        "NAME", "PEER ID", "ONLINE", "RTT", "LAST SEEN"
+    );
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_millis() as i64)
+        .unwrap_or_default();
+    for user in users.iter().filter(|user| user.secret.is_none()) {
+        let peer_id = user.peer_id.to_string();
+        let rtt = latencies
+            .get(&peer_id)
+            .map(|rtt| format!("{}ms", rtt))
+            .unwrap_or_else(|| "-".to_string());
+        let online = if user.online == 1 { "yes" } else { "no" };
+        let last_seen = if user.last_seen > 0 {
+            format!("{}s ago", (now - user.last_seen).max(0) / 1000)
+        } else {
+            "-".to_string()
+        };
+        println!(
+            "{:<20} {:<54} {:<8} {:>8} {:>10}",
+            user.name, peer_id, online, rtt, last_seen
+        );
+    }
+}
@@ -18,6 +18,8 @@ pub enum Error {
     DieselConnection(diesel::result::ConnectionError),
     #[display(fmt = "Diesel migrations error: {}", _0)]
     DieselMigration(diesel_migrations::RunMigrationsError),
+    #[display(fmt = "Network error: {}", _0)]
+    Core(tata_core::Error),
     #[display(fmt = "{}", _0)]
     Msg(String),
 }
@@ -31,6 +33,7 @@ impl std::error::Error for Error {
             Error::Diesel(e) => Some(e),
             Error::DieselConnection(e) => Some(e),
             Error::DieselMigration(e) => Some(e),
+            Error::Core(e) => Some(e),
             Error::Msg(_) => None,
         }
     }
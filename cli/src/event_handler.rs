@@ -0,0 +1,108 @@
+//! Handles events coming from the network
+
+use crate::models::{NewUser, PeerId, UpdateUser};
+use crate::prelude::*;
+use crate::repos::{UsersRepo, UsersRepoImpl};
+use diesel::sqlite::SqliteConnection;
+use primitives::{Event, PeerEvent, PresenceStatus};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Last round-trip times of the peers in milliseconds, shared with the command line
+pub type Latencies = Arc<Mutex<HashMap<String, u64>>>;
+
+/// Prints the events and keeps the database in sync with them
+pub struct EventHandler {
+    conn: SqliteConnection,
+    /// Names of the peers seen in metadata
+    names: HashMap<String, String>,
+    latencies: Latencies,
+}
+
+impl EventHandler {
+    pub fn new(conn: SqliteConnection, latencies: Latencies) -> Self {
+        Self {
+            conn,
+            names: HashMap::new(),
+            latencies,
+        }
+    }
+
+    pub fn handle(&mut self, event: PeerEvent) -> Result<()> {
+        // Pings are too frequent to be printed, see `peers` command
+        if !matches!(event.event, Event::PeerRtt { .. }) {
+            println!("{:?}", event);
+        }
+        let PeerEvent { peer_id, event } = event;
+        match event {
+            Event::PeerRtt { rtt_ms } => {
+                if let Ok(mut latencies) = self.latencies.lock() {
+                    latencies.insert(peer_id, rtt_ms);
+                }
+            }
+            Event::ReceivedMetadata { metadata } => {
+                self.update_name(&peer_id, &metadata.name)?;
+                self.names.insert(peer_id, metadata.name);
+            }
+            Event::ContactRequestReceived { metadata, note } => {
+                println!(
+                    "Contact request from {} ({}): {}",
+                    metadata.name, peer_id, note
+                );
+                println!("Use `accept -p {}` or `decline -p {}`", peer_id, peer_id);
+                self.names.insert(peer_id, metadata.name);
+            }
+            Event::ContactRequestAccepted => self.add_contact(&peer_id)?,
+            Event::PresenceChanged { status, last_seen } => {
+                self.update_presence(&peer_id, status, last_seen)?
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn add_contact(&self, peer_id: &str) -> Result<()> {
+        let users_repo = UsersRepoImpl::new(&self.conn);
+        let peer = PeerId::new(peer_id.to_string());
+        if users_repo.find_by_peer_id(&peer)?.is_some() {
+            return Ok(());
+        }
+        let name = self
+            .names
+            .get(peer_id)
+            .cloned()
+            .unwrap_or_else(|| peer_id.to_string());
+        users_repo.create(&NewUser::new_peer(name, peer))?;
+        Ok(())
+    }
+
+    fn update_name(&self, peer_id: &str, name: &str) -> Result<()> {
+        let users_repo = UsersRepoImpl::new(&self.conn);
+        if let Some(user) = users_repo.find_by_peer_id(&PeerId::new(peer_id.to_string()))? {
+            let update = UpdateUser {
+                name: Some(name.to_string()),
+                online: None,
+                last_seen: None,
+            };
+            users_repo.update(user.id, &update)?;
+        }
+        Ok(())
+    }
+
+    fn update_presence(&self, peer_id: &str, status: PresenceStatus, last_seen: u64) -> Result<()> {
+        let users_repo = UsersRepoImpl::new(&self.conn);
+        if let Some(user) = users_repo.find_by_peer_id(&PeerId::new(peer_id.to_string()))? {
+            let online = match status {
+                PresenceStatus::Offline => 0,
+                PresenceStatus::Online | PresenceStatus::Away | PresenceStatus::Busy => 1,
+            };
+            let update = UpdateUser {
+                name: None,
+                online: Some(online),
+                last_seen: Some(last_seen as i64),
+            };
+            users_repo.update(user.id, &update)?;
+        }
+        Ok(())
+    }
+}
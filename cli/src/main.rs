@@ -1,10 +1,15 @@
+// diesel 1.4 derives its trait impls inside anonymous consts
+#![allow(non_local_definitions)]
+
 use async_std::task::spawn;
 use db::{establish_connection, run_migrations};
+use event_handler::{EventHandler, Latencies};
 use futures::{future::ready, StreamExt};
-use network::start;
+use network::{allow, block, create_psk, start};
 use onboarding::onboard_if_necessary;
 use prelude::*;
-use repos::{UsersRepo, UsersRepoImpl};
+use repos::{BlockedPeersRepo, BlockedPeersRepoImpl, UsersRepo, UsersRepoImpl};
+use structopt::StructOpt;
 
 #[macro_use]
 extern crate diesel_migrations;
@@ -14,6 +19,7 @@ extern crate diesel;
 mod command_line;
 mod db;
 mod error;
+mod event_handler;
 mod models;
 mod network;
 mod onboarding;
@@ -21,26 +27,68 @@ mod prelude;
 mod repos;
 mod schema;
 
+#[derive(StructOpt, Debug)]
+#[structopt(name = "tata")]
+struct Args {
+    /// Join the private network with the pre-shared key (64 hex characters).
+    /// Only the nodes with the same key can connect to each other.
+    #[structopt(long)]
+    psk: Option<String>,
+    /// Dial all outbound connections through the SOCKS5 proxy, e.g. `127.0.0.1:9050` for Tor
+    #[structopt(long)]
+    proxy: Option<String>,
+    /// Generate a pre-shared key for a private network, print it and exit
+    #[structopt(long)]
+    generate_psk: bool,
+}
+
 #[async_std::main]
 async fn main() -> Result<()> {
+    let args = Args::from_args();
+    if args.generate_psk {
+        println!("{}", create_psk());
+        println!("Share the key with your team and start with `--psk <key>`");
+        return Ok(());
+    }
     let conn = establish_connection()?;
     run_migrations(&conn)?;
     let users_repo = UsersRepoImpl::new(&conn);
+    let blocked_peers_repo = BlockedPeersRepoImpl::new(&conn);
     onboard_if_necessary(&users_repo).await?;
     let current_user = users_repo
         .local_users()?
         .pop()
         .ok_or("Unexpected missing local user")?;
     println!("Current user: {:?}", current_user);
-    let network_stream = start(
+    let (node, network_stream) = start(
         current_user.secret.expect("Local user has secret; qed"),
         current_user.name,
-    );
-    let network_future = network_stream.for_each(|ev| {
-        println!("{:?}", ev);
+        args.psk,
+        args.proxy,
+    )?;
+    // The core keeps the lists in memory only, so restore them on every start
+    // A bad row is skipped, so that it doesn't prevent the cli from starting
+    for blocked_peer in blocked_peers_repo.list()? {
+        if let Err(e) = block(&node, &blocked_peer.peer_id.to_string()).await {
+            println!("Skipping blocked peer {}: {}", blocked_peer.peer_id, e);
+        }
+    }
+    for user in users_repo.list()? {
+        if user.secret.is_none() {
+            if let Err(e) = allow(&node, &user.peer_id.to_string()).await {
+                println!("Skipping contact {}: {}", user.peer_id, e);
+            }
+        }
+    }
+    let latencies = Latencies::default();
+    let mut event_handler = EventHandler::new(establish_connection()?, latencies.clone());
+    let network_future = network_stream.for_each(move |ev| {
+        if let Err(e) = event_handler.handle(ev) {
+            println!("Error handling network event: {}", e);
+        }
         ready(())
     });
     spawn(network_future);
-    command_line::start_command_line().await;
+    command_line::start_command_line(&node, &users_repo, &blocked_peers_repo, &latencies).await;
     Ok(())
 }
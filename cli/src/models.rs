@@ -1,7 +1,9 @@
+mod blocked_peer;
 mod peer_id;
 mod secret;
 mod user;
 
+pub use blocked_peer::*;
 pub use peer_id::*;
 pub use secret::*;
 pub use user::*;
@@ -0,0 +1,17 @@
+use super::peer_id::PeerId;
+use crate::schema::blocked_peers;
+use diesel::Queryable;
+
+#[derive(Debug, Queryable, Clone)]
+pub struct BlockedPeer {
+    /// Row id, selected with the row only
+    #[allow(dead_code)]
+    pub id: i32,
+    pub peer_id: PeerId,
+}
+
+#[derive(Insertable)]
+#[table_name = "blocked_peers"]
+pub struct NewBlockedPeer {
+    pub peer_id: PeerId,
+}
@@ -27,7 +27,7 @@ impl ToSql<Text, Sqlite> for PeerId {
 impl FromSql<Text, Sqlite> for PeerId {
     fn from_sql(binary: Option<&<Sqlite as Backend>::RawValue>) -> deserialize::Result<Self> {
         let binary = <String as FromSql<Text, Sqlite>>::from_sql(binary)?;
-        Ok(PeerId(binary.into()))
+        Ok(PeerId(binary))
     }
 }
 
@@ -5,19 +5,13 @@ use diesel::sql_types::Binary;
 use diesel::sqlite::Sqlite;
 use std::io::prelude::*;
 
-#[derive(PartialEq, Eq, FromSqlRow, AsExpression, Clone)]
+#[derive(PartialEq, Eq, FromSqlRow, AsExpression, Clone, Default)]
 #[sql_type = "Binary"]
 pub struct Secret(Vec<u8>);
 
-impl Into<Vec<u8>> for Secret {
-    fn into(self) -> Vec<u8> {
-        self.0
-    }
-}
-
-impl Default for Secret {
-    fn default() -> Self {
-        Secret(vec![])
+impl From<Secret> for Vec<u8> {
+    fn from(secret: Secret) -> Vec<u8> {
+        secret.0
     }
 }
 
@@ -47,7 +41,7 @@ impl ToSql<Binary, Sqlite> for Secret {
 impl FromSql<Binary, Sqlite> for Secret {
     fn from_sql(binary: Option<&<Sqlite as Backend>::RawValue>) -> deserialize::Result<Self> {
         let binary = <Vec<u8> as FromSql<Binary, Sqlite>>::from_sql(binary)?;
-        Ok(Secret(binary.into()))
+        Ok(Secret(binary))
     }
 }
 
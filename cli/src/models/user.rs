@@ -11,6 +11,8 @@ pub struct User {
     pub peer_id: PeerId,
     pub online: i32,
     pub secret: Option<Secret>,
+    /// Unix timestamp in milliseconds of the last presence update
+    pub last_seen: i64,
 }
 
 #[derive(Insertable)]
@@ -27,6 +29,7 @@ pub struct NewUser {
 pub struct UpdateUser {
     pub name: Option<String>,
     pub online: Option<i32>,
+    pub last_seen: Option<i64>,
 }
 
 impl NewUser {
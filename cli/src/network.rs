@@ -1,13 +1,98 @@
-mod core;
-mod events;
-mod reactor;
+//! Network layer built on the core node
 
-use crate::models::Secret;
-use events::NetworkEventStream;
+use crate::models::{PeerId, Secret};
+use crate::prelude::*;
+use async_std::task::spawn;
+use primitives::LogLevel;
+use tata_core::{
+    decode_peer_id, parse_address, Config, EventReceiver, Node, ProxyConfig, SecretKey,
+};
 
-pub use self::core::{create_keypair, send};
+pub fn start(
+    secret: Secret,
+    name: String,
+    psk: Option<String>,
+    proxy: Option<String>,
+) -> Result<(Node, EventReceiver)> {
+    env_logger::Builder::from_default_env()
+        .filter_level(LogLevel::Debug)
+        .init();
+    let secret_bytes: Vec<u8> = secret.into();
+    let secret = SecretKey::from_bytes(secret_bytes)
+        .map_err(|e| Error::Msg(format!("Invalid secret key: {}", e)))?;
+    let config = Config {
+        psk,
+        proxy: proxy.map(|address| ProxyConfig {
+            address,
+            username: None,
+            password: None,
+        }),
+        ..Config::default()
+    };
+    Node::start(secret, name, config).map_err(Error::from)
+}
+
+/// Sends the message and reports its delivery once it's confirmed
+pub async fn send(node: &Node, peer: String, message: String) -> tata_core::Result<()> {
+    let delivery = node.send(&decode_peer_id(&peer)?, message).await?;
+    let timestamp = delivery.timestamp();
+    println!("Sending message {} to {}", timestamp, peer);
+    spawn(async move {
+        match delivery.await {
+            Ok(delivery) => println!("Message {} to {}: {:?}", timestamp, peer, delivery),
+            Err(e) => println!("Message {} to {}: {}", timestamp, peer, e),
+        }
+    });
+    Ok(())
+}
+
+pub async fn block(node: &Node, peer: &str) -> tata_core::Result<()> {
+    node.block_peer(decode_peer_id(peer)?).await
+}
+
+pub async fn unblock(node: &Node, peer: &str) -> tata_core::Result<()> {
+    node.unblock_peer(decode_peer_id(peer)?).await
+}
+
+pub async fn allow(node: &Node, peer: &str) -> tata_core::Result<()> {
+    node.allow_peer(decode_peer_id(peer)?).await
+}
+
+pub async fn request_contact(node: &Node, peer: &str, note: String) -> tata_core::Result<()> {
+    node.send_contact_request(decode_peer_id(peer)?, note).await
+}
+
+pub async fn accept_contact(node: &Node, peer: &str) -> tata_core::Result<()> {
+    node.accept_contact_request(decode_peer_id(peer)?).await
+}
+
+pub async fn decline_contact(node: &Node, peer: &str) -> tata_core::Result<()> {
+    node.decline_contact_request(decode_peer_id(peer)?).await
+}
+
+pub async fn mailbox(node: &Node, peer: &str, address: &str) -> tata_core::Result<()> {
+    node.set_mailbox(decode_peer_id(peer)?, parse_address(address)?)
+        .await
+}
+
+pub async fn rendezvous(node: &Node, peer: &str, address: &str) -> tata_core::Result<()> {
+    node.set_rendezvous(decode_peer_id(peer)?, parse_address(address)?)
+        .await
+}
+
+pub async fn profile(node: &Node, name: String, status: String) -> tata_core::Result<()> {
+    let status = Some(status).filter(|status| !status.is_empty());
+    node.update_profile(name, status, None).await
+}
+
+pub fn create_psk() -> String {
+    tata_core::generate_psk()
+}
 
-pub fn start(secret: Secret, name: String) -> NetworkEventStream {
-    core::start(secret, name);
-    NetworkEventStream::new()
+pub fn create_keypair() -> (Secret, PeerId) {
+    let (secret, peer_id) = tata_core::generate_secret();
+    (
+        Secret::new(secret.to_bytes().to_vec()),
+        PeerId::new(peer_id.to_base58()),
+    )
 }
@@ -20,7 +20,7 @@ pub async fn onboard_if_necessary(users_repo: &dyn UsersRepo) -> Result<()> {
 }
 
 fn is_initial_run(users_repo: &dyn UsersRepo) -> Result<bool> {
-    Ok(users_repo.local_users()?.len() == 0)
+    Ok(users_repo.local_users()?.is_empty())
 }
 
 fn flush() {
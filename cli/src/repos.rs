@@ -1,3 +1,5 @@
+mod blocked_peers;
 mod users;
 
+pub use blocked_peers::*;
 pub use users::*;
@@ -0,0 +1,41 @@
+use super::super::models::*;
+use crate::schema::blocked_peers::{self, dsl::*};
+use diesel::prelude::*;
+
+/// Persistent storage for blocked peers
+pub trait BlockedPeersRepo {
+    fn list(&self) -> QueryResult<Vec<BlockedPeer>>;
+    fn create(&self, blocked_peer: &NewBlockedPeer) -> QueryResult<()>;
+    fn delete(&self, blocked_peer_id: &PeerId) -> QueryResult<()>;
+}
+
+pub struct BlockedPeersRepoImpl<'a> {
+    conn: &'a SqliteConnection,
+}
+
+impl<'a> BlockedPeersRepo for BlockedPeersRepoImpl<'a> {
+    fn list(&self) -> QueryResult<Vec<BlockedPeer>> {
+        blocked_peers
+            .order(id.desc())
+            .load::<BlockedPeer>(self.conn)
+    }
+
+    fn create(&self, blocked_peer: &NewBlockedPeer) -> QueryResult<()> {
+        diesel::replace_into(blocked_peers::table)
+            .values(blocked_peer)
+            .execute(self.conn)?;
+        Ok(())
+    }
+
+    fn delete(&self, blocked_peer_id: &PeerId) -> QueryResult<()> {
+        diesel::delete(blocked_peers.filter(peer_id.eq(blocked_peer_id))).execute(self.conn)?;
+        Ok(())
+    }
+}
+
+impl<'a> BlockedPeersRepoImpl<'a> {
+    /// Create new instance
+    pub fn new(conn: &'a SqliteConnection) -> BlockedPeersRepoImpl<'a> {
+        Self { conn }
+    }
+}
@@ -5,12 +5,10 @@ use diesel::prelude::*;
 /// Persistent storage for users
 pub trait UsersRepo {
     fn list(&self) -> QueryResult<Vec<User>>;
-    fn count(&self) -> QueryResult<i64>;
     fn local_users(&self) -> QueryResult<Vec<User>>;
-    fn find(&self, user_id: i32) -> QueryResult<Option<User>>;
+    fn find_by_peer_id(&self, user_peer_id: &PeerId) -> QueryResult<Option<User>>;
     fn create(&self, user: &NewUser) -> QueryResult<()>;
     fn update(&self, user_id: i32, user: &UpdateUser) -> QueryResult<()>;
-    fn delete(&self, user_id: i32) -> QueryResult<()>;
 }
 
 pub struct UsersRepoImpl<'a> {
@@ -22,10 +20,6 @@ impl<'a> UsersRepo for UsersRepoImpl<'a> {
         users.order(id.desc()).load::<User>(self.conn)
     }
 
-    fn count(&self) -> QueryResult<i64> {
-        users.count().get_result(self.conn)
-    }
-
     fn local_users(&self) -> QueryResult<Vec<User>> {
         users
             .filter(secret.is_not_null())
@@ -33,8 +27,11 @@ impl<'a> UsersRepo for UsersRepoImpl<'a> {
             .load::<User>(self.conn)
     }
 
-    fn find(&self, user_id: i32) -> QueryResult<Option<User>> {
-        users.find(user_id).first(self.conn).optional()
+    fn find_by_peer_id(&self, user_peer_id: &PeerId) -> QueryResult<Option<User>> {
+        users
+            .filter(peer_id.eq(user_peer_id))
+            .first(self.conn)
+            .optional()
     }
 
     fn create(&self, user: &NewUser) -> QueryResult<()> {
@@ -50,11 +47,6 @@ impl<'a> UsersRepo for UsersRepoImpl<'a> {
             .execute(self.conn)?;
         Ok(())
     }
-
-    fn delete(&self, user_id: i32) -> QueryResult<()> {
-        diesel::delete(users.find(user_id)).execute(self.conn)?;
-        Ok(())
-    }
 }
 
 impl<'a> UsersRepoImpl<'a> {
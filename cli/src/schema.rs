@@ -1,3 +1,10 @@
+table! {
+    blocked_peers (id) {
+        id -> Integer,
+        peer_id -> Text,
+    }
+}
+
 table! {
     users (id) {
         id -> Integer,
@@ -5,5 +12,8 @@ table! {
         peer_id -> Text,
         online -> Integer,
         secret -> Nullable<Binary>,
+        last_seen -> BigInt,
     }
 }
+
+allow_tables_to_appear_in_same_query!(blocked_peers, users,);
@@ -0,0 +1,20 @@
+//! Versions the shared library with the ABI version, so that the hosts built against
+//! different versions of the header can't load an incompatible library.
+use std::env;
+
+include!("src/abi.rs");
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/abi.rs");
+    match env::var("CARGO_CFG_TARGET_OS").as_deref() {
+        Ok("linux") => println!(
+            "cargo:rustc-cdylib-link-arg=-Wl,-soname,libtata_core.so.{}",
+            TATA_ABI_VERSION
+        ),
+        Ok("macos") => println!(
+            "cargo:rustc-cdylib-link-arg=-Wl,-install_name,@rpath/libtata_core.{}.dylib",
+            TATA_ABI_VERSION
+        ),
+        _ => (),
+    }
+}
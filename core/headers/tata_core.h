@@ -3,6 +3,13 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * Version of the C ABI, i.e. of the exported functions and types. It's incremented
+ * on every incompatible change and used as the major version of the shared library
+ * (`libtata_core.so.<version>`).
+ */
+#define TATA_ABI_VERSION 1
+
 /**
  * An enum representing the available verbosity level filters of the logger.
  *
@@ -41,6 +48,54 @@ enum LevelFilter {
 };
 typedef uintptr_t LevelFilter;
 
+/**
+ * Presence status of a peer
+ */
+typedef enum {
+  /**
+   * Peer is online
+   */
+  Online,
+  /**
+   * Peer is online but inactive
+   */
+  Away,
+  /**
+   * Peer is online but doesn't want to be disturbed
+   */
+  Busy,
+  /**
+   * Peer is disconnected
+   */
+  Offline,
+} PresenceStatus;
+
+/**
+ * FFI representation of the outcome of a sent message
+ */
+typedef enum {
+  /**
+   * Sent directly to the peer
+   */
+  Sent,
+  /**
+   * Stored in the mailbox until the peer fetches it
+   */
+  Deposited,
+  /**
+   * Relayed through other peers, the peer confirmed the delivery
+   */
+  Relayed,
+  /**
+   * The message was refused or the peer couldn't be reached
+   */
+  Failed,
+  /**
+   * No confirmation of the delivery in time
+   */
+  Timeout,
+} SendResult;
+
 /**
  * FFI representation of array of bytes
  *
@@ -65,6 +120,86 @@ typedef struct {
  */
 typedef LevelFilter LogLevel;
 
+/**
+ * Accept the contact request received in `ContactRequestReceived` event.
+ *
+ * ## Arguments
+ *
+ * `peer_id` - base58 Libp2p peer_id.
+ */
+bool accept_contact_request(ByteArray peer_id);
+
+/**
+ * Acknowledge that the events are processed by the host, so they are not replayed anymore
+ *
+ * ## Arguments
+ *
+ * `seq` - sequence number of the last processed event
+ */
+bool ack_events(uint64_t seq);
+
+/**
+ * Add the peer to the allowlist (contacts). In contacts only mode
+ * messages are accepted only from allowlisted peers. Peers are also added
+ * to the allowlist once a contact request is accepted.
+ *
+ * Messages can be sent only to contacts.
+ *
+ * The allowlist is kept in memory only, the host is responsible for persisting it
+ * and restoring after `start_network`.
+ *
+ * ## Arguments
+ *
+ * `peer_id` - base58 Libp2p peer_id.
+ */
+bool allow_peer(ByteArray peer_id);
+
+/**
+ * Block the peer. Connections with blocked peers are refused and messages to them are not sent.
+ *
+ * The blocklist is kept in memory only, the host is responsible for persisting it
+ * and restoring after `start_network`.
+ *
+ * ## Arguments
+ *
+ * `peer_id` - base58 Libp2p peer_id.
+ */
+bool block_peer(ByteArray peer_id);
+
+/**
+ * Decline the contact request received in `ContactRequestReceived` event.
+ *
+ * ## Arguments
+ *
+ * `peer_id` - base58 Libp2p peer_id.
+ */
+bool decline_contact_request(ByteArray peer_id);
+
+/**
+ * Remove the peer from the allowlist.
+ *
+ * ## Arguments
+ *
+ * `peer_id` - base58 Libp2p peer_id.
+ */
+bool disallow_peer(ByteArray peer_id);
+
+/**
+ * Query the rendezvous server for the peers in the namespace without registering in it.
+ * Peers are reported with `PeerDiscovered` events.
+ *
+ * ## Arguments
+ *
+ * `namespace` - utf8 namespace
+ */
+bool discover_namespace(ByteArray namespace);
+
+/**
+ * Fetch the messages held by the mailbox. Received messages come as
+ * `ReceivedPlainTextMessage` events.
+ */
+bool fetch_mailbox(void);
+
 /**
  * Free allocated ByteArray memory. This needs to be called e.g. after start function for `secret_array`
  * if you're using the library from C.
@@ -76,6 +211,50 @@ void free_array(ByteArray array);
  */
 KeyPair generate_keypair(void);
 
+/**
+ * Generate a random pre-shared key for a private network. The key is returned as
+ * utf8 hex string to be shared with the members of the network and passed in the `psk`
+ * field of the config to `start_network_with_config`.
+ */
+ByteArray generate_psk(void);
+
+/**
+ * Register in the namespace at the rendezvous server. The registration is refreshed
+ * while the node is running and the peers registered in the namespace are reported with
+ * `PeerDiscovered` events.
+ *
+ * ## Arguments
+ *
+ * `namespace` - utf8 namespace, e.g. a team name
+ */
+bool register_namespace(ByteArray namespace);
+
+/**
+ * Deliver again the events that are not acknowledged yet, starting from the sequence
+ * number. The journal keeps up to `events.journal_capacity` events (see the config),
+ * unacknowledged events evicted from it are replayed as an `EventsDropped` event.
+ *
+ * ## Arguments
+ *
+ * `from_seq` - sequence number of the first event to replay, `0` to replay all
+ */
+bool replay_events(uint64_t from_seq);
+
+/**
+ * Ask the peer to become a contact. Messages can be sent to the peer
+ * right away, they are delivered once the request is accepted.
+ *
+ * The result comes as `ContactRequestAccepted` or `ContactRequestDeclined` event.
+ *
+ * ## Arguments
+ *
+ * `peer_id` - base58 Libp2p peer_id.
+ *
+ * `note` - utf8 introduction shown to the peer
+ */
+bool send_contact_request(ByteArray peer_id,
+                          ByteArray note);
+
 /**
  * Send a message to peer.
  *
@@ -91,6 +270,103 @@ bool send_message(ByteArray to_peer_id,
                   ByteArray message,
                   uint64_t timestamp);
 
+/**
+ * Send a message to peer and report the outcome of the delivery.
+ *
+ * ## Arguments
+ *
+ * `to_peer_id` - base58 Libp2p peer_id. This one is taken from discovery events from `start_network`.
+ *
+ * `message` - utf8 text content of the message
+ *
+ * `timestamp` - unix timestamp, essentially an id of the message
+ *
+ * `timeout_ms` - how long to wait for the delivery confirmation, 0 for the default of 60 seconds
+ *
+ * `callback` - invoked exactly once with the outcome if this function returns `true`,
+ * never invoked otherwise. It's called from a background thread.
+ *
+ * `user_data` - opaque pointer passed back to `callback`
+ */
+bool send_message_with_callback(ByteArray to_peer_id,
+                                ByteArray message,
+                                uint64_t timestamp,
+                                uint64_t timeout_ms,
+                                void (*callback)(SendResult, void *),
+                                void *user_data);
+
+/**
+ * Enable or disable contacts only mode (enabled by default). In this mode messages
+ * are accepted only from peers added with `allow_peer` or accepted contact requests.
+ */
+bool set_contacts_only(bool enabled);
+
+/**
+ * Start or stop discovering peers on the local network (enabled by default). Discovered
+ * peers are reported with `PeerDiscovered` events. `PeerGone` is reported once their
+ * addresses expire, even if discovery is stopped meanwhile.
+ */
+bool set_discovery(bool enabled);
+
+/**
+ * Replace the callback the events are delivered to, e.g. after the UI layer of the host
+ * is restarted. Use `replay_events` to receive the events missed in between.
+ */
+bool set_event_callback(void (*callback)(ByteArray));
+
+/**
+ * Enable or disable forwarding (disabled by default). If enabled, messages to contacts
+ * that can't be reached directly are relayed through connected peers, and messages
+ * of other peers are relayed through this node. Delivery of relayed messages is
+ * confirmed with `RelayedMessageDelivered` event.
+ */
+bool set_forwarding(bool enabled);
+
+/**
+ * Use the mailbox node for messages to offline contacts. Messages that can't be
+ * delivered directly are deposited to the mailbox (`MessageDeposited` event) and delivered
+ * once the recipient fetches them. Registers with the mailbox and fetches the messages
+ * received while you were offline.
+ *
+ * ## Arguments
+ *
+ * `peer_id` - base58 Libp2p peer_id of the mailbox.
+ *
+ * `address` - multiaddress of the mailbox, e.g. `/ip4/1.2.3.4/tcp/4001`
+ */
+bool set_mailbox(ByteArray peer_id,
+                 ByteArray address);
+
+/**
+ * Set your presence status. It's sent to connected contacts, they receive
+ * `PresenceChanged` event. Contacts see you `Offline` once disconnected.
+ *
+ * ## Arguments
+ *
+ * `status` - one of the `PresenceStatus` values. Returns false for any other value.
+ */
+bool set_presence(uint32_t status);
+
+/**
+ * Use the rendezvous server for discovery of peers outside of the local network.
+ * Registrations in the namespaces are sent as soon as the node listens on an address.
+ *
+ * ## Arguments
+ *
+ * `peer_id` - base58 Libp2p peer_id of the rendezvous server.
+ *
+ * `address` - multiaddress of the rendezvous server, e.g. `/ip4/1.2.3.4/tcp/4002`
+ */
+bool set_rendezvous(ByteArray peer_id,
+                    ByteArray address);
+
+/**
+ * Start or stop advertising yourself on the local network with mDNS (visible by default).
+ * Known contacts can still be dialed and other peers can still be discovered while
+ * invisible, see `set_discovery`.
+ */
+bool set_visible(bool visible);
+
 /**
  * Starts the networking process in the background.
  * ## Arguments
@@ -101,7 +377,7 @@ bool send_message(ByteArray to_peer_id,
  * `name` - your name as seen to other peers
  *
  * `callback` - triggered on any event with bytes representing
- * serialized json event (`primitives::PeerEvent`).
+ * serialized json event (`primitives::SequencedEvent`).
  *
  * `enable_logs` - enables or disables logs
  *
@@ -112,3 +388,99 @@ bool start_network(ByteArray secret_array,
                    void (*callback)(ByteArray),
                    bool enable_logs,
                    LogLevel log_level);
+
+/**
+ * Starts the networking process in the background with the custom configuration.
+ * ## Arguments
+ *
+ *
+ * `secret_array` - a Sec256k1 private key bytes
+ *
+ * `name` - your name as seen to other peers
+ *
+ * `config` - utf8 json of the configuration (`tata_core::Config`), e.g.
+ * `{"psk": "<64 hex characters>"}` to join a private network or
+ * `{"proxy": {"address": "127.0.0.1:9050"}}` to dial through a SOCKS5 proxy.
+ * Missing fields take default values.
+ *
+ * `callback` - triggered on any event with bytes representing
+ * serialized event (`primitives::SequencedEvent`) in `events.encoding` of the config,
+ * json by default.
+ *
+ * `enable_logs` - enables or disables logs
+ *
+ * `log_level` - the level of the log
+ */
+bool start_network_with_config(ByteArray secret_array,
+                               ByteArray name,
+                               ByteArray config,
+                               void (*callback)(ByteArray),
+                               bool enable_logs,
+                               LogLevel log_level);
+
+/**
+ * Subscribe to the events matching the filter. The subscriber receives the events in
+ * addition to the callback passed to `start_network`. Returns the subscription id
+ * or `0` on error.
+ *
+ * ## Arguments
+ *
+ * `filter` - utf8 json of the filter, e.g.
+ * `{"kinds": ["ReceivedPlainTextMessage"], "peer_ids": ["<base58 peer id>"]}`.
+ * Missing or empty lists match everything.
+ *
+ * `callback` - triggered on the matching events with bytes representing
+ * serialized event (`primitives::SequencedEvent`) in `events.encoding` of the config,
+ * json by default.
+ */
+uint64_t subscribe(ByteArray filter,
+                   void (*callback)(ByteArray));
+
+/**
+ * Version of the ABI the library is built with. Hosts should refuse to work with
+ * the library if it differs from `TATA_ABI_VERSION` of the header they are built with.
+ */
+uint32_t tata_abi_version(void);
+
+/**
+ * Unblock previously blocked peer.
+ *
+ * ## Arguments
+ *
+ * `peer_id` - base58 Libp2p peer_id.
+ */
+bool unblock_peer(ByteArray peer_id);
+
+/**
+ * Remove the registration in the namespace from the rendezvous server
+ *
+ * ## Arguments
+ *
+ * `namespace` - utf8 namespace passed to `register_namespace`
+ */
+bool unregister_namespace(ByteArray namespace);
+
+/**
+ * Stop delivering the events to the subscriber
+ *
+ * ## Arguments
+ *
+ * `subscription_id` - id returned by `subscribe`
+ */
+bool unsubscribe(uint64_t subscription_id);
+
+/**
+ * Update your profile. The new profile is sent to connected peers (they receive
+ * `ReceivedMetadata` event) and used for all new connections.
+ *
+ * ## Arguments
+ *
+ * `name` - your name as seen to other peers
+ *
+ * `status` - utf8 status text, empty to clear the status
+ *
+ * `avatar_hash` - hash of your avatar image, empty to clear the avatar
+ */
+bool update_profile(ByteArray name,
+                    ByteArray status,
+                    ByteArray avatar_hash);
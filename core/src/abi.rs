@@ -0,0 +1,4 @@
+/// Version of the C ABI, i.e. of the exported functions and types. It's incremented
+/// on every incompatible change and used as the major version of the shared library
+/// (`libtata_core.so.<version>`).
+pub const TATA_ABI_VERSION: u32 = 1;
@@ -0,0 +1,398 @@
+//! Configuration of the network layer
+
+use primitives::{Capability, EventEncoding};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Configuration used to create a swarm
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Pre-shared key of the private network, 64 hex characters or go-libp2p `swarm.key` format.
+    /// Only the nodes with the same key can connect to each other.
+    pub psk: Option<String>,
+    /// SOCKS5 proxy for the outbound connections, e.g. a corporate proxy or Tor.
+    /// Listening is not proxied.
+    pub proxy: Option<ProxyConfig>,
+    /// Transports and listen addresses
+    pub transport: TransportConfig,
+    /// Detection of dead connections with pings
+    pub liveness: LivenessConfig,
+    /// Status text announced to peers
+    pub status: Option<String>,
+    /// Hash of the avatar image announced to peers
+    pub avatar_hash: Option<String>,
+    /// Optional features supported by the host application, announced to peers
+    pub capabilities: Vec<Capability>,
+    /// Limits applied to the data received from peers
+    pub limits: Limits,
+    /// Rate limits and misbehaviour scoring of peers
+    pub scoring: ScoringConfig,
+    /// Mailbox holding messages while the recipients are offline
+    pub mailbox: Option<MailboxConfig>,
+    /// Forwarding of messages through connected peers
+    pub forwarding: ForwardingConfig,
+    /// Local discovery with mDNS
+    pub discovery: DiscoveryConfig,
+    /// Discovery through a rendezvous server
+    pub rendezvous: Option<RendezvousConfig>,
+    /// Delivery of the events to the host
+    pub events: EventsConfig,
+}
+
+/// Delivery of the events to the host
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventsConfig {
+    /// Max total size in bytes of the events waiting for the host, as encoded with
+    /// `encoding`. Further events are dropped and reported with an `EventsDropped` event.
+    pub max_queued_bytes: usize,
+    /// Max number of delivered events kept until the host acknowledges them,
+    /// so that they can be replayed
+    pub journal_capacity: usize,
+    /// Encoding of the events passed to the callbacks: `Json`, `Cbor` or `MessagePack`
+    pub encoding: EventEncoding,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        EventsConfig {
+            max_queued_bytes: 16 * 1024 * 1024,
+            journal_capacity: 1000,
+            encoding: EventEncoding::Json,
+        }
+    }
+}
+
+/// Rendezvous server and the namespaces to register in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RendezvousConfig {
+    /// Base58 peer id of the rendezvous server
+    pub peer_id: String,
+    /// Multiaddress of the rendezvous server
+    pub address: String,
+    /// Namespaces to register in, e.g. team names
+    #[serde(default)]
+    pub namespaces: Vec<String>,
+    /// Time the registration is kept by the server, it's refreshed in half of this time
+    #[serde(default = "default_rendezvous_ttl")]
+    pub ttl_secs: u64,
+    /// Interval of querying the server for the peers in the namespaces
+    #[serde(default = "default_discover_interval")]
+    pub discover_interval_secs: u64,
+}
+
+fn default_rendezvous_ttl() -> u64 {
+    2 * 60 * 60
+}
+
+fn default_discover_interval() -> u64 {
+    60
+}
+
+/// Local discovery with mDNS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiscoveryConfig {
+    /// Advertise ourselves with mDNS. Invisible nodes can still dial known contacts.
+    pub visible: bool,
+    /// Query the peers on the local network with mDNS and report them.
+    /// Independent of `visible`.
+    pub discover: bool,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        DiscoveryConfig {
+            visible: true,
+            discover: true,
+        }
+    }
+}
+
+/// Multi-hop forwarding. If enabled, messages to peers that can't be reached directly
+/// are relayed through connected peers, and messages of other peers are relayed through us.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ForwardingConfig {
+    /// Disabled by default
+    pub enabled: bool,
+    /// Max number of hops a relayed message can make
+    pub max_hops: u8,
+    /// Number of recently seen message ids kept for loop prevention
+    pub seen_capacity: usize,
+}
+
+impl Default for ForwardingConfig {
+    fn default() -> Self {
+        ForwardingConfig {
+            enabled: false,
+            max_hops: 3,
+            seen_capacity: 1024,
+        }
+    }
+}
+
+/// Connected peers are pinged periodically. The round-trip time is reported with
+/// `PeerRtt` events and the connection is closed after `max_failures` failed pings in a row.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LivenessConfig {
+    /// Interval between pings
+    pub interval_secs: u64,
+    /// Time to wait for a pong
+    pub timeout_secs: u64,
+    /// Number of consecutive failures after which the connection is closed
+    pub max_failures: u32,
+}
+
+impl Default for LivenessConfig {
+    fn default() -> Self {
+        LivenessConfig {
+            interval_secs: 15,
+            timeout_secs: 20,
+            max_failures: 3,
+        }
+    }
+}
+
+/// Transports enabled for the node and the addresses it listens on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransportConfig {
+    /// TCP, e.g. `/ip4/0.0.0.0/tcp/0`
+    pub tcp: bool,
+    /// WebSocket over TCP, e.g. `/ip4/0.0.0.0/tcp/0/ws`. Lets browser clients reach the node.
+    /// Off by default.
+    pub websocket: bool,
+    /// Unix domain sockets for the same host IPC, e.g. `/unix/tmp/tata.sock`. Unix only.
+    pub unix: bool,
+    /// In-memory transport for tests, e.g. `/memory/1234`
+    pub memory: bool,
+    /// Multiaddresses to listen on. Every address must be supported by one of the
+    /// enabled transports.
+    pub listen_addresses: Vec<String>,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        TransportConfig {
+            tcp: true,
+            websocket: false,
+            unix: false,
+            memory: false,
+            listen_addresses: vec!["/ip4/0.0.0.0/tcp/0".to_string()],
+        }
+    }
+}
+
+impl TransportConfig {
+    /// Default transports of the servers, which listen on the configured `listen_address` only
+    fn server() -> Self {
+        TransportConfig {
+            listen_addresses: vec![],
+            ..TransportConfig::default()
+        }
+    }
+}
+
+/// SOCKS5 proxy the outbound connections are dialed through
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    /// Address of the proxy, e.g. `127.0.0.1:9050`
+    pub address: String,
+    /// Username for the username/password authentication
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Password for the username/password authentication
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// Address of the mailbox node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailboxConfig {
+    /// Base58 peer id of the mailbox
+    pub peer_id: String,
+    /// Multiaddress of the mailbox
+    pub address: String,
+}
+
+/// Limits protecting the node from hostile or broken peers.
+/// Peers violating them are disconnected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    /// Max size of a single frame in bytes (excluding the length prefix)
+    pub max_frame_size: usize,
+    /// Max length of the peer name in bytes
+    pub max_name_length: usize,
+    /// Max length of the client version in bytes
+    pub max_client_version_length: usize,
+    /// Max length of the avatar hash in bytes
+    pub max_avatar_hash_length: usize,
+    /// Max length of the status text in bytes
+    pub max_status_length: usize,
+    /// Max length of the message text in bytes
+    pub max_message_length: usize,
+    /// Max duration of the protocol handshake in milliseconds
+    pub handshake_timeout_ms: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_frame_size: 1024 * 1024,
+            max_name_length: 256,
+            max_client_version_length: 64,
+            max_avatar_hash_length: 128,
+            max_status_length: 1024,
+            max_message_length: 64 * 1024,
+            handshake_timeout_ms: 5_000,
+        }
+    }
+}
+
+impl Limits {
+    /// Max duration of the protocol handshake
+    pub fn handshake_timeout(&self) -> Duration {
+        Duration::from_millis(self.handshake_timeout_ms)
+    }
+}
+
+/// Rate limits and misbehaviour scoring of peers.
+///
+/// Every peer starts with a score of `0`. Misbehaviour lowers the score by the corresponding
+/// penalty, and the score recovers by `recovery_per_sec` every second, up to `0`.
+/// Peers with a score at or below `ban_threshold` are banned for `ban_duration_secs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoringConfig {
+    /// Sustained number of messages per second accepted from a peer
+    pub messages_per_sec: u32,
+    /// Number of messages a peer can send in a burst above the sustained rate
+    pub message_burst: u32,
+    /// Number of inbound connections from a peer allowed per minute
+    pub connections_per_min: u32,
+    /// Penalty for a message that couldn't be parsed
+    pub malformed_message_penalty: i32,
+    /// Penalty for exceeding the limits or violating the protocol
+    pub protocol_violation_penalty: i32,
+    /// Penalty for every message exceeding the rate limit
+    pub message_flood_penalty: i32,
+    /// Penalty for every connection exceeding the rate limit
+    pub connection_flood_penalty: i32,
+    /// Score recovered every second
+    pub recovery_per_sec: i32,
+    /// Score at which the peer is banned
+    pub ban_threshold: i32,
+    /// Duration of the ban in seconds
+    pub ban_duration_secs: u64,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        ScoringConfig {
+            messages_per_sec: 5,
+            message_burst: 20,
+            connections_per_min: 10,
+            malformed_message_penalty: 10,
+            protocol_violation_penalty: 50,
+            message_flood_penalty: 5,
+            connection_flood_penalty: 20,
+            recovery_per_sec: 1,
+            ban_threshold: -100,
+            ban_duration_secs: 600,
+        }
+    }
+}
+
+impl ScoringConfig {
+    /// Duration of the ban
+    pub fn ban_duration(&self) -> Duration {
+        Duration::from_secs(self.ban_duration_secs)
+    }
+}
+
+/// Configuration of the mailbox node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MailboxServerConfig {
+    /// Multiaddress to listen on
+    pub listen_address: String,
+    /// Pre-shared key of the private network, see [Config::psk](struct.Config.html#structfield.psk)
+    pub psk: Option<String>,
+    /// Enabled transports. The server listens on `listen_address` and `transport.listen_addresses`
+    pub transport: TransportConfig,
+    /// File the envelopes are persisted to, one json record per line. The envelopes are
+    /// kept in memory only if `None`
+    pub store_path: Option<PathBuf>,
+    /// Base58 peer ids allowed to register with the mailbox. Any peer can register if empty
+    pub allowed_peers: Vec<String>,
+    /// Max number of envelopes held for a single peer
+    pub max_envelopes_per_peer: usize,
+    /// Max number of envelopes held for a single peer from the same sender, so that
+    /// a single sender can't fill the mailbox of the peer
+    pub max_envelopes_per_sender: usize,
+    /// Max size of the envelope payload in bytes
+    pub max_envelope_size: usize,
+    /// Max size of a request or response in bytes
+    pub max_frame_size: usize,
+}
+
+impl Default for MailboxServerConfig {
+    fn default() -> Self {
+        MailboxServerConfig {
+            listen_address: "/ip4/0.0.0.0/tcp/4001".to_string(),
+            psk: None,
+            transport: TransportConfig::server(),
+            store_path: None,
+            allowed_peers: vec![],
+            max_envelopes_per_peer: 1000,
+            max_envelopes_per_sender: 100,
+            max_envelope_size: 128 * 1024,
+            max_frame_size: 4 * 1024 * 1024,
+        }
+    }
+}
+
+/// Configuration of the rendezvous server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RendezvousServerConfig {
+    /// Multiaddress to listen on
+    pub listen_address: String,
+    /// Pre-shared key of the private network, see [Config::psk](struct.Config.html#structfield.psk)
+    pub psk: Option<String>,
+    /// Enabled transports. The server listens on `listen_address` and `transport.listen_addresses`
+    pub transport: TransportConfig,
+    /// Max time a registration is kept without being refreshed, in seconds
+    pub max_ttl_secs: u64,
+    /// Max length of the namespace in bytes
+    pub max_namespace_length: usize,
+    /// Max number of peers registered in a namespace
+    pub max_registrations_per_namespace: usize,
+    /// Max number of namespaces a peer is registered in
+    pub max_namespaces_per_peer: usize,
+    /// Max number of addresses in a registration
+    pub max_addresses: usize,
+    /// Max size of a request or response in bytes
+    pub max_frame_size: usize,
+}
+
+impl Default for RendezvousServerConfig {
+    fn default() -> Self {
+        RendezvousServerConfig {
+            listen_address: "/ip4/0.0.0.0/tcp/4002".to_string(),
+            psk: None,
+            transport: TransportConfig::server(),
+            max_ttl_secs: 72 * 60 * 60,
+            max_namespace_length: 255,
+            max_registrations_per_namespace: 1000,
+            max_namespaces_per_peer: 16,
+            max_addresses: 16,
+            max_frame_size: 1024 * 1024,
+        }
+    }
+}
@@ -29,11 +29,22 @@ pub enum Error {
     /// Generic I/O error
     #[display(fmt = "Io error: {}", _0)]
     Io(std::io::Error),
+    /// Peer exceeded one of the configured limits
+    #[display(fmt = "Limit exceeded: {}", _0)]
+    Limit(LimitExceeded),
+    /// Operation timed out
+    #[display(fmt = "Timeout: {}", _0)]
+    Timeout(async_std::future::TimeoutError),
     /// Generic error
     #[display(fmt = "{}", _0)]
     Msg(String),
 }
 
+/// Description of the limit violated by a peer
+#[derive(Debug, Display)]
+#[display(fmt = "{}", _0)]
+pub struct LimitExceeded(pub String);
+
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
@@ -42,6 +53,8 @@ impl std::error::Error for Error {
             Error::Base58(e) => Some(e),
             Error::PeerId(_) => None,
             Error::Io(e) => Some(e),
+            Error::Limit(_) => None,
+            Error::Timeout(e) => Some(e),
             Error::Json(e) => Some(e),
             Error::Utf8(e) => Some(e),
             Error::Msg(_) => None,
@@ -0,0 +1,208 @@
+//! Queue of the events from the swarm to the host.
+//!
+//! The queue holds up to `max_bytes` of events as encoded for the host, so a slow host
+//! doesn't lose events on short bursts. When the host falls further behind, new events
+//! are dropped and an `EventsDropped` marker with the number of dropped events takes
+//! their place, so the host knows to resync.
+use futures::Stream;
+use libp2p::PeerId;
+use primitives::{Event, EventEncoding, PeerEvent};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Create the sending and the receiving halves of the queue
+pub fn channel(
+    max_bytes: usize,
+    encoding: EventEncoding,
+    local_peer_id: &PeerId,
+) -> (EventSender, EventReceiver) {
+    new_channel(max_bytes, encoding, local_peer_id.to_base58())
+}
+
+fn new_channel(
+    max_bytes: usize,
+    encoding: EventEncoding,
+    local_peer_id: String,
+) -> (EventSender, EventReceiver) {
+    let queue = Arc::new(Mutex::new(Queue {
+        events: VecDeque::new(),
+        size: 0,
+        max_bytes,
+        encoding,
+        local_peer_id,
+        waker: None,
+        closed: false,
+    }));
+    (
+        EventSender {
+            queue: queue.clone(),
+        },
+        EventReceiver { queue },
+    )
+}
+
+struct Queue {
+    /// Events and their encoded sizes
+    events: VecDeque<(PeerEvent, usize)>,
+    /// Encoded size of the queued events
+    size: usize,
+    max_bytes: usize,
+    encoding: EventEncoding,
+    local_peer_id: String,
+    /// Waker of the task polling the receiver
+    waker: Option<Waker>,
+    /// Sender is dropped
+    closed: bool,
+}
+
+impl Queue {
+    fn push(&mut self, event: PeerEvent) {
+        let size = match self.encoding.encode(&event) {
+            Ok(bytes) => bytes.len(),
+            Err(e) => {
+                log::error!("Error encoding event: {}", e);
+                return;
+            }
+        };
+        if self.size.saturating_add(size) <= self.max_bytes {
+            self.size += size;
+            self.events.push_back((event, size));
+        } else if let Some((
+            PeerEvent {
+                event: Event::EventsDropped { count },
+                ..
+            },
+            _,
+        )) = self.events.back_mut()
+        {
+            *count += 1;
+        } else {
+            log::warn!("Event queue is full, dropping events");
+            // The marker is small, it's queued even when it doesn't fit
+            self.events.push_back((
+                PeerEvent {
+                    peer_id: self.local_peer_id.clone(),
+                    event: Event::EventsDropped { count: 1 },
+                },
+                0,
+            ));
+        }
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Sending half of the event queue
+pub struct EventSender {
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl EventSender {
+    /// Queue the event for the host. Never blocks.
+    pub fn send(&self, event: PeerEvent) {
+        match self.queue.lock() {
+            Ok(mut queue) => queue.push(event),
+            Err(e) => log::error!("Error locking event queue: {}", e),
+        }
+    }
+
+    /// Create another queue with the same size limit and encoding
+    pub fn channel(&self) -> (EventSender, EventReceiver) {
+        let queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        new_channel(queue.max_bytes, queue.encoding, queue.local_peer_id.clone())
+    }
+
+    /// The receiver is dropped
+    pub fn is_closed(&self) -> bool {
+        Arc::strong_count(&self.queue) == 1
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.closed = true;
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Receiving half of the event queue, a stream of events
+pub struct EventReceiver {
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl Stream for EventReceiver {
+    type Item = PeerEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<PeerEvent>> {
+        let mut queue = match self.queue.lock() {
+            Ok(queue) => queue,
+            Err(e) => {
+                log::error!("Error locking event queue: {}", e);
+                return Poll::Ready(None);
+            }
+        };
+        if let Some((event, size)) = queue.events.pop_front() {
+            queue.size -= size;
+            return Poll::Ready(Some(event));
+        }
+        if queue.closed {
+            return Poll::Ready(None);
+        }
+        queue.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    fn event(address: &str) -> PeerEvent {
+        PeerEvent {
+            peer_id: "peer".to_string(),
+            event: Event::ListenAddressAdded {
+                address: address.to_string(),
+            },
+        }
+    }
+
+    fn dropped(event: &PeerEvent) -> Option<u64> {
+        match event.event {
+            Event::EventsDropped { count } => Some(count),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn events_over_the_size_are_dropped() {
+        let size = EventEncoding::Json.encode(&event("a")).unwrap().len();
+        let (sender, mut receiver) = channel(2 * size, EventEncoding::Json, &PeerId::random());
+        for _ in 0..4 {
+            sender.send(event("a"));
+        }
+        // Receiving an event makes room for another one
+        block_on(receiver.next()).unwrap();
+        sender.send(event("a"));
+        drop(sender);
+
+        let events = block_on(receiver.collect::<Vec<_>>());
+        let dropped = events.iter().map(dropped).collect::<Vec<_>>();
+        assert_eq!(dropped, vec![None, Some(2), None]);
+    }
+
+    #[test]
+    fn event_larger_than_the_queue_is_dropped() {
+        let (sender, mut receiver) = channel(64, EventEncoding::Json, &PeerId::random());
+        sender.send(event(&"a".repeat(100)));
+        assert_eq!(dropped(&block_on(receiver.next()).unwrap()), Some(1));
+    }
+}
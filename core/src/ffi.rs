@@ -1,21 +1,42 @@
 //! Exports for `C` library
 
-use async_std::{future::poll_fn, task::Poll};
+use async_std::{
+    future::poll_fn,
+    task::{self, Poll},
+};
 use futures::{channel::mpsc::Sender, stream::StreamExt};
+use libp2p::PeerId;
 use once_cell::sync::OnceCell;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::journal::Journal;
+use crate::node::{Command, Delivery, DeliveryError, Node, DEFAULT_DELIVERY_TIMEOUT};
+use crate::subscriptions::{EventFilter, Subscriptions};
+use crate::transport::parse_address;
+use crate::utils::decode_peer_id;
+use crate::Config;
 
 use primitives::{
-    ffi::{ByteArray, KeyPair},
-    LogLevel, PlainTextMessage,
+    ffi::{ByteArray, KeyPair, SendResult},
+    EventEncoding, LogLevel, PlainTextMessage, PresenceStatus, SequencedEvent,
 };
 
+static NODE: OnceCell<Node> = OnceCell::new();
 static EVENTS_SENDER: OnceCell<Mutex<Sender<IncomingEvent>>> = OnceCell::new();
+static NEXT_SUBSCRIPTION_ID: AtomicU64 = AtomicU64::new(1);
 const CHANNEL_BUFFER_SIZE: usize = 10;
 
+/// Commands of the host handled by the FFI layer, the rest are executed by the node
 enum IncomingEvent {
-    Message(PlainTextMessage),
+    SetCallback(extern "C" fn(ByteArray)),
+    ReplayEvents(u64),
+    AckEvents(u64),
+    Subscribe(u64, EventFilter, extern "C" fn(ByteArray)),
+    Unsubscribe(u64),
 }
 
 /// Starts the networking process in the background.
@@ -27,7 +48,7 @@ enum IncomingEvent {
 /// `name` - your name as seen to other peers
 ///
 /// `callback` - triggered on any event with bytes representing
-/// serialized json event (`primitives::PeerEvent`).
+/// serialized json event (`primitives::SequencedEvent`).
 ///
 /// `enable_logs` - enables or disables logs
 ///
@@ -40,6 +61,74 @@ pub extern "C" fn start_network(
     enable_logs: bool,
     log_level: LogLevel,
 ) -> bool {
+    start(
+        secret_array,
+        name,
+        Config::default(),
+        callback,
+        enable_logs,
+        log_level,
+    )
+}
+
+/// Starts the networking process in the background with the custom configuration.
+/// ## Arguments
+///
+///
+/// `secret_array` - a Sec256k1 private key bytes
+///
+/// `name` - your name as seen to other peers
+///
+/// `config` - utf8 json of the configuration (`tata_core::Config`), e.g.
+/// `{"psk": "<64 hex characters>"}` to join a private network or
+/// `{"proxy": {"address": "127.0.0.1:9050"}}` to dial through a SOCKS5 proxy.
+/// Missing fields take default values.
+///
+/// `callback` - triggered on any event with bytes representing
+/// serialized event (`primitives::SequencedEvent`) in `events.encoding` of the config,
+/// json by default.
+///
+/// `enable_logs` - enables or disables logs
+///
+/// `log_level` - the level of the log
+#[no_mangle]
+pub extern "C" fn start_network_with_config(
+    secret_array: ByteArray,
+    name: ByteArray,
+    config: ByteArray,
+    callback: extern "C" fn(ByteArray),
+    enable_logs: bool,
+    log_level: LogLevel,
+) -> bool {
+    let config: String = match config.try_into() {
+        Ok(config) => config,
+        Err(e) => {
+            log::error!("Error converting `config` bytearray: {}", e);
+            return false;
+        }
+    };
+    let config = match serde_json::from_str(&config) {
+        Ok(config) => config,
+        Err(e) => {
+            log::error!("Error parsing config: {}", e);
+            return false;
+        }
+    };
+    start(secret_array, name, config, callback, enable_logs, log_level)
+}
+
+fn start(
+    secret_array: ByteArray,
+    name: ByteArray,
+    config: Config,
+    mut callback: extern "C" fn(ByteArray),
+    enable_logs: bool,
+    log_level: LogLevel,
+) -> bool {
+    if NODE.get().is_some() {
+        log::error!("Network is already started");
+        return false;
+    }
     let name: Result<String, _> = name.try_into();
     let name = match name {
         Ok(name) => name,
@@ -62,13 +151,22 @@ pub extern "C" fn start_network(
             .init();
     }
     log::debug!("Starting network layer");
-    let (mut swarm, mut out_events) = match crate::create_swarm(secret, name) {
+    let journal_capacity = config.events.journal_capacity;
+    let encoding = config.events.encoding;
+    let mut subscriptions = Subscriptions::default();
+    let (node, mut out_events) = match Node::start(secret, name, config) {
         Ok(x) => x,
         Err(e) => {
-            log::error!("Error creating swarm: {}", e);
+            log::error!("Error starting node: {}", e);
             return false;
         }
     };
+    let mut journal = Journal::new(journal_capacity, node.local_peer_id().to_base58());
+    // Dropping the node of a concurrent start stops it
+    if let Err(_node) = NODE.set(node) {
+        log::error!("Network is already started");
+        return false;
+    }
     let (in_events_tx, mut in_events_rx) = futures::channel::mpsc::channel(CHANNEL_BUFFER_SIZE);
     if let Err(_e) = EVENTS_SENDER.set(Mutex::new(in_events_tx)) {
         log::error!("Error setting global in_events_tx");
@@ -78,29 +176,37 @@ pub extern "C" fn start_network(
         loop {
             match out_events.poll_next_unpin(cx) {
                 Poll::Ready(Some(event)) => {
-                    match event.try_into() {
-                        Ok(bytes) => callback(bytes),
-                        Err(e) => log::error!("Error serializing out event: {}", e),
-                    };
+                    let event = journal.push(event);
+                    for subscriber in subscriptions.matching(&event.event) {
+                        deliver(*subscriber, &event, encoding);
+                    }
+                    deliver(callback, &event, encoding);
                 }
-                _ => break,
+                Poll::Ready(None) => {
+                    log::error!("Node is stopped");
+                    return Poll::Ready(());
+                }
+                Poll::Pending => break,
             }
         }
         loop {
             match in_events_rx.poll_next_unpin(cx) {
-                Poll::Ready(Some(IncomingEvent::Message(message))) => {
-                    if let Err(e) = swarm.private_chat.send_message(message) {
-                        log::error!("Error sending message: {}", e);
-                    };
+                Poll::Ready(Some(IncomingEvent::SetCallback(new_callback))) => {
+                    callback = new_callback;
                 }
-                _ => break,
-            }
-        }
-        loop {
-            match swarm.poll_next_unpin(cx) {
-                Poll::Ready(None) => {
-                    log::error!("Swarm is finished");
-                    return Poll::Ready(());
+                Poll::Ready(Some(IncomingEvent::ReplayEvents(from_seq))) => {
+                    for event in journal.replay(from_seq) {
+                        deliver(callback, &event, encoding);
+                    }
+                }
+                Poll::Ready(Some(IncomingEvent::AckEvents(seq))) => journal.ack(seq),
+                Poll::Ready(Some(IncomingEvent::Subscribe(id, filter, subscriber))) => {
+                    subscriptions.subscribe(id, filter, subscriber);
+                }
+                Poll::Ready(Some(IncomingEvent::Unsubscribe(id))) => {
+                    if !subscriptions.unsubscribe(id) {
+                        log::error!("Subscription {} is not found", id);
+                    }
                 }
                 _ => break,
             }
@@ -111,6 +217,96 @@ pub extern "C" fn start_network(
     true
 }
 
+fn deliver(callback: extern "C" fn(ByteArray), event: &SequencedEvent, encoding: EventEncoding) {
+    match encoding.encode(event) {
+        Ok(bytes) => callback(bytes.into()),
+        Err(e) => log::error!("Error serializing out event: {}", e),
+    };
+}
+
+/// Replace the callback the events are delivered to, e.g. after the UI layer of the host
+/// is restarted. Use `replay_events` to receive the events missed in between.
+#[no_mangle]
+pub extern "C" fn set_event_callback(callback: extern "C" fn(ByteArray)) -> bool {
+    send_incoming_event(IncomingEvent::SetCallback(callback))
+}
+
+/// Deliver again the events that are not acknowledged yet, starting from the sequence
+/// number. The journal keeps up to `events.journal_capacity` events (see the config),
+/// unacknowledged events evicted from it are replayed as an `EventsDropped` event.
+///
+/// ## Arguments
+///
+/// `from_seq` - sequence number of the first event to replay, `0` to replay all
+#[no_mangle]
+pub extern "C" fn replay_events(from_seq: u64) -> bool {
+    send_incoming_event(IncomingEvent::ReplayEvents(from_seq))
+}
+
+/// Acknowledge that the events are processed by the host, so they are not replayed anymore
+///
+/// ## Arguments
+///
+/// `seq` - sequence number of the last processed event
+#[no_mangle]
+pub extern "C" fn ack_events(seq: u64) -> bool {
+    send_incoming_event(IncomingEvent::AckEvents(seq))
+}
+
+/// Subscribe to the events matching the filter. The subscriber receives the events in
+/// addition to the callback passed to `start_network`. Returns the subscription id
+/// or `0` on error.
+///
+/// ## Arguments
+///
+/// `filter` - utf8 json of the filter, e.g.
+/// `{"kinds": ["ReceivedPlainTextMessage"], "peer_ids": ["<base58 peer id>"]}`.
+/// Missing or empty lists match everything.
+///
+/// `callback` - triggered on the matching events with bytes representing
+/// serialized event (`primitives::SequencedEvent`) in `events.encoding` of the config,
+/// json by default.
+#[no_mangle]
+pub extern "C" fn subscribe(filter: ByteArray, callback: extern "C" fn(ByteArray)) -> u64 {
+    let filter: String = match filter.try_into() {
+        Ok(filter) => filter,
+        Err(e) => {
+            log::error!("Error converting `filter` bytearray: {}", e);
+            return 0;
+        }
+    };
+    let filter = match serde_json::from_str(&filter) {
+        Ok(filter) => filter,
+        Err(e) => {
+            log::error!("Error parsing filter: {}", e);
+            return 0;
+        }
+    };
+    let id = NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed);
+    if send_incoming_event(IncomingEvent::Subscribe(id, filter, callback)) {
+        id
+    } else {
+        0
+    }
+}
+
+/// Stop delivering the events to the subscriber
+///
+/// ## Arguments
+///
+/// `subscription_id` - id returned by `subscribe`
+#[no_mangle]
+pub extern "C" fn unsubscribe(subscription_id: u64) -> bool {
+    send_incoming_event(IncomingEvent::Unsubscribe(subscription_id))
+}
+
+/// Version of the ABI the library is built with. Hosts should refuse to work with
+/// the library if it differs from `TATA_ABI_VERSION` of the header they are built with.
+#[no_mangle]
+pub extern "C" fn tata_abi_version() -> u32 {
+    crate::TATA_ABI_VERSION
+}
+
 /// Free allocated ByteArray memory. This needs to be called e.g. after start function for `secret_array`
 /// if you're using the library from C.
 #[no_mangle]
@@ -131,35 +327,224 @@ pub extern "C" fn free_array(array: ByteArray) {
 /// `timestamp` - unix timestamp, essentially an id of the message
 #[no_mangle]
 pub extern "C" fn send_message(to_peer_id: ByteArray, message: ByteArray, timestamp: u64) -> bool {
-    if let Some(sender_mutex) = EVENTS_SENDER.get() {
-        if let Ok(mut sender) = sender_mutex.lock() {
-            let to = match to_peer_id.try_into() {
-                Ok(to) => to,
-                Err(e) => {
-                    log::error!("Error converting `peer_id` bytearray: {}", e);
-                    return false;
-                }
-            };
-            let text = match message.try_into() {
-                Ok(text) => text,
-                Err(e) => {
-                    log::error!("Error converting `message` bytearray: {}", e);
-                    return false;
-                }
-            };
-            let message = PlainTextMessage {
-                to,
-                timestamp,
-                text,
-            };
-            if let Err(e) = sender.try_send(IncomingEvent::Message(message)) {
-                log::error!("Error sending message to tx: {}", e);
+    let to = match to_peer_id.try_into() {
+        Ok(to) => to,
+        Err(e) => {
+            log::error!("Error converting `peer_id` bytearray: {}", e);
+            return false;
+        }
+    };
+    let text = match message.try_into() {
+        Ok(text) => text,
+        Err(e) => {
+            log::error!("Error converting `message` bytearray: {}", e);
+            return false;
+        }
+    };
+    let message = PlainTextMessage {
+        to,
+        timestamp,
+        text,
+    };
+    send_command(Command::Send {
+        message,
+        delivery: None,
+    })
+}
+
+/// Send a message to peer and report the outcome of the delivery.
+///
+/// ## Arguments
+///
+/// `to_peer_id` - base58 Libp2p peer_id. This one is taken from discovery events from `start_network`.
+///
+/// `message` - utf8 text content of the message
+///
+/// `timestamp` - unix timestamp, essentially an id of the message
+///
+/// `timeout_ms` - how long to wait for the delivery confirmation, 0 for the default of 60 seconds
+///
+/// `callback` - invoked exactly once with the outcome if this function returns `true`,
+/// never invoked otherwise. It's called from a background thread.
+///
+/// `user_data` - opaque pointer passed back to `callback`
+#[no_mangle]
+pub extern "C" fn send_message_with_callback(
+    to_peer_id: ByteArray,
+    message: ByteArray,
+    timestamp: u64,
+    timeout_ms: u64,
+    callback: extern "C" fn(SendResult, *mut c_void),
+    user_data: *mut c_void,
+) -> bool {
+    let to = match to_peer_id.try_into() {
+        Ok(to) => to,
+        Err(e) => {
+            log::error!("Error converting `peer_id` bytearray: {}", e);
+            return false;
+        }
+    };
+    let text = match message.try_into() {
+        Ok(text) => text,
+        Err(e) => {
+            log::error!("Error converting `message` bytearray: {}", e);
+            return false;
+        }
+    };
+    let message = PlainTextMessage {
+        to,
+        timestamp,
+        text,
+    };
+    let timeout = match timeout_ms {
+        0 => DEFAULT_DELIVERY_TIMEOUT,
+        ms => Duration::from_millis(ms),
+    };
+    let node = match NODE.get() {
+        Some(node) => node,
+        None => {
+            log::error!("Node is not started");
+            return false;
+        }
+    };
+    let delivery = match node.try_send_message(message, timeout) {
+        Some(delivery) => delivery,
+        None => return false,
+    };
+    let user_data = UserData(user_data);
+    task::spawn(async move {
+        let result = match delivery.await {
+            Ok(Delivery::Sent) => SendResult::Sent,
+            Ok(Delivery::Deposited) => SendResult::Deposited,
+            Ok(Delivery::Relayed) => SendResult::Relayed,
+            Err(DeliveryError::Failed(e)) => {
+                log::debug!("Message {} was not delivered: {}", timestamp, e);
+                SendResult::Failed
             }
-            return true;
+            Err(DeliveryError::Timeout) => SendResult::Timeout,
+        };
+        callback(result, user_data.0);
+    });
+    true
+}
+
+/// Block the peer. Connections with blocked peers are refused and messages to them are not sent.
+///
+/// The blocklist is kept in memory only, the host is responsible for persisting it
+/// and restoring after `start_network`.
+///
+/// ## Arguments
+///
+/// `peer_id` - base58 Libp2p peer_id.
+#[no_mangle]
+pub extern "C" fn block_peer(peer_id: ByteArray) -> bool {
+    match parse_peer_id(peer_id) {
+        Some(peer_id) => send_command(Command::BlockPeer(peer_id)),
+        None => false,
+    }
+}
+
+/// Unblock previously blocked peer.
+///
+/// ## Arguments
+///
+/// `peer_id` - base58 Libp2p peer_id.
+#[no_mangle]
+pub extern "C" fn unblock_peer(peer_id: ByteArray) -> bool {
+    match parse_peer_id(peer_id) {
+        Some(peer_id) => send_command(Command::UnblockPeer(peer_id)),
+        None => false,
+    }
+}
+
+/// Add the peer to the allowlist (contacts). In contacts only mode
+/// messages are accepted only from allowlisted peers. Peers are also added
+/// to the allowlist once a contact request is accepted.
+///
+/// Messages can be sent only to contacts.
+///
+/// The allowlist is kept in memory only, the host is responsible for persisting it
+/// and restoring after `start_network`.
+///
+/// ## Arguments
+///
+/// `peer_id` - base58 Libp2p peer_id.
+#[no_mangle]
+pub extern "C" fn allow_peer(peer_id: ByteArray) -> bool {
+    match parse_peer_id(peer_id) {
+        Some(peer_id) => send_command(Command::AllowPeer(peer_id)),
+        None => false,
+    }
+}
+
+/// Remove the peer from the allowlist.
+///
+/// ## Arguments
+///
+/// `peer_id` - base58 Libp2p peer_id.
+#[no_mangle]
+pub extern "C" fn disallow_peer(peer_id: ByteArray) -> bool {
+    match parse_peer_id(peer_id) {
+        Some(peer_id) => send_command(Command::DisallowPeer(peer_id)),
+        None => false,
+    }
+}
+
+/// Enable or disable contacts only mode (enabled by default). In this mode messages
+/// are accepted only from peers added with `allow_peer` or accepted contact requests.
+#[no_mangle]
+pub extern "C" fn set_contacts_only(enabled: bool) -> bool {
+    send_command(Command::SetContactsOnly(enabled))
+}
+
+/// Set your presence status. It's sent to connected contacts, they receive
+/// `PresenceChanged` event. Contacts see you `Offline` once disconnected.
+///
+/// ## Arguments
+///
+/// `status` - one of the `PresenceStatus` values. Returns false for any other value.
+#[no_mangle]
+pub extern "C" fn set_presence(status: u32) -> bool {
+    match PresenceStatus::try_from(status) {
+        Ok(status) => send_command(Command::SetPresence(status)),
+        Err(e) => {
+            log::error!("{}", e);
+            false
         }
     }
-    log::error!("Couldn't extract swarm from static cell");
-    false
+}
+
+/// Enable or disable forwarding (disabled by default). If enabled, messages to contacts
+/// that can't be reached directly are relayed through connected peers, and messages
+/// of other peers are relayed through this node. Delivery of relayed messages is
+/// confirmed with `RelayedMessageDelivered` event.
+#[no_mangle]
+pub extern "C" fn set_forwarding(enabled: bool) -> bool {
+    send_command(Command::SetForwarding(enabled))
+}
+
+/// Start or stop advertising yourself on the local network with mDNS (visible by default).
+/// Known contacts can still be dialed and other peers can still be discovered while
+/// invisible, see `set_discovery`.
+#[no_mangle]
+pub extern "C" fn set_visible(visible: bool) -> bool {
+    send_command(Command::SetVisible(visible))
+}
+
+/// Start or stop discovering peers on the local network (enabled by default). Discovered
+/// peers are reported with `PeerDiscovered` events. `PeerGone` is reported once their
+/// addresses expire, even if discovery is stopped meanwhile.
+#[no_mangle]
+pub extern "C" fn set_discovery(enabled: bool) -> bool {
+    send_command(Command::SetDiscovery(enabled))
+}
+
+/// Generate a random pre-shared key for a private network. The key is returned as
+/// utf8 hex string to be shared with the members of the network and passed in the `psk`
+/// field of the config to `start_network_with_config`.
+#[no_mangle]
+pub extern "C" fn generate_psk() -> ByteArray {
+    crate::transport::generate_psk().into()
 }
 
 /// Generate secret keypair (to derive PeerId, i.e. p2p identity)
@@ -173,3 +558,267 @@ pub extern "C" fn generate_keypair() -> KeyPair {
         peer_id: peer_id_bytes.into(),
     }
 }
+
+/// Ask the peer to become a contact. Messages can be sent to the peer
+/// right away, they are delivered once the request is accepted.
+///
+/// The result comes as `ContactRequestAccepted` or `ContactRequestDeclined` event.
+///
+/// ## Arguments
+///
+/// `peer_id` - base58 Libp2p peer_id.
+///
+/// `note` - utf8 introduction shown to the peer
+#[no_mangle]
+pub extern "C" fn send_contact_request(peer_id: ByteArray, note: ByteArray) -> bool {
+    let peer_id = match parse_peer_id(peer_id) {
+        Some(peer_id) => peer_id,
+        None => return false,
+    };
+    let note = match note.try_into() {
+        Ok(note) => note,
+        Err(e) => {
+            log::error!("Error converting `note` bytearray: {}", e);
+            return false;
+        }
+    };
+    send_command(Command::ContactRequest(peer_id, note))
+}
+
+/// Accept the contact request received in `ContactRequestReceived` event.
+///
+/// ## Arguments
+///
+/// `peer_id` - base58 Libp2p peer_id.
+#[no_mangle]
+pub extern "C" fn accept_contact_request(peer_id: ByteArray) -> bool {
+    match parse_peer_id(peer_id) {
+        Some(peer_id) => send_command(Command::AcceptContactRequest(peer_id)),
+        None => false,
+    }
+}
+
+/// Decline the contact request received in `ContactRequestReceived` event.
+///
+/// ## Arguments
+///
+/// `peer_id` - base58 Libp2p peer_id.
+#[no_mangle]
+pub extern "C" fn decline_contact_request(peer_id: ByteArray) -> bool {
+    match parse_peer_id(peer_id) {
+        Some(peer_id) => send_command(Command::DeclineContactRequest(peer_id)),
+        None => false,
+    }
+}
+
+/// Update your profile. The new profile is sent to connected peers (they receive
+/// `ReceivedMetadata` event) and used for all new connections.
+///
+/// ## Arguments
+///
+/// `name` - your name as seen to other peers
+///
+/// `status` - utf8 status text, empty to clear the status
+///
+/// `avatar_hash` - hash of your avatar image, empty to clear the avatar
+#[no_mangle]
+pub extern "C" fn update_profile(
+    name: ByteArray,
+    status: ByteArray,
+    avatar_hash: ByteArray,
+) -> bool {
+    let name = match name.try_into() {
+        Ok(name) => name,
+        Err(e) => {
+            log::error!("Error converting `name` bytearray: {}", e);
+            return false;
+        }
+    };
+    let status: String = match status.try_into() {
+        Ok(status) => status,
+        Err(e) => {
+            log::error!("Error converting `status` bytearray: {}", e);
+            return false;
+        }
+    };
+    let avatar_hash: String = match avatar_hash.try_into() {
+        Ok(avatar_hash) => avatar_hash,
+        Err(e) => {
+            log::error!("Error converting `avatar_hash` bytearray: {}", e);
+            return false;
+        }
+    };
+    send_command(Command::UpdateProfile {
+        name,
+        status: Some(status).filter(|status| !status.is_empty()),
+        avatar_hash: Some(avatar_hash).filter(|hash| !hash.is_empty()),
+    })
+}
+
+/// Use the mailbox node for messages to offline contacts. Messages that can't be
+/// delivered directly are deposited to the mailbox (`MessageDeposited` event) and delivered
+/// once the recipient fetches them. Registers with the mailbox and fetches the messages
+/// received while you were offline.
+///
+/// ## Arguments
+///
+/// `peer_id` - base58 Libp2p peer_id of the mailbox.
+///
+/// `address` - multiaddress of the mailbox, e.g. `/ip4/1.2.3.4/tcp/4001`
+#[no_mangle]
+pub extern "C" fn set_mailbox(peer_id: ByteArray, address: ByteArray) -> bool {
+    let peer_id = match parse_peer_id(peer_id) {
+        Some(peer_id) => peer_id,
+        None => return false,
+    };
+    let address: String = match address.try_into() {
+        Ok(address) => address,
+        Err(e) => {
+            log::error!("Error converting `address` bytearray: {}", e);
+            return false;
+        }
+    };
+    match parse_address(&address) {
+        Ok(address) => send_command(Command::SetMailbox(peer_id, address)),
+        Err(e) => {
+            log::error!("Error parsing `address`: {}", e);
+            false
+        }
+    }
+}
+
+/// Fetch the messages held by the mailbox. Received messages come as
+/// `ReceivedPlainTextMessage` events.
+#[no_mangle]
+pub extern "C" fn fetch_mailbox() -> bool {
+    send_command(Command::FetchMailbox)
+}
+
+/// Use the rendezvous server for discovery of peers outside of the local network.
+/// Registrations in the namespaces are sent as soon as the node listens on an address.
+///
+/// ## Arguments
+///
+/// `peer_id` - base58 Libp2p peer_id of the rendezvous server.
+///
+/// `address` - multiaddress of the rendezvous server, e.g. `/ip4/1.2.3.4/tcp/4002`
+#[no_mangle]
+pub extern "C" fn set_rendezvous(peer_id: ByteArray, address: ByteArray) -> bool {
+    let peer_id = match parse_peer_id(peer_id) {
+        Some(peer_id) => peer_id,
+        None => return false,
+    };
+    let address: String = match address.try_into() {
+        Ok(address) => address,
+        Err(e) => {
+            log::error!("Error converting `address` bytearray: {}", e);
+            return false;
+        }
+    };
+    match parse_address(&address) {
+        Ok(address) => send_command(Command::SetRendezvous(peer_id, address)),
+        Err(e) => {
+            log::error!("Error parsing `address`: {}", e);
+            false
+        }
+    }
+}
+
+/// Register in the namespace at the rendezvous server. The registration is refreshed
+/// while the node is running and the peers registered in the namespace are reported with
+/// `PeerDiscovered` events.
+///
+/// ## Arguments
+///
+/// `namespace` - utf8 namespace, e.g. a team name
+#[no_mangle]
+pub extern "C" fn register_namespace(namespace: ByteArray) -> bool {
+    match namespace.try_into() {
+        Ok(namespace) => send_command(Command::RegisterNamespace(namespace)),
+        Err(e) => {
+            log::error!("Error converting `namespace` bytearray: {}", e);
+            false
+        }
+    }
+}
+
+/// Remove the registration in the namespace from the rendezvous server
+///
+/// ## Arguments
+///
+/// `namespace` - utf8 namespace passed to `register_namespace`
+#[no_mangle]
+pub extern "C" fn unregister_namespace(namespace: ByteArray) -> bool {
+    match namespace.try_into() {
+        Ok(namespace) => send_command(Command::UnregisterNamespace(namespace)),
+        Err(e) => {
+            log::error!("Error converting `namespace` bytearray: {}", e);
+            false
+        }
+    }
+}
+
+/// Query the rendezvous server for the peers in the namespace without registering in it.
+/// Peers are reported with `PeerDiscovered` events.
+///
+/// ## Arguments
+///
+/// `namespace` - utf8 namespace
+#[no_mangle]
+pub extern "C" fn discover_namespace(namespace: ByteArray) -> bool {
+    match namespace.try_into() {
+        Ok(namespace) => send_command(Command::DiscoverNamespace(namespace)),
+        Err(e) => {
+            log::error!("Error converting `namespace` bytearray: {}", e);
+            false
+        }
+    }
+}
+
+fn parse_peer_id(peer_id: ByteArray) -> Option<PeerId> {
+    let peer_id: String = match peer_id.try_into() {
+        Ok(peer_id) => peer_id,
+        Err(e) => {
+            log::error!("Error converting `peer_id` bytearray: {}", e);
+            return None;
+        }
+    };
+    match decode_peer_id(&peer_id) {
+        Ok(peer_id) => Some(peer_id),
+        Err(e) => {
+            log::error!("Error decoding `peer_id`: {}", e);
+            None
+        }
+    }
+}
+
+/// Host pointer handed back to the send callback on another thread
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+fn send_command(command: Command) -> bool {
+    match NODE.get() {
+        Some(node) => node.try_command(command),
+        None => {
+            log::error!("Node is not started");
+            false
+        }
+    }
+}
+
+fn send_incoming_event(event: IncomingEvent) -> bool {
+    if let Some(sender_mutex) = EVENTS_SENDER.get() {
+        if let Ok(mut sender) = sender_mutex.lock() {
+            return match sender.try_send(event) {
+                Ok(()) => true,
+                Err(e) => {
+                    log::error!("Error sending event to tx: {}", e);
+                    false
+                }
+            };
+        }
+    }
+    log::error!("Couldn't extract swarm from static cell");
+    false
+}
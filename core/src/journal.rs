@@ -0,0 +1,139 @@
+//! Journal of the events delivered to the host.
+//!
+//! Every event gets the next sequence number and is kept until the host acknowledges it,
+//! so a host that restarts its UI layer can replay the events it hasn't processed.
+//! Unacknowledged events evicted from a full journal are replayed as an `EventsDropped`
+//! marker with the number of the lost events.
+use primitives::{Event, PeerEvent, SequencedEvent};
+use std::collections::VecDeque;
+
+pub struct Journal {
+    events: VecDeque<SequencedEvent>,
+    next_seq: u64,
+    acked_seq: u64,
+    capacity: usize,
+    local_peer_id: String,
+}
+
+impl Journal {
+    pub fn new(capacity: usize, local_peer_id: String) -> Self {
+        Journal {
+            events: VecDeque::new(),
+            next_seq: 1,
+            acked_seq: 0,
+            capacity,
+            local_peer_id,
+        }
+    }
+
+    /// Assigns the next sequence number to the event and keeps it until acknowledged.
+    /// The oldest event is evicted if the journal is full.
+    pub fn push(&mut self, event: PeerEvent) -> SequencedEvent {
+        let event = SequencedEvent {
+            seq: self.next_seq,
+            event,
+        };
+        self.next_seq += 1;
+        if self.capacity == 0 {
+            return event;
+        }
+        if self.events.len() >= self.capacity {
+            if let Some(evicted) = self.events.pop_front() {
+                log::warn!("Event journal is full, event {} is evicted", evicted.seq);
+            }
+        }
+        self.events.push_back(event.clone());
+        event
+    }
+
+    /// Forgets the events up to and including `seq`
+    pub fn ack(&mut self, seq: u64) {
+        self.acked_seq = self.acked_seq.max(seq);
+        while self
+            .events
+            .front()
+            .map(|event| event.seq <= seq)
+            .unwrap_or(false)
+        {
+            self.events.pop_front();
+        }
+    }
+
+    /// Events not acknowledged yet starting from `from_seq`, preceded by an `EventsDropped`
+    /// marker if some of them were evicted. The marker has the sequence number of the last
+    /// evicted event, so acknowledging it acknowledges the lost events.
+    pub fn replay(&self, from_seq: u64) -> impl Iterator<Item = SequencedEvent> + '_ {
+        let first_kept = self
+            .events
+            .front()
+            .map(|event| event.seq)
+            .unwrap_or(self.next_seq);
+        let first_lost = from_seq.max(self.acked_seq + 1);
+        let dropped = if first_lost < first_kept && self.capacity > 0 {
+            Some(SequencedEvent {
+                seq: first_kept - 1,
+                event: PeerEvent {
+                    peer_id: self.local_peer_id.clone(),
+                    event: Event::EventsDropped {
+                        count: first_kept - first_lost,
+                    },
+                },
+            })
+        } else {
+            None
+        };
+        dropped.into_iter().chain(
+            self.events
+                .iter()
+                .filter(move |event| event.seq >= from_seq)
+                .cloned(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal(capacity: usize) -> Journal {
+        let mut journal = Journal::new(capacity, "local".to_string());
+        for _ in 0..5 {
+            journal.push(PeerEvent {
+                peer_id: "remote".to_string(),
+                event: Event::PeerDiscovered,
+            });
+        }
+        journal
+    }
+
+    fn seqs(journal: &Journal, from_seq: u64) -> Vec<u64> {
+        journal.replay(from_seq).map(|event| event.seq).collect()
+    }
+
+    #[test]
+    fn unacknowledged_events_are_replayed() {
+        let mut journal = journal(10);
+        assert_eq!(seqs(&journal, 0), vec![1, 2, 3, 4, 5]);
+        journal.ack(3);
+        assert_eq!(seqs(&journal, 0), vec![4, 5]);
+        assert_eq!(seqs(&journal, 5), vec![5]);
+    }
+
+    #[test]
+    fn evicted_events_are_replayed_as_dropped() {
+        let mut journal = journal(2);
+        journal.ack(1);
+        let replayed = journal.replay(0).collect::<Vec<_>>();
+        assert_eq!(replayed.len(), 3);
+        assert_eq!(replayed[0].seq, 3);
+        assert_eq!(replayed[0].event.peer_id, "local");
+        assert!(matches!(
+            replayed[0].event.event,
+            Event::EventsDropped { count: 2 }
+        ));
+        assert_eq!(seqs(&journal, 4), vec![4, 5]);
+
+        journal.ack(3);
+        assert_eq!(seqs(&journal, 0), vec![4, 5]);
+    }
+}
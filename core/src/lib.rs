@@ -1,22 +1,42 @@
 //! Provides underlying mechanics for p2p communcations.
 //!
-//! Use [create_swarm](./fn.create_swarm.html) function to create if you use
-//! this lib from from Rust apps. It creates a Swarm tham implements Stream and can be polled.
+//! Use [Node](./struct.Node.html) if you use this lib from Rust apps. It runs the swarm
+//! in the background and provides async methods and a stream of events.
+//! Use [create_swarm](./fn.create_swarm.html) function to poll the Swarm yourself.
 //!
 //! Use functions in the [ffi](./ffi/index.html) if you use this lib as a `C` lib.
+mod abi;
+mod config;
 mod error;
+mod event_queue;
 pub mod ffi;
+mod journal;
 mod network;
+mod node;
+mod subscriptions;
+mod transport;
 mod utils;
 
-use error::Result;
-use futures::channel::mpsc::Receiver;
-use libp2p::identity::secp256k1::{Keypair, SecretKey};
-use libp2p::{PeerId, Swarm};
+pub use abi::TATA_ABI_VERSION;
+pub use config::{
+    Config, DiscoveryConfig, EventsConfig, ForwardingConfig, Limits, LivenessConfig, MailboxConfig,
+    MailboxServerConfig, ProxyConfig, RendezvousConfig, RendezvousServerConfig, ScoringConfig,
+    TransportConfig,
+};
+pub use error::{Error, Result};
+pub use event_queue::EventReceiver;
+use libp2p::identity::secp256k1::Keypair;
+pub use libp2p::identity::secp256k1::SecretKey;
+use libp2p::Swarm;
+pub use libp2p::{Multiaddr, PeerId};
 use network::{CoreNetworkBehaviour, HandshakeMetadata};
-use primitives::{Metadata, PeerEvent};
-
-const CHANNEL_BUFFER_SIZE: usize = 10;
+pub use network::{MailboxServer, RendezvousServer};
+pub use node::{
+    Delivery, DeliveryError, DeliveryFuture, DeliveryResult, Node, DEFAULT_DELIVERY_TIMEOUT,
+};
+pub use subscriptions::EventFilter;
+pub use transport::{generate_psk, parse_address};
+pub use utils::{decode_peer_id, generate_secret};
 
 /// Create a libp2p swarm for polling
 ///
@@ -24,25 +44,105 @@ const CHANNEL_BUFFER_SIZE: usize = 10;
 /// `secret` - secret key for the current peer
 ///
 /// `name` - The username for the current user
+///
+/// `config` - Network configuration, see [Config](./struct.Config.html)
 pub fn create_swarm(
     secret: SecretKey,
     name: String,
-) -> Result<(Swarm<CoreNetworkBehaviour>, Receiver<PeerEvent>)> {
-    let metadata = Metadata { name };
+    config: Config,
+) -> Result<(Swarm<CoreNetworkBehaviour>, EventReceiver)> {
     let keypair: Keypair = secret.into();
-    let libp2p_keypair = libp2p::identity::Keypair::Secp256k1(keypair);
-    let public_key = libp2p_keypair.public().clone();
-    let peer_id = PeerId::from_public_key(public_key);
-    let transport = libp2p::build_development_transport(libp2p_keypair)?;
-    let (tx, rx) = futures::channel::mpsc::channel(CHANNEL_BUFFER_SIZE);
+    let libp2p_keypair = libp2p::identity::Keypair::Secp256k1(keypair.clone());
+    let peer_id = PeerId::from_public_key(libp2p_keypair.public());
+    let psk = config
+        .psk
+        .as_ref()
+        .map(|psk| transport::parse_psk(psk))
+        .transpose()?;
+    let (tx, rx) = event_queue::channel(
+        config.events.max_queued_bytes,
+        config.events.encoding,
+        &peer_id,
+    );
     let behaviour = CoreNetworkBehaviour::new(
-        HandshakeMetadata {
-            name: metadata.name,
-        },
+        keypair,
+        HandshakeMetadata::new(
+            name,
+            config.status.clone(),
+            config.avatar_hash.clone(),
+            config.capabilities.clone(),
+        ),
+        &config,
         tx,
     )?;
 
+    let transport =
+        transport::build_transport(libp2p_keypair, psk, config.proxy.clone(), &config.transport)?;
     let mut swarm = Swarm::new(transport, behaviour, peer_id);
-    Swarm::listen_on(&mut swarm, "/ip4/0.0.0.0/tcp/0".parse()?)?;
+    for address in config.transport.listen_addresses.iter() {
+        Swarm::listen_on(&mut swarm, transport::parse_address(address)?)?;
+    }
     Ok((swarm, rx))
 }
+
+/// Create a libp2p swarm of the mailbox node holding messages for offline peers
+///
+/// # Arguments
+/// `secret` - secret key of the mailbox node
+///
+/// `config` - Mailbox configuration, see [MailboxServerConfig](./struct.MailboxServerConfig.html)
+pub fn create_mailbox_swarm(
+    secret: SecretKey,
+    config: MailboxServerConfig,
+) -> Result<Swarm<MailboxServer>> {
+    let keypair: Keypair = secret.into();
+    let libp2p_keypair = libp2p::identity::Keypair::Secp256k1(keypair);
+    let peer_id = PeerId::from_public_key(libp2p_keypair.public());
+    let psk = config
+        .psk
+        .as_deref()
+        .map(transport::parse_psk)
+        .transpose()?;
+    let transport = transport::build_transport(libp2p_keypair, psk, None, &config.transport)?;
+    let behaviour = MailboxServer::new(&config)?;
+    let mut swarm = Swarm::new(transport, behaviour, peer_id);
+    Swarm::listen_on(
+        &mut swarm,
+        transport::parse_address(&config.listen_address)?,
+    )?;
+    for address in config.transport.listen_addresses.iter() {
+        Swarm::listen_on(&mut swarm, transport::parse_address(address)?)?;
+    }
+    Ok(swarm)
+}
+
+/// Create a libp2p swarm for the rendezvous server
+///
+/// # Arguments
+/// `secret` - secret key of the rendezvous server
+///
+/// `config` - Rendezvous server configuration, see [RendezvousServerConfig](./struct.RendezvousServerConfig.html)
+pub fn create_rendezvous_swarm(
+    secret: SecretKey,
+    config: RendezvousServerConfig,
+) -> Result<Swarm<RendezvousServer>> {
+    let keypair: Keypair = secret.into();
+    let libp2p_keypair = libp2p::identity::Keypair::Secp256k1(keypair);
+    let peer_id = PeerId::from_public_key(libp2p_keypair.public());
+    let psk = config
+        .psk
+        .as_deref()
+        .map(transport::parse_psk)
+        .transpose()?;
+    let transport = transport::build_transport(libp2p_keypair, psk, None, &config.transport)?;
+    let mut listen_addresses = vec![transport::parse_address(&config.listen_address)?];
+    for address in config.transport.listen_addresses.iter() {
+        listen_addresses.push(transport::parse_address(address)?);
+    }
+    let behaviour = RendezvousServer::new(config);
+    let mut swarm = Swarm::new(transport, behaviour, peer_id);
+    for address in listen_addresses {
+        Swarm::listen_on(&mut swarm, address)?;
+    }
+    Ok(swarm)
+}
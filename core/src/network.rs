@@ -1,5 +1,12 @@
 mod core_behaviour;
+mod deliveries;
+mod json_codec;
+mod local_discovery;
+mod mailbox;
 mod private_chat;
+mod rendezvous;
 
 pub use self::core_behaviour::CoreNetworkBehaviour;
+pub use mailbox::MailboxServer;
 pub use private_chat::*;
+pub use rendezvous::RendezvousServer;
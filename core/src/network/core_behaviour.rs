@@ -1,80 +1,635 @@
 //! Network behaviour implementation
 
-use futures::channel::mpsc::Sender;
+use futures::channel::oneshot;
 use libp2p::{
-    mdns::{Mdns, MdnsEvent},
-    swarm::NetworkBehaviourEventProcess,
-    NetworkBehaviour,
+    identify::{Identify, IdentifyEvent},
+    identity::{secp256k1::Keypair, PublicKey},
+    ping::{Ping, PingConfig, PingEvent, PingSuccess},
+    request_response::{
+        ProtocolSupport, RequestId, RequestResponse, RequestResponseConfig, RequestResponseEvent,
+        RequestResponseMessage,
+    },
+    swarm::{NetworkBehaviourAction, NetworkBehaviourEventProcess, PollParameters},
+    Multiaddr, NetworkBehaviour, PeerId,
 };
-use primitives::{Event, PeerEvent};
-use std::collections::HashSet;
+use primitives::{ErrorMessage, Event, PeerEvent, PlainTextMessage};
+use std::collections::{HashMap, HashSet};
+use std::iter;
+use std::num::NonZeroU32;
+use std::task::{Context, Poll};
+use std::time::Duration;
 
-use crate::error::Result;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::event_queue::{EventReceiver, EventSender};
+use crate::node::DeliveryResult;
+use crate::subscriptions::{EventFilter, Subscriptions};
+use crate::transport::parse_address;
+use crate::utils::decode_peer_id;
 
+use super::deliveries::Deliveries;
+use super::local_discovery::{LocalDiscovery, LocalDiscoveryEvent};
+use super::mailbox::{
+    open_payload, seal_payload, Envelope, MailboxCodec, MailboxProtocol, MailboxRequest,
+    MailboxResponse, StoredEnvelope,
+};
 use super::private_chat::{HandshakeMetadata, PrivateChatBehaviour};
+use super::rendezvous::{
+    Registration, RendezvousClient, RendezvousCodec, RendezvousProtocol, RendezvousRequest,
+    RendezvousResponse,
+};
+
+const IDENTIFY_PROTOCOL_VERSION: &str = "/tata/1.0.0";
+const DEFAULT_RENDEZVOUS_TTL_SECS: u64 = 2 * 60 * 60;
+const DEFAULT_DISCOVER_INTERVAL_SECS: u64 = 60;
 
 /// Implementation of networking behaviour for core
 #[derive(NetworkBehaviour)]
+#[behaviour(poll_method = "poll_rendezvous")]
 pub struct CoreNetworkBehaviour {
-    pub mdns: Mdns,
+    pub local_discovery: LocalDiscovery,
+    pub ping: Ping,
+    pub identify: Identify,
     pub private_chat: PrivateChatBehaviour,
+    pub mailbox: RequestResponse<MailboxCodec>,
+    pub rendezvous: RequestResponse<RendezvousCodec>,
+    #[behaviour(ignore)]
+    pub event_sink: EventSender,
+    /// Deliveries resolved by the events before they are queued
+    #[behaviour(ignore)]
+    deliveries: Deliveries,
+    /// Queues of the subscribers to the events, in addition to `event_sink`
+    #[behaviour(ignore)]
+    subscriptions: Subscriptions<EventSender>,
+    #[behaviour(ignore)]
+    next_subscription_id: u64,
+    /// Report the peers discovered with the rendezvous server
     #[behaviour(ignore)]
-    pub event_sink: Sender<PeerEvent>,
+    discover: bool,
+    /// Key of the node, envelopes from the mailbox are sealed to it
+    #[behaviour(ignore)]
+    local_key: Keypair,
+    /// Max size of the mailbox response accepted by the codec
+    #[behaviour(ignore)]
+    max_fetch_size: usize,
+    /// Peer id of the mailbox holding messages for offline peers
+    #[behaviour(ignore)]
+    mailbox_peer: Option<PeerId>,
+    /// Recipients and timestamps of the messages deposited to the mailbox
+    #[behaviour(ignore)]
+    deposits: HashMap<RequestId, (PeerId, u64)>,
+    /// Registrations at the rendezvous server
+    #[behaviour(ignore)]
+    rendezvous_client: RendezvousClient,
+    /// Our addresses observed by the identified peers, not yet added to the swarm
+    #[behaviour(ignore)]
+    observed_addresses: Vec<Multiaddr>,
 }
 
 impl CoreNetworkBehaviour {
-    pub fn new(local_metadata: HandshakeMetadata, event_sink: Sender<PeerEvent>) -> Result<Self> {
-        let mdns = Mdns::new()?;
-        let private_chat = PrivateChatBehaviour::new(local_metadata);
-        Ok(CoreNetworkBehaviour {
-            mdns,
+    pub fn new(
+        local_key: Keypair,
+        local_metadata: HandshakeMetadata,
+        config: &Config,
+        event_sink: EventSender,
+    ) -> Result<Self> {
+        let local_discovery =
+            LocalDiscovery::new(config.discovery.visible, config.discovery.discover)?;
+        let max_failures = NonZeroU32::new(config.liveness.max_failures)
+            .ok_or("Max ping failures must be positive")?;
+        let ping = Ping::new(
+            PingConfig::new()
+                .with_interval(Duration::from_secs(config.liveness.interval_secs))
+                .with_timeout(Duration::from_secs(config.liveness.timeout_secs))
+                .with_max_failures(max_failures),
+        );
+        let identify = Identify::new(
+            IDENTIFY_PROTOCOL_VERSION.to_string(),
+            local_metadata.client_version.clone(),
+            PublicKey::Secp256k1(local_key.public().clone()),
+        );
+        let private_chat = PrivateChatBehaviour::new(
+            libp2p::identity::Keypair::Secp256k1(local_key.clone()),
+            local_metadata,
+            config.limits.clone(),
+            config.scoring.clone(),
+            config.forwarding.clone(),
+        );
+        let mailbox = RequestResponse::new(
+            MailboxCodec::new(config.limits.max_frame_size),
+            iter::once((MailboxProtocol, ProtocolSupport::Outbound)),
+            RequestResponseConfig::default(),
+        );
+        let rendezvous = RequestResponse::new(
+            RendezvousCodec::new(config.limits.max_frame_size),
+            iter::once((RendezvousProtocol, ProtocolSupport::Outbound)),
+            RequestResponseConfig::default(),
+        );
+        let rendezvous_client = match &config.rendezvous {
+            Some(rendezvous) => RendezvousClient::new(
+                rendezvous.ttl_secs,
+                Duration::from_secs(rendezvous.discover_interval_secs),
+            ),
+            None => RendezvousClient::new(
+                DEFAULT_RENDEZVOUS_TTL_SECS,
+                Duration::from_secs(DEFAULT_DISCOVER_INTERVAL_SECS),
+            ),
+        };
+        let mut behaviour = CoreNetworkBehaviour {
+            local_discovery,
+            ping,
+            identify,
+            discover: config.discovery.discover,
+            local_key,
+            max_fetch_size: config.limits.max_frame_size,
             event_sink,
+            deliveries: Deliveries::default(),
+            subscriptions: Subscriptions::default(),
+            next_subscription_id: 0,
             private_chat,
-        })
+            mailbox,
+            mailbox_peer: None,
+            deposits: HashMap::new(),
+            rendezvous,
+            rendezvous_client,
+            observed_addresses: Vec::new(),
+        };
+        if let Some(mailbox) = &config.mailbox {
+            behaviour.set_mailbox(
+                decode_peer_id(&mailbox.peer_id)?,
+                parse_address(&mailbox.address)?,
+            );
+        }
+        if let Some(rendezvous) = &config.rendezvous {
+            behaviour.set_rendezvous(
+                decode_peer_id(&rendezvous.peer_id)?,
+                parse_address(&rendezvous.address)?,
+            );
+            for namespace in rendezvous.namespaces.iter() {
+                behaviour.register_namespace(namespace.clone());
+            }
+        }
+        Ok(behaviour)
+    }
+
+    /// Start or stop advertising ourselves with mDNS. Known contacts can still be dialed
+    /// and other peers can still be discovered while invisible.
+    pub fn set_visible(&mut self, visible: bool) -> Result<()> {
+        Ok(self.local_discovery.set_visible(visible)?)
+    }
+
+    /// Start or stop discovering other peers with mDNS and the rendezvous server
+    pub fn set_discovery(&mut self, discover: bool) -> Result<()> {
+        self.local_discovery.set_discovery(discover)?;
+        self.discover = discover;
+        Ok(())
+    }
+
+    /// Use the mailbox for messages to offline peers. Registers with the mailbox
+    /// and fetches the messages received while we were offline.
+    pub fn set_mailbox(&mut self, peer_id: PeerId, address: Multiaddr) {
+        self.mailbox.add_address(&peer_id, address);
+        self.mailbox
+            .send_request(&peer_id, MailboxRequest::Register);
+        self.mailbox_peer = Some(peer_id);
+        self.send_fetch();
+    }
+
+    /// Fetch the messages held by the mailbox
+    pub fn fetch_mailbox(&mut self) -> Result<()> {
+        self.mailbox_peer.as_ref().ok_or("Mailbox is not set")?;
+        self.send_fetch();
+        Ok(())
+    }
+
+    /// Use the rendezvous server for discovery of peers outside of the local network.
+    /// Registrations in the namespaces are sent to the server as soon as our listen
+    /// addresses are known.
+    pub fn set_rendezvous(&mut self, peer_id: PeerId, address: Multiaddr) {
+        self.rendezvous.add_address(&peer_id, address);
+        self.rendezvous_client.set_server(peer_id);
+    }
+
+    /// Register in the namespace at the rendezvous server and periodically discover
+    /// the other peers registered in it
+    pub fn register_namespace(&mut self, namespace: String) {
+        self.rendezvous_client.register(namespace);
+    }
+
+    /// Remove our registration in the namespace from the rendezvous server
+    pub fn unregister_namespace(&mut self, namespace: String) -> Result<()> {
+        let peer_id = self
+            .rendezvous_client
+            .server()
+            .ok_or("Rendezvous server is not set")?
+            .clone();
+        if !self.rendezvous_client.unregister(&namespace) {
+            return Err(format!("Not registered in namespace {}", namespace).into());
+        }
+        self.rendezvous
+            .send_request(&peer_id, RendezvousRequest::Unregister { namespace });
+        Ok(())
+    }
+
+    /// Query the rendezvous server for the peers registered in the namespace
+    pub fn discover_namespace(&mut self, namespace: String) -> Result<()> {
+        let peer_id = self
+            .rendezvous_client
+            .server()
+            .ok_or("Rendezvous server is not set")?
+            .clone();
+        self.rendezvous
+            .send_request(&peer_id, RendezvousRequest::Discover { namespace });
+        Ok(())
+    }
+
+    /// Sends the registrations and discovery requests that are due
+    fn poll_rendezvous<TEv>(
+        &mut self,
+        cx: &mut Context<'_>,
+        params: &mut impl PollParameters,
+    ) -> Poll<NetworkBehaviourAction<TEv, ()>> {
+        let addresses = params.listened_addresses().collect();
+        for (peer_id, request) in self.rendezvous_client.poll(cx, addresses) {
+            self.rendezvous.send_request(&peer_id, request);
+        }
+        Poll::Pending
+    }
+
+    /// Records the addresses of the peers found at the rendezvous server
+    fn receive_registrations(&mut self, namespace: String, registrations: Vec<Registration>) {
+        for registration in registrations {
+            let peer_id = match decode_peer_id(&registration.peer_id) {
+                Ok(peer_id) => peer_id,
+                Err(e) => {
+                    log::error!("Error decoding registered peer id: {}", e);
+                    continue;
+                }
+            };
+            for address in registration.addresses {
+                match parse_address(&address) {
+                    Ok(address) => self.private_chat.add_address(&peer_id, address),
+                    Err(e) => log::error!("Error parsing address of {}: {}", peer_id, e),
+                }
+            }
+            if self.discover && self.rendezvous_client.discovered(&peer_id) {
+                log::debug!("Discovered {} in namespace {}", peer_id, namespace);
+                self.send_event(PeerEvent {
+                    peer_id: peer_id.to_base58(),
+                    event: Event::PeerDiscovered,
+                });
+            }
+        }
+    }
+
+    /// Deposits the messages that couldn't be delivered to the peer to the mailbox.
+    /// Without the mailbox the messages are dropped and reported as failed.
+    fn deposit_undelivered(&mut self, peer_id: &PeerId) {
+        let messages = self.private_chat.take_undelivered(peer_id);
+        let mailbox_peer = match &self.mailbox_peer {
+            Some(mailbox_peer) => mailbox_peer.clone(),
+            None => {
+                for message in messages {
+                    self.send_event(PeerEvent {
+                        peer_id: peer_id.to_base58(),
+                        event: Event::Error {
+                            error: ErrorMessage::MessageValidation {
+                                timestamp: message.timestamp,
+                                cause: "No mailbox to deposit and no peers to relay the message"
+                                    .to_string(),
+                            },
+                        },
+                    });
+                }
+                return;
+            }
+        };
+        for message in messages {
+            let payload = match serde_json::to_vec(&message)
+                .map_err(Error::from)
+                .and_then(|payload| seal_payload(&self.local_key, peer_id, &payload))
+            {
+                Ok(payload) => payload,
+                Err(e) => {
+                    log::error!("Error sealing message to {}: {}", peer_id, e);
+                    self.send_event(PeerEvent {
+                        peer_id: peer_id.to_base58(),
+                        event: Event::Error {
+                            error: ErrorMessage::MessageValidation {
+                                timestamp: message.timestamp,
+                                cause: e.to_string(),
+                            },
+                        },
+                    });
+                    continue;
+                }
+            };
+            let envelope = Envelope {
+                to: peer_id.to_base58(),
+                payload,
+            };
+            let request_id = self
+                .mailbox
+                .send_request(&mailbox_peer, MailboxRequest::Deposit { envelope });
+            self.deposits
+                .insert(request_id, (peer_id.clone(), message.timestamp));
+        }
+    }
+
+    /// Delivers the fetched envelopes and acknowledges them
+    fn receive_envelopes(&mut self, mailbox_peer: &PeerId, envelopes: Vec<StoredEnvelope>) {
+        if envelopes.is_empty() {
+            return;
+        }
+        let ids = envelopes.iter().map(|envelope| envelope.id).collect();
+        for envelope in envelopes {
+            let from = match decode_peer_id(&envelope.from) {
+                Ok(from) => from,
+                Err(e) => {
+                    log::error!("Error decoding envelope sender: {}", e);
+                    continue;
+                }
+            };
+            let message = open_payload(&self.local_key, &from, &envelope.payload)
+                .and_then(|payload| Ok(serde_json::from_slice::<PlainTextMessage>(&payload)?));
+            match message {
+                Ok(message) => self.private_chat.receive_indirect_message(from, message),
+                Err(e) => log::error!("Error opening envelope from {}: {}", envelope.from, e),
+            }
+        }
+        self.mailbox
+            .send_request(mailbox_peer, MailboxRequest::Ack { ids });
+        // The mailbox returns a limited number of envelopes at once, fetch the rest
+        self.send_fetch();
+    }
+
+    /// Requests the envelopes that fit into the frame accepted by the codec
+    fn send_fetch(&mut self) {
+        if let Some(mailbox_peer) = &self.mailbox_peer {
+            self.mailbox.send_request(
+                mailbox_peer,
+                MailboxRequest::Fetch {
+                    max_size: Some(self.max_fetch_size),
+                },
+            );
+        }
+    }
+
+    /// Our addresses observed by the peers since the last call, to be added to the
+    /// external addresses of the swarm
+    pub fn take_observed_addresses(&mut self) -> Vec<Multiaddr> {
+        std::mem::take(&mut self.observed_addresses)
+    }
+
+    /// Waits for the delivery of the message sent to the peer
+    pub fn track_delivery(
+        &mut self,
+        to: String,
+        timestamp: u64,
+        delivery: oneshot::Sender<DeliveryResult>,
+    ) {
+        self.deliveries.track(to, timestamp, delivery);
+    }
+
+    /// Whether the delivery of a message to the peer with the timestamp is tracked
+    pub fn is_delivery_pending(&mut self, to: &str, timestamp: u64) -> bool {
+        self.deliveries.is_pending(to, timestamp)
+    }
+
+    /// Queue of the events matching the filter. The subscription ends once the queue
+    /// is dropped.
+    pub fn subscribe(&mut self, filter: EventFilter) -> EventReceiver {
+        let (sender, receiver) = self.event_sink.channel();
+        self.next_subscription_id += 1;
+        self.subscriptions
+            .subscribe(self.next_subscription_id, filter, sender);
+        receiver
+    }
+
+    fn send_event(&mut self, event: PeerEvent) {
+        self.deliveries.resolve(
+            &event,
+            self.mailbox_peer.is_some(),
+            self.private_chat.forwarding(),
+        );
+        self.subscriptions.retain(|sender| !sender.is_closed());
+        for sender in self.subscriptions.matching(&event) {
+            sender.send(event.clone());
+        }
+        self.event_sink.send(event);
+    }
+
+    fn mailbox_error(&mut self, peer_id: &PeerId, cause: String) {
+        self.send_event(PeerEvent {
+            peer_id: peer_id.to_base58(),
+            event: Event::Error {
+                error: ErrorMessage::Mailbox { cause },
+            },
+        });
     }
 }
 
 impl NetworkBehaviourEventProcess<PeerEvent> for CoreNetworkBehaviour {
     fn inject_event(&mut self, event: PeerEvent) {
-        if let Err(e) = self.event_sink.start_send(event) {
-            log::error!("Error sending message to event sink: {}", e);
+        if let Event::Error {
+            error: ErrorMessage::FailedToDial { .. },
+        } = &event.event
+        {
+            match decode_peer_id(&event.peer_id) {
+                Ok(peer_id) => self.deposit_undelivered(&peer_id),
+                Err(e) => log::error!("Error decoding peer id: {}", e),
+            }
         }
+        self.send_event(event);
     }
 }
 
-impl NetworkBehaviourEventProcess<MdnsEvent> for CoreNetworkBehaviour {
-    fn inject_event(&mut self, event: MdnsEvent) {
+impl NetworkBehaviourEventProcess<RequestResponseEvent<MailboxRequest, MailboxResponse>>
+    for CoreNetworkBehaviour
+{
+    fn inject_event(&mut self, event: RequestResponseEvent<MailboxRequest, MailboxResponse>) {
         match event {
-            MdnsEvent::Discovered(list) => {
-                let peer_ids = list
-                    .into_iter()
+            RequestResponseEvent::Message {
+                peer,
+                message:
+                    RequestResponseMessage::Response {
+                        request_id,
+                        response,
+                    },
+            } => match response {
+                MailboxResponse::Deposited { .. } => {
+                    if let Some((peer_id, timestamp)) = self.deposits.remove(&request_id) {
+                        self.send_event(PeerEvent {
+                            peer_id: peer_id.to_base58(),
+                            event: Event::MessageDeposited { timestamp },
+                        });
+                    }
+                }
+                MailboxResponse::Envelopes { envelopes } => {
+                    self.receive_envelopes(&peer, envelopes)
+                }
+                MailboxResponse::Registered => log::debug!("Registered with mailbox {}", peer),
+                MailboxResponse::Acked => (),
+                MailboxResponse::Error { cause } => {
+                    let peer_id = self
+                        .deposits
+                        .remove(&request_id)
+                        .map(|(peer_id, _)| peer_id)
+                        .unwrap_or(peer);
+                    self.mailbox_error(&peer_id, cause);
+                }
+            },
+            RequestResponseEvent::Message {
+                peer,
+                message: RequestResponseMessage::Request { .. },
+            } => log::warn!("Unexpected mailbox request from {}", peer),
+            RequestResponseEvent::OutboundFailure {
+                peer,
+                request_id,
+                error,
+            } => {
+                let peer_id = self
+                    .deposits
+                    .remove(&request_id)
                     .map(|(peer_id, _)| peer_id)
-                    .collect::<HashSet<_>>();
+                    .unwrap_or(peer);
+                self.mailbox_error(&peer_id, format!("{:?}", error));
+            }
+            RequestResponseEvent::InboundFailure { peer, error, .. } => {
+                log::error!("Mailbox inbound failure with {}: {:?}", peer, error)
+            }
+        }
+    }
+}
+
+impl NetworkBehaviourEventProcess<RequestResponseEvent<RendezvousRequest, RendezvousResponse>>
+    for CoreNetworkBehaviour
+{
+    fn inject_event(&mut self, event: RequestResponseEvent<RendezvousRequest, RendezvousResponse>) {
+        match event {
+            RequestResponseEvent::Message {
+                peer,
+                message: RequestResponseMessage::Response { response, .. },
+            } => match response {
+                RendezvousResponse::Registered {
+                    namespace,
+                    ttl_secs,
+                } => log::debug!(
+                    "Registered in namespace {} at {} for {}s",
+                    namespace,
+                    peer,
+                    ttl_secs
+                ),
+                RendezvousResponse::Unregistered { namespace } => {
+                    log::debug!("Unregistered from namespace {} at {}", namespace, peer)
+                }
+                RendezvousResponse::Registrations {
+                    namespace,
+                    registrations,
+                } => self.receive_registrations(namespace, registrations),
+                RendezvousResponse::Error { cause } => self.send_event(PeerEvent {
+                    peer_id: peer.to_base58(),
+                    event: Event::Error {
+                        error: ErrorMessage::Rendezvous { cause },
+                    },
+                }),
+            },
+            RequestResponseEvent::Message {
+                peer,
+                message: RequestResponseMessage::Request { .. },
+            } => log::warn!("Unexpected rendezvous request from {}", peer),
+            // Requests are repeated periodically, so an unreachable server is only logged
+            RequestResponseEvent::OutboundFailure { peer, error, .. } => {
+                log::warn!("Rendezvous request to {} failed: {:?}", peer, error)
+            }
+            RequestResponseEvent::InboundFailure { peer, error, .. } => {
+                log::error!("Rendezvous inbound failure with {}: {:?}", peer, error)
+            }
+        }
+    }
+}
+
+impl NetworkBehaviourEventProcess<PingEvent> for CoreNetworkBehaviour {
+    fn inject_event(&mut self, event: PingEvent) {
+        match event.result {
+            Ok(PingSuccess::Ping { rtt }) => self.send_event(PeerEvent {
+                peer_id: event.peer.to_base58(),
+                event: Event::PeerRtt {
+                    rtt_ms: rtt.as_millis() as u64,
+                },
+            }),
+            Ok(PingSuccess::Pong) => (),
+            // The connection is closed by the ping handler after too many failures
+            Err(e) => log::debug!("Ping of {} failed: {}", event.peer, e),
+        }
+    }
+}
+
+impl NetworkBehaviourEventProcess<IdentifyEvent> for CoreNetworkBehaviour {
+    fn inject_event(&mut self, event: IdentifyEvent) {
+        match event {
+            IdentifyEvent::Received {
+                peer_id,
+                info,
+                observed_addr,
+            } => {
+                // Lets us dial back the peers that connected to us
+                for address in info.listen_addrs.iter() {
+                    self.private_chat.add_address(&peer_id, address.clone());
+                }
+                self.observed_addresses.push(observed_addr.clone());
+                self.send_event(PeerEvent {
+                    peer_id: peer_id.to_base58(),
+                    event: Event::PeerInfo {
+                        agent_version: info.agent_version,
+                        protocol_version: info.protocol_version,
+                        protocols: info.protocols,
+                        listen_addresses: info
+                            .listen_addrs
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect(),
+                        observed_address: observed_addr.to_string(),
+                    },
+                });
+            }
+            IdentifyEvent::Sent { .. } => (),
+            IdentifyEvent::Error { peer_id, error } => {
+                log::debug!("Error identifying {}: {:?}", peer_id, error)
+            }
+        }
+    }
+}
+
+impl NetworkBehaviourEventProcess<LocalDiscoveryEvent> for CoreNetworkBehaviour {
+    fn inject_event(&mut self, event: LocalDiscoveryEvent) {
+        match event {
+            // Reported only while discovering
+            LocalDiscoveryEvent::Discovered(list) => {
+                let mut peer_ids = HashSet::new();
+                for (peer_id, address) in list {
+                    self.private_chat.add_address(&peer_id, address);
+                    peer_ids.insert(peer_id);
+                }
                 for peer_id in peer_ids {
-                    let peer_id = peer_id.to_base58().into();
-                    let event = PeerEvent {
-                        peer_id,
+                    self.send_event(PeerEvent {
+                        peer_id: peer_id.to_base58(),
                         event: Event::PeerDiscovered,
-                    };
-                    if let Err(e) = self.event_sink.try_send(event) {
-                        log::error!("Error sending message to event sink: {}", e);
-                    }
+                    });
                 }
             }
-            MdnsEvent::Expired(list) => {
+            // Reported regardless of discovery, so that stale addresses are forgotten
+            LocalDiscoveryEvent::Expired(list) => {
                 let peer_ids = list
                     .into_iter()
                     .map(|(peer_id, _)| peer_id)
                     .collect::<HashSet<_>>();
                 for peer_id in peer_ids {
-                    let peer_id = peer_id.to_base58().into();
-                    let event = PeerEvent {
-                        peer_id,
-                        event: Event::PeerDiscovered,
-                    };
-                    if let Err(e) = self.event_sink.try_send(event) {
-                        log::error!("Error sending message to event sink: {}", e);
-                    }
+                    self.private_chat.expire_addresses(&peer_id);
+                    self.send_event(PeerEvent {
+                        peer_id: peer_id.to_base58(),
+                        event: Event::PeerGone,
+                    });
                 }
             }
         }
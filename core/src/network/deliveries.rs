@@ -0,0 +1,97 @@
+//! Deliveries of the messages sent with `Node::send`, resolved by the events reporting
+//! on the messages before the events are queued for the host.
+use crate::node::{Delivery, DeliveryError, DeliveryResult};
+use futures::channel::oneshot;
+use primitives::{ErrorMessage, Event, PeerEvent};
+use std::collections::HashMap;
+
+/// Messages waiting for the delivery by recipient and timestamp
+#[derive(Default)]
+pub struct Deliveries {
+    pending: HashMap<(String, u64), oneshot::Sender<DeliveryResult>>,
+}
+
+impl Deliveries {
+    /// Waits for the delivery of the message to the peer
+    pub fn track(&mut self, to: String, timestamp: u64, delivery: oneshot::Sender<DeliveryResult>) {
+        self.pending.insert((to, timestamp), delivery);
+    }
+
+    /// Whether a message to the peer with the timestamp is waiting for the delivery.
+    /// The events report on the messages by timestamp, so such a message can't be told
+    /// apart from another one with the same timestamp.
+    pub fn is_pending(&mut self, to: &str, timestamp: u64) -> bool {
+        self.pending.retain(|_, delivery| !delivery.is_canceled());
+        self.pending.contains_key(&(to.to_string(), timestamp))
+    }
+
+    /// Resolves the deliveries of the messages the event reports on. Failures to dial
+    /// fail the deliveries unless the messages are deposited or relayed instead.
+    pub fn resolve(&mut self, event: &PeerEvent, mailbox: bool, forwarding: bool) {
+        let (timestamp, result) = match &event.event {
+            Event::SentPlainTextMessage { timestamp } => (*timestamp, Ok(Delivery::Sent)),
+            Event::MessageDeposited { timestamp } => (*timestamp, Ok(Delivery::Deposited)),
+            Event::RelayedMessageDelivered { timestamp } => (*timestamp, Ok(Delivery::Relayed)),
+            Event::Error {
+                error: ErrorMessage::MessageValidation { timestamp, cause },
+            } => (*timestamp, Err(DeliveryError::Failed(cause.clone()))),
+            Event::Error {
+                error: ErrorMessage::Mailbox { cause },
+            } => return self.fail_all(&event.peer_id, cause),
+            Event::Error {
+                error: ErrorMessage::FailedToDial { cause },
+            } if !mailbox && !forwarding => return self.fail_all(&event.peer_id, cause),
+            _ => return,
+        };
+        if let Some(delivery) = self.pending.remove(&(event.peer_id.clone(), timestamp)) {
+            let _ = delivery.send(result);
+        }
+    }
+
+    /// Fails the deliveries of all the messages to the peer
+    fn fail_all(&mut self, peer_id: &str, cause: &str) {
+        let keys = self
+            .pending
+            .keys()
+            .filter(|(to, _)| to == peer_id)
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in keys {
+            if let Some(delivery) = self.pending.remove(&key) {
+                let _ = delivery.send(Err(DeliveryError::Failed(cause.to_string())));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_with_the_same_timestamp_is_pending_until_resolved() {
+        let mut deliveries = Deliveries::default();
+        let (delivery, mut result) = oneshot::channel();
+        deliveries.track("bob".to_string(), 1, delivery);
+        assert!(deliveries.is_pending("bob", 1));
+        assert!(!deliveries.is_pending("bob", 2));
+        assert!(!deliveries.is_pending("carol", 1));
+
+        let sent = PeerEvent {
+            peer_id: "bob".to_string(),
+            event: Event::SentPlainTextMessage { timestamp: 1 },
+        };
+        deliveries.resolve(&sent, false, false);
+        assert!(matches!(result.try_recv(), Ok(Some(Ok(Delivery::Sent)))));
+        assert!(!deliveries.is_pending("bob", 1));
+    }
+
+    #[test]
+    fn canceled_delivery_is_not_pending() {
+        let mut deliveries = Deliveries::default();
+        let (delivery, result) = oneshot::channel();
+        deliveries.track("bob".to_string(), 1, delivery);
+        drop(result);
+        assert!(!deliveries.is_pending("bob", 1));
+    }
+}
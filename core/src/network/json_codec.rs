@@ -0,0 +1,92 @@
+//! Json codec for request-response protocols
+
+use async_trait::async_trait;
+use futures::prelude::*;
+use libp2p::core::{
+    upgrade::{read_one, write_one},
+    ProtocolName,
+};
+use libp2p::request_response::RequestResponseCodec;
+use serde::{de::DeserializeOwned, Serialize};
+use std::io;
+use std::marker::PhantomData;
+
+/// Marks the types used by the codec without owning them, so the codec is `Send`
+type Marker<P, Req, Res> = PhantomData<fn() -> (P, Req, Res)>;
+
+/// Codec sending requests and responses as length prefixed json
+pub struct JsonCodec<P, Req, Res> {
+    max_size: usize,
+    _marker: Marker<P, Req, Res>,
+}
+
+impl<P, Req, Res> JsonCodec<P, Req, Res> {
+    /// Creates a codec refusing requests and responses larger than `max_size` bytes
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            max_size,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<P, Req, Res> Clone for JsonCodec<P, Req, Res> {
+    fn clone(&self) -> Self {
+        Self::new(self.max_size)
+    }
+}
+
+async fn read_json<T, R>(io: &mut R, max_size: usize) -> io::Result<T>
+where
+    T: DeserializeOwned,
+    R: AsyncRead + Unpin + Send,
+{
+    let bytes = read_one(io, max_size)
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    serde_json::from_slice(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn to_json<T: Serialize>(value: &T) -> io::Result<Vec<u8>> {
+    serde_json::to_vec(value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[async_trait]
+impl<P, Req, Res> RequestResponseCodec for JsonCodec<P, Req, Res>
+where
+    P: ProtocolName + Send + Sync + Clone,
+    Req: Serialize + DeserializeOwned + Send,
+    Res: Serialize + DeserializeOwned + Send,
+{
+    type Protocol = P;
+    type Request = Req;
+    type Response = Res;
+
+    async fn read_request<T>(&mut self, _: &P, io: &mut T) -> io::Result<Req>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_json(io, self.max_size).await
+    }
+
+    async fn read_response<T>(&mut self, _: &P, io: &mut T) -> io::Result<Res>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_json(io, self.max_size).await
+    }
+
+    async fn write_request<T>(&mut self, _: &P, io: &mut T, request: Req) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_one(io, to_json(&request)?).await
+    }
+
+    async fn write_response<T>(&mut self, _: &P, io: &mut T, response: Res) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_one(io, to_json(&response)?).await
+    }
+}
@@ -0,0 +1,329 @@
+//! Discovery of peers on the local network with mDNS.
+//!
+//! Unlike `libp2p::mdns::Mdns`, advertising and discovery are separate controls:
+//! queries of other peers are answered only while visible, and other peers are queried
+//! and reported only while discovering. Discovered addresses expire regardless of both.
+
+use futures::prelude::*;
+use futures_timer::Delay;
+use libp2p::core::{address_translation, connection::ConnectionId, multiaddr::Protocol};
+use libp2p::mdns::service::{
+    build_query_response, build_service_discovery_response, MdnsPacket, MdnsService,
+};
+use libp2p::swarm::{
+    protocols_handler::DummyProtocolsHandler, NetworkBehaviour, NetworkBehaviourAction,
+    PollParameters, ProtocolsHandler,
+};
+use libp2p::{Multiaddr, PeerId};
+use std::collections::HashMap;
+use std::io;
+use std::iter;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Time the peers keep our addresses
+const RESPONSE_TTL: Duration = Duration::from_secs(5 * 60);
+/// Max number of discovered peers kept. Responses of further peers are ignored until
+/// the known ones expire.
+const MAX_DISCOVERED_PEERS: usize = 256;
+/// Max number of discovered addresses kept per peer
+const MAX_ADDRESSES_PER_PEER: usize = 16;
+
+/// Event of the local discovery
+#[derive(Debug)]
+pub enum LocalDiscoveryEvent {
+    /// Addresses of the peers discovered on the local network
+    Discovered(Vec<(PeerId, Multiaddr)>),
+    /// Addresses that weren't refreshed in time
+    Expired(Vec<(PeerId, Multiaddr)>),
+}
+
+/// `MdnsService::next` takes the service and gives it back with the received packet
+enum Service {
+    Free(Box<MdnsService>),
+    Busy(Pin<Box<dyn Future<Output = (MdnsService, MdnsPacket)> + Send>>),
+}
+
+/// Mode the mDNS service runs in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Stopped,
+    /// Answers the queries only
+    Silent,
+    /// Answers the queries and queries the other peers
+    Querying,
+}
+
+/// Network behaviour advertising the node and discovering the peers on the local network
+pub struct LocalDiscovery {
+    service: Option<Service>,
+    mode: Mode,
+    visible: bool,
+    discover: bool,
+    /// Discovered addresses of the peers and the time they expire at
+    discovered: HashMap<PeerId, Vec<(Multiaddr, Instant)>>,
+    /// Fires when the closest address expires
+    expiration: Option<Delay>,
+}
+
+impl LocalDiscovery {
+    /// Creates the behaviour. The mDNS socket is bound only if `visible` or `discover` is set.
+    pub fn new(visible: bool, discover: bool) -> io::Result<Self> {
+        let mut discovery = LocalDiscovery {
+            service: None,
+            mode: Mode::Stopped,
+            visible,
+            discover,
+            discovered: HashMap::new(),
+            expiration: None,
+        };
+        discovery.restart()?;
+        Ok(discovery)
+    }
+
+    /// Start or stop answering the queries of other peers
+    pub fn set_visible(&mut self, visible: bool) -> io::Result<()> {
+        self.visible = visible;
+        self.restart()
+    }
+
+    /// Start or stop querying and reporting other peers
+    pub fn set_discovery(&mut self, discover: bool) -> io::Result<()> {
+        self.discover = discover;
+        self.restart()
+    }
+
+    /// Restarts the service if it should run in another mode
+    fn restart(&mut self) -> io::Result<()> {
+        let mode = match (self.visible, self.discover) {
+            (_, true) => Mode::Querying,
+            (true, false) => Mode::Silent,
+            (false, false) => Mode::Stopped,
+        };
+        if mode == self.mode {
+            return Ok(());
+        }
+        self.service = match mode {
+            Mode::Querying => Some(Service::Free(Box::new(MdnsService::new()?))),
+            Mode::Silent => Some(Service::Free(Box::new(MdnsService::silent()?))),
+            Mode::Stopped => None,
+        };
+        self.mode = mode;
+        Ok(())
+    }
+
+    fn enqueue_response(&mut self, response: Vec<u8>) {
+        if let Some(Service::Free(service)) = self.service.as_mut() {
+            service.enqueue_response(response);
+        }
+    }
+
+    /// Removes the expired addresses
+    fn expire(&mut self) -> Vec<(PeerId, Multiaddr)> {
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        self.discovered.retain(|peer_id, addresses| {
+            addresses.retain(|(address, expires)| {
+                if *expires <= now {
+                    expired.push((peer_id.clone(), address.clone()));
+                    return false;
+                }
+                true
+            });
+            !addresses.is_empty()
+        });
+        self.reset_expiration(now);
+        expired
+    }
+
+    fn reset_expiration(&mut self, now: Instant) {
+        self.expiration = self
+            .discovered
+            .values()
+            .flatten()
+            .map(|(_, expires)| *expires)
+            .min()
+            .map(|expires| Delay::new(expires.saturating_duration_since(now)));
+    }
+
+    /// Remembers the addresses of the peers in the response
+    fn discovered(
+        &mut self,
+        response: &libp2p::mdns::service::MdnsResponse,
+        local_peer_id: &PeerId,
+    ) -> Vec<(PeerId, Multiaddr)> {
+        // The addresses the peers listen on are translated to the address they're observed at
+        let observed: Multiaddr = iter::once(Protocol::from(response.remote_addr().ip()))
+            .chain(iter::once(Protocol::Udp(response.remote_addr().port())))
+            .collect();
+        let now = Instant::now();
+        let mut discovered = Vec::new();
+        for peer in response.discovered_peers() {
+            if peer.id() == local_peer_id {
+                continue;
+            }
+            let expires = now + peer.ttl();
+            for address in peer.addresses() {
+                let translated = address_translation(address, &observed);
+                for address in translated.into_iter().chain(iter::once(address.clone())) {
+                    if self.remember(peer.id(), &address, expires) {
+                        discovered.push((peer.id().clone(), address));
+                    }
+                }
+            }
+        }
+        self.reset_expiration(now);
+        discovered
+    }
+
+    /// Remembers the address of the peer or extends its expiration.
+    /// Returns false if the address is dropped because of the limits.
+    fn remember(&mut self, peer_id: &PeerId, address: &Multiaddr, expires: Instant) -> bool {
+        if !self.discovered.contains_key(peer_id) && self.discovered.len() >= MAX_DISCOVERED_PEERS {
+            return false;
+        }
+        let addresses = self.discovered.entry(peer_id.clone()).or_default();
+        if let Some((_, known_expires)) = addresses.iter_mut().find(|(known, _)| known == address) {
+            *known_expires = (*known_expires).max(expires);
+            return true;
+        }
+        if addresses.len() >= MAX_ADDRESSES_PER_PEER {
+            return false;
+        }
+        addresses.push((address.clone(), expires));
+        true
+    }
+}
+
+impl NetworkBehaviour for LocalDiscovery {
+    type ProtocolsHandler = DummyProtocolsHandler;
+    type OutEvent = LocalDiscoveryEvent;
+
+    fn new_handler(&mut self) -> Self::ProtocolsHandler {
+        DummyProtocolsHandler::default()
+    }
+
+    fn addresses_of_peer(&mut self, peer_id: &PeerId) -> Vec<Multiaddr> {
+        let now = Instant::now();
+        self.discovered
+            .get(peer_id)
+            .into_iter()
+            .flatten()
+            .filter(|(_, expires)| *expires > now)
+            .map(|(address, _)| address.clone())
+            .collect()
+    }
+
+    fn inject_connected(&mut self, _: &PeerId) {}
+
+    fn inject_disconnected(&mut self, _: &PeerId) {}
+
+    fn inject_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        event: <Self::ProtocolsHandler as ProtocolsHandler>::OutEvent,
+    ) {
+        match event {}
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+        params: &mut impl PollParameters,
+    ) -> Poll<
+        NetworkBehaviourAction<
+            <Self::ProtocolsHandler as ProtocolsHandler>::InEvent,
+            Self::OutEvent,
+        >,
+    > {
+        if let Some(expiration) = self.expiration.as_mut() {
+            if expiration.poll_unpin(cx).is_ready() {
+                let expired = self.expire();
+                if !expired.is_empty() {
+                    return Poll::Ready(NetworkBehaviourAction::GenerateEvent(
+                        LocalDiscoveryEvent::Expired(expired),
+                    ));
+                }
+            }
+        }
+        loop {
+            let packet = match self.service.take() {
+                None => return Poll::Pending,
+                Some(Service::Free(service)) => {
+                    self.service = Some(Service::Busy(Box::pin(service.next())));
+                    continue;
+                }
+                Some(Service::Busy(mut next)) => match next.as_mut().poll(cx) {
+                    Poll::Ready((service, packet)) => {
+                        self.service = Some(Service::Free(Box::new(service)));
+                        packet
+                    }
+                    Poll::Pending => {
+                        self.service = Some(Service::Busy(next));
+                        return Poll::Pending;
+                    }
+                },
+            };
+            match packet {
+                MdnsPacket::Query(query) if self.visible => {
+                    match build_query_response(
+                        query.query_id(),
+                        params.local_peer_id().clone(),
+                        params.listened_addresses(),
+                        RESPONSE_TTL,
+                    ) {
+                        Ok(response) => self.enqueue_response(response),
+                        Err(e) => log::warn!("Error building mDNS response: {}", e),
+                    }
+                }
+                MdnsPacket::ServiceDiscovery(query) if self.visible => {
+                    let response = build_service_discovery_response(query.query_id(), RESPONSE_TTL);
+                    self.enqueue_response(response);
+                }
+                MdnsPacket::Response(response) if self.discover => {
+                    let discovered = self.discovered(&response, params.local_peer_id());
+                    if !discovered.is_empty() {
+                        return Poll::Ready(NetworkBehaviourAction::GenerateEvent(
+                            LocalDiscoveryEvent::Discovered(discovered),
+                        ));
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(port: u16) -> Multiaddr {
+        format!("/ip4/192.168.0.1/tcp/{}", port).parse().unwrap()
+    }
+
+    #[test]
+    fn discovered_peers_and_addresses_are_bounded() {
+        let mut discovery = LocalDiscovery::new(false, false).unwrap();
+        let expires = Instant::now() + RESPONSE_TTL;
+        let peer_id = PeerId::random();
+        for port in 0..MAX_ADDRESSES_PER_PEER as u16 {
+            assert!(discovery.remember(&peer_id, &address(port), expires));
+        }
+        assert!(!discovery.remember(&peer_id, &address(1000), expires));
+        // Known addresses are still refreshed
+        assert!(discovery.remember(&peer_id, &address(0), expires));
+        assert_eq!(
+            discovery.addresses_of_peer(&peer_id).len(),
+            MAX_ADDRESSES_PER_PEER
+        );
+
+        while discovery.discovered.len() < MAX_DISCOVERED_PEERS {
+            assert!(discovery.remember(&PeerId::random(), &address(0), expires));
+        }
+        assert!(!discovery.remember(&PeerId::random(), &address(0), expires));
+        assert!(discovery.remember(&peer_id, &address(0), expires));
+    }
+}
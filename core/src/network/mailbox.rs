@@ -0,0 +1,15 @@
+//! Store-and-forward mailbox for offline peers.
+//!
+//! A mailbox is an always-on peer holding envelopes for the peers registered with it.
+//! Messages that can't be delivered directly are deposited to the mailbox, recipients
+//! fetch and acknowledge them once they are online.
+mod protocol;
+mod seal;
+mod server;
+mod store;
+
+pub use protocol::{
+    Envelope, MailboxCodec, MailboxProtocol, MailboxRequest, MailboxResponse, StoredEnvelope,
+};
+pub use seal::{open_payload, seal_payload};
+pub use server::MailboxServer;
@@ -0,0 +1,72 @@
+use crate::network::json_codec::JsonCodec;
+use libp2p::core::ProtocolName;
+use serde::{Deserialize, Serialize};
+
+/// Envelope deposited to the mailbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    /// Base58 peer id of the recipient
+    pub to: String,
+    /// Serialized message signed by the sender and sealed to the key of the recipient.
+    /// The mailbox treats it as opaque data and can't read it.
+    pub payload: String,
+}
+
+/// Envelope held by the mailbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredEnvelope {
+    /// Id of the envelope used for acknowledgement
+    pub id: u64,
+    /// Base58 peer id of the sender, as authenticated by the mailbox. The recipient
+    /// verifies it against the signature in the payload.
+    pub from: String,
+    /// Serialized message signed by the sender and sealed to the key of the recipient
+    pub payload: String,
+}
+
+/// Request sent to the mailbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum MailboxRequest {
+    /// Hold envelopes addressed to the requesting peer
+    Register,
+    /// Hold the envelope until the recipient fetches it
+    Deposit { envelope: Envelope },
+    /// Get envelopes addressed to the requesting peer. The response is limited to
+    /// `max_size` bytes so that it fits into the frame the peer accepts.
+    Fetch {
+        #[serde(default)]
+        max_size: Option<usize>,
+    },
+    /// Remove delivered envelopes
+    Ack { ids: Vec<u64> },
+}
+
+/// Response of the mailbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum MailboxResponse {
+    /// The peer is registered
+    Registered,
+    /// The envelope is stored
+    Deposited { id: u64 },
+    /// Envelopes addressed to the requesting peer
+    Envelopes { envelopes: Vec<StoredEnvelope> },
+    /// The envelopes are removed
+    Acked,
+    /// The request is refused
+    Error { cause: String },
+}
+
+/// Mailbox protocol name
+#[derive(Debug, Clone)]
+pub struct MailboxProtocol;
+
+impl ProtocolName for MailboxProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        b"/tata/mailbox/1.0.0"
+    }
+}
+
+/// Json codec for mailbox requests and responses
+pub type MailboxCodec = JsonCodec<MailboxProtocol, MailboxRequest, MailboxResponse>;
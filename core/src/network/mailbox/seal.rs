@@ -0,0 +1,163 @@
+//! End-to-end encryption and signatures of the envelope payloads.
+//!
+//! The payload is encrypted with ChaCha20-Poly1305. The key is agreed with ECDH between
+//! a random ephemeral secp256k1 key and the key the recipient's peer id is derived from,
+//! so only the recipient can open the envelope. The sealed payload is base64 of
+//! `ephemeral public key (33 bytes) | nonce (12 bytes) | ciphertext`.
+//!
+//! The mailbox can't be trusted to report the sender, so the sender signs the payload
+//! and the recipient verifies the signature against the key inlined into the sender's
+//! peer id. The plaintext is `signature length (1 byte) | signature | payload`.
+use crate::error::{Error, Result};
+use crate::utils::public_key_of;
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use libp2p::identity::{secp256k1::Keypair, PublicKey};
+use libp2p::PeerId;
+use rand::RngCore;
+use sha2::Sha256;
+use std::convert::TryFrom;
+
+const PUBLIC_KEY_SIZE: usize = 33;
+const NONCE_SIZE: usize = 12;
+const ENVELOPE_DOMAIN: &[u8] = b"tata/mailbox-envelope";
+
+/// Signs the payload with the key of the sender and encrypts it to the key of the recipient
+pub fn seal_payload(key: &Keypair, recipient: &PeerId, payload: &[u8]) -> Result<String> {
+    let public_key = recipient_key(recipient)?;
+    let signature = key
+        .secret()
+        .sign(&signed_bytes(recipient, payload))
+        .map_err(|e| Error::Msg(format!("Failed to sign the envelope: {}", e)))?;
+    let mut plaintext = Vec::with_capacity(1 + signature.len() + payload.len());
+    plaintext.push(signature.len() as u8);
+    plaintext.extend_from_slice(&signature);
+    plaintext.extend_from_slice(payload);
+    let ephemeral = secp256k1::SecretKey::random(&mut rand::thread_rng());
+    let cipher = cipher(&public_key, &ephemeral)?;
+    let mut nonce = [0u8; NONCE_SIZE];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(&Nonce::from(nonce), plaintext.as_slice())
+        .map_err(|_| "Failed to encrypt the envelope")?;
+    let mut sealed = Vec::with_capacity(PUBLIC_KEY_SIZE + NONCE_SIZE + ciphertext.len());
+    sealed.extend_from_slice(
+        &secp256k1::PublicKey::from_secret_key(&ephemeral).serialize_compressed(),
+    );
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(base64::encode(sealed))
+}
+
+/// Decrypts the payload sealed to the local key and checks that it's signed by the sender
+pub fn open_payload(key: &Keypair, sender: &PeerId, sealed: &str) -> Result<Vec<u8>> {
+    let sealed = base64::decode(sealed).map_err(|e| Error::Msg(e.to_string()))?;
+    if sealed.len() < PUBLIC_KEY_SIZE + NONCE_SIZE {
+        return Err("Sealed envelope is too short".into());
+    }
+    let (public_key, rest) = sealed.split_at(PUBLIC_KEY_SIZE);
+    let (nonce, ciphertext) = rest.split_at(NONCE_SIZE);
+    let nonce = <[u8; NONCE_SIZE]>::try_from(nonce).map_err(|_| "Invalid nonce")?;
+    let public_key = secp256k1::PublicKey::parse_slice(public_key, None)
+        .map_err(|e| Error::Msg(format!("Invalid ephemeral key: {:?}", e)))?;
+    let secret = secp256k1::SecretKey::parse(&key.secret().to_bytes())
+        .map_err(|e| Error::Msg(format!("Invalid secret key: {:?}", e)))?;
+    let plaintext = cipher(&public_key, &secret)?
+        .decrypt(&Nonce::from(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt the envelope")?;
+    let (signature, payload) = match plaintext.split_first() {
+        Some((&len, rest)) if rest.len() >= len as usize => rest.split_at(len as usize),
+        _ => return Err("Envelope isn't signed".into()),
+    };
+    let recipient = PeerId::from_public_key(PublicKey::Secp256k1(key.public().clone()));
+    if !public_key_of(sender)?.verify(&signed_bytes(&recipient, payload), signature) {
+        return Err(format!("Envelope isn't signed by {}", sender).into());
+    }
+    Ok(payload.to_vec())
+}
+
+/// Length prefixed fields, so that the fields can't be shifted into each other
+fn signed_bytes(recipient: &PeerId, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for field in &[ENVELOPE_DOMAIN, recipient.as_bytes(), payload] {
+        bytes.extend_from_slice(&(field.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(field);
+    }
+    bytes
+}
+
+fn cipher(
+    public_key: &secp256k1::PublicKey,
+    secret: &secp256k1::SecretKey,
+) -> Result<ChaCha20Poly1305> {
+    let shared = secp256k1::SharedSecret::<Sha256>::new(public_key, secret)
+        .map_err(|e| Error::Msg(format!("Key agreement failed: {:?}", e)))?;
+    let mut key = [0u8; 32];
+    key.copy_from_slice(shared.as_ref());
+    Ok(ChaCha20Poly1305::new(&Key::from(key)))
+}
+
+/// Extracts the secp256k1 key inlined into the peer id
+fn recipient_key(peer_id: &PeerId) -> Result<secp256k1::PublicKey> {
+    match public_key_of(peer_id)? {
+        PublicKey::Secp256k1(public_key) => {
+            secp256k1::PublicKey::parse_compressed(&public_key.encode())
+                .map_err(|e| Error::Msg(format!("Invalid public key: {:?}", e)))
+        }
+        _ => Err(format!("Peer id {} doesn't contain a secp256k1 key", peer_id).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer_id(key: &Keypair) -> PeerId {
+        PeerId::from_public_key(PublicKey::Secp256k1(key.public().clone()))
+    }
+
+    #[test]
+    fn only_the_recipient_opens_the_envelope() {
+        let sender = Keypair::generate();
+        let recipient = Keypair::generate();
+        let sealed = seal_payload(&sender, &peer_id(&recipient), b"hello").unwrap();
+        assert!(!sealed.contains("hello"));
+        assert_eq!(
+            open_payload(&recipient, &peer_id(&sender), &sealed).unwrap(),
+            b"hello"
+        );
+
+        let other = Keypair::generate();
+        assert!(open_payload(&other, &peer_id(&sender), &sealed).is_err());
+    }
+
+    #[test]
+    fn envelope_is_verified_against_the_sender() {
+        let sender = Keypair::generate();
+        let recipient = Keypair::generate();
+        let sealed = seal_payload(&sender, &peer_id(&recipient), b"hello").unwrap();
+        // E.g. the mailbox reports another sender
+        let forger = Keypair::generate();
+        assert!(open_payload(&recipient, &peer_id(&forger), &sealed).is_err());
+        let forged = seal_payload(&forger, &peer_id(&recipient), b"hello").unwrap();
+        assert!(open_payload(&recipient, &peer_id(&sender), &forged).is_err());
+    }
+
+    #[test]
+    fn tampered_envelope_is_refused() {
+        let sender = Keypair::generate();
+        let recipient = Keypair::generate();
+        let sealed = seal_payload(&sender, &peer_id(&recipient), b"hello").unwrap();
+        let mut sealed = base64::decode(sealed).unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        let sender = peer_id(&sender);
+        assert!(open_payload(&recipient, &sender, &base64::encode(sealed)).is_err());
+        assert!(open_payload(&recipient, &sender, "AAAA").is_err());
+    }
+
+    #[test]
+    fn peer_id_without_a_key_is_refused() {
+        assert!(seal_payload(&Keypair::generate(), &PeerId::random(), b"hello").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use super::protocol::{MailboxCodec, MailboxProtocol, MailboxRequest, MailboxResponse};
+use super::store::MailboxStore;
+use crate::config::MailboxServerConfig;
+use crate::error::Result;
+use crate::utils::decode_peer_id;
+use libp2p::{
+    ping::{Ping, PingConfig, PingEvent},
+    request_response::{
+        ProtocolSupport, RequestResponse, RequestResponseConfig, RequestResponseEvent,
+        RequestResponseMessage,
+    },
+    swarm::NetworkBehaviourEventProcess,
+    NetworkBehaviour, PeerId,
+};
+use std::collections::HashSet;
+use std::iter;
+
+/// Room left in the response for the json framing around the envelopes
+const RESPONSE_OVERHEAD: usize = 64;
+
+/// Network behaviour of the mailbox node
+#[derive(NetworkBehaviour)]
+pub struct MailboxServer {
+    /// Answers the pings of the nodes, which otherwise close the connection
+    ping: Ping,
+    request_response: RequestResponse<MailboxCodec>,
+    #[behaviour(ignore)]
+    store: MailboxStore,
+    /// Peers allowed to register, any peer if empty
+    #[behaviour(ignore)]
+    allowed_peers: HashSet<PeerId>,
+    #[behaviour(ignore)]
+    max_envelope_size: usize,
+    #[behaviour(ignore)]
+    max_response_size: usize,
+}
+
+impl MailboxServer {
+    pub fn new(config: &MailboxServerConfig) -> Result<Self> {
+        let store = MailboxStore::open(
+            config.store_path.clone(),
+            config.max_envelopes_per_peer,
+            config.max_envelopes_per_sender,
+        )?;
+        let allowed_peers = config
+            .allowed_peers
+            .iter()
+            .map(|peer_id| decode_peer_id(peer_id))
+            .collect::<Result<_>>()?;
+        let request_response = RequestResponse::new(
+            MailboxCodec::new(config.max_frame_size),
+            iter::once((MailboxProtocol, ProtocolSupport::Full)),
+            RequestResponseConfig::default(),
+        );
+        Ok(MailboxServer {
+            ping: Ping::new(PingConfig::new()),
+            request_response,
+            store,
+            allowed_peers,
+            max_envelope_size: config.max_envelope_size,
+            max_response_size: config.max_frame_size,
+        })
+    }
+
+    fn handle_request(&mut self, peer_id: &PeerId, request: MailboxRequest) -> MailboxResponse {
+        let result = match request {
+            MailboxRequest::Register => {
+                if !self.allowed_peers.is_empty() && !self.allowed_peers.contains(peer_id) {
+                    return MailboxResponse::Error {
+                        cause: "Peer is not allowed to register".to_string(),
+                    };
+                }
+                log::info!("Registering peer {}", peer_id);
+                self.store
+                    .register(peer_id)
+                    .map(|_| MailboxResponse::Registered)
+            }
+            MailboxRequest::Deposit { envelope } => {
+                if envelope.payload.len() > self.max_envelope_size {
+                    return MailboxResponse::Error {
+                        cause: format!(
+                            "Envelope of {} bytes exceeds the limit of {} bytes",
+                            envelope.payload.len(),
+                            self.max_envelope_size
+                        ),
+                    };
+                }
+                log::debug!("Deposit from {} to {}", peer_id, envelope.to);
+                self.store
+                    .deposit(peer_id, envelope)
+                    .map(|id| MailboxResponse::Deposited { id })
+            }
+            MailboxRequest::Fetch { max_size } => {
+                let max_size = max_size
+                    .map_or(self.max_response_size, |size| {
+                        size.min(self.max_response_size)
+                    })
+                    .saturating_sub(RESPONSE_OVERHEAD);
+                Ok(MailboxResponse::Envelopes {
+                    envelopes: self.store.fetch(peer_id, max_size),
+                })
+            }
+            MailboxRequest::Ack { ids } => self
+                .store
+                .ack(peer_id, &ids)
+                .map(|_| MailboxResponse::Acked),
+        };
+        result.unwrap_or_else(|e| MailboxResponse::Error {
+            cause: e.to_string(),
+        })
+    }
+}
+
+impl NetworkBehaviourEventProcess<RequestResponseEvent<MailboxRequest, MailboxResponse>>
+    for MailboxServer
+{
+    fn inject_event(&mut self, event: RequestResponseEvent<MailboxRequest, MailboxResponse>) {
+        match event {
+            RequestResponseEvent::Message {
+                peer,
+                message:
+                    RequestResponseMessage::Request {
+                        request, channel, ..
+                    },
+            } => {
+                let response = self.handle_request(&peer, request);
+                self.request_response.send_response(channel, response);
+            }
+            RequestResponseEvent::Message {
+                peer,
+                message: RequestResponseMessage::Response { .. },
+            } => log::warn!("Unexpected response from {}", peer),
+            RequestResponseEvent::OutboundFailure { peer, error, .. } => {
+                log::error!("Outbound failure with {}: {:?}", peer, error)
+            }
+            RequestResponseEvent::InboundFailure { peer, error, .. } => {
+                log::error!("Inbound failure with {}: {:?}", peer, error)
+            }
+        }
+    }
+}
+
+impl NetworkBehaviourEventProcess<PingEvent> for MailboxServer {
+    fn inject_event(&mut self, _: PingEvent) {}
+}
@@ -0,0 +1,334 @@
+use super::protocol::{Envelope, StoredEnvelope};
+use crate::error::{Error, Result};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::iter;
+use std::path::PathBuf;
+
+/// The file is compacted once it holds this many records more than needed for the state
+const MAX_STALE_RECORDS: usize = 1000;
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    registered: HashSet<String>,
+    envelopes: HashMap<String, Vec<StoredEnvelope>>,
+}
+
+/// Change of the state appended to the store file as a line of json
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum Record {
+    /// Keeps the ids of the acknowledged envelopes from being reused
+    NextId {
+        id: u64,
+    },
+    Register {
+        peer_id: String,
+    },
+    Deposit {
+        to: String,
+        envelope: StoredEnvelope,
+    },
+    Ack {
+        peer_id: String,
+        ids: Vec<u64>,
+    },
+}
+
+impl State {
+    fn apply(&mut self, record: Record) {
+        match record {
+            Record::NextId { id } => self.next_id = self.next_id.max(id),
+            Record::Register { peer_id } => {
+                self.registered.insert(peer_id);
+            }
+            Record::Deposit { to, envelope } => {
+                self.next_id = self.next_id.max(envelope.id + 1);
+                self.envelopes.entry(to).or_default().push(envelope);
+            }
+            Record::Ack { peer_id, ids } => {
+                if let Some(envelopes) = self.envelopes.get_mut(&peer_id) {
+                    envelopes.retain(|envelope| !ids.contains(&envelope.id));
+                    if envelopes.is_empty() {
+                        self.envelopes.remove(&peer_id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Records recreating the state
+    fn records(&self) -> impl Iterator<Item = Record> + '_ {
+        let next_id = iter::once(Record::NextId { id: self.next_id });
+        let registered = self.registered.iter().map(|peer_id| Record::Register {
+            peer_id: peer_id.clone(),
+        });
+        let deposits = self.envelopes.iter().flat_map(|(to, envelopes)| {
+            envelopes.iter().map(move |envelope| Record::Deposit {
+                to: to.clone(),
+                envelope: envelope.clone(),
+            })
+        });
+        next_id.chain(registered).chain(deposits)
+    }
+
+    /// Number of the records recreating the state
+    fn len(&self) -> usize {
+        1 + self.registered.len() + self.envelopes.values().map(Vec::len).sum::<usize>()
+    }
+}
+
+/// Envelopes held by the mailbox, optionally persisted to a file.
+///
+/// The changes are appended to the file, one json record per line, and the file is
+/// rewritten with the current state only once most of the records are stale.
+pub struct MailboxStore {
+    path: Option<PathBuf>,
+    file: Option<File>,
+    /// Number of records in the file
+    records: usize,
+    max_envelopes_per_peer: usize,
+    max_envelopes_per_sender: usize,
+    state: State,
+}
+
+impl MailboxStore {
+    /// Opens the store. The state is loaded from `path` if the file exists.
+    pub fn open(
+        path: Option<PathBuf>,
+        max_envelopes_per_peer: usize,
+        max_envelopes_per_sender: usize,
+    ) -> Result<Self> {
+        let mut state = State::default();
+        if let Some(path) = path.as_ref().filter(|path| path.exists()) {
+            for line in BufReader::new(File::open(path)?).lines() {
+                match serde_json::from_str(&line?) {
+                    Ok(record) => state.apply(record),
+                    // The last record is cut if the mailbox stopped while writing it
+                    Err(e) => log::warn!("Skipping malformed mailbox record: {}", e),
+                }
+            }
+        }
+        let mut store = MailboxStore {
+            path,
+            file: None,
+            records: 0,
+            max_envelopes_per_peer,
+            max_envelopes_per_sender,
+            state,
+        };
+        store.compact()?;
+        Ok(store)
+    }
+
+    /// Starts holding envelopes for the peer
+    pub fn register(&mut self, peer_id: &PeerId) -> Result<()> {
+        let peer_id = peer_id.to_base58();
+        if !self.state.registered.contains(&peer_id) {
+            self.apply(Record::Register { peer_id })?;
+        }
+        Ok(())
+    }
+
+    /// Stores the envelope from the peer. Returns id of the stored envelope.
+    pub fn deposit(&mut self, from: &PeerId, envelope: Envelope) -> Result<u64> {
+        if !self.state.registered.contains(&envelope.to) {
+            return Err(Error::Msg(format!(
+                "Peer {} is not registered with the mailbox",
+                envelope.to
+            )));
+        }
+        let from = from.to_base58();
+        let envelopes = self
+            .state
+            .envelopes
+            .get(&envelope.to)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if envelopes.len() >= self.max_envelopes_per_peer {
+            return Err("Mailbox of the peer is full".into());
+        }
+        let from_sender = envelopes
+            .iter()
+            .filter(|stored| stored.from == from)
+            .count();
+        if from_sender >= self.max_envelopes_per_sender {
+            return Err("Too many envelopes for the peer from the sender".into());
+        }
+        let id = self.state.next_id;
+        self.apply(Record::Deposit {
+            to: envelope.to,
+            envelope: StoredEnvelope {
+                id,
+                from,
+                payload: envelope.payload,
+            },
+        })?;
+        Ok(id)
+    }
+
+    /// Returns the oldest envelopes addressed to the peer, up to `max_size` bytes
+    /// of serialized envelopes
+    pub fn fetch(&self, peer_id: &PeerId, max_size: usize) -> Vec<StoredEnvelope> {
+        let mut size: usize = 0;
+        self.state
+            .envelopes
+            .get(&peer_id.to_base58())
+            .map(|envelopes| {
+                envelopes
+                    .iter()
+                    .take_while(|envelope| {
+                        // The envelope and the separating comma
+                        size = size.saturating_add(
+                            serde_json::to_vec(envelope).map_or(usize::MAX, |v| v.len() + 1),
+                        );
+                        size <= max_size
+                    })
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Removes the envelopes delivered to the peer
+    pub fn ack(&mut self, peer_id: &PeerId, ids: &[u64]) -> Result<()> {
+        let peer_id = peer_id.to_base58();
+        let delivered = self
+            .state
+            .envelopes
+            .get(&peer_id)
+            .into_iter()
+            .flatten()
+            .any(|envelope| ids.contains(&envelope.id));
+        if delivered {
+            self.apply(Record::Ack {
+                peer_id,
+                ids: ids.to_vec(),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Appends the record to the file and applies it to the state
+    fn apply(&mut self, record: Record) -> Result<()> {
+        if let Some(file) = self.file.as_mut() {
+            let mut line = serde_json::to_vec(&record)?;
+            line.push(b'\n');
+            file.write_all(&line)?;
+            self.records += 1;
+        }
+        self.state.apply(record);
+        if self.records > self.state.len() + MAX_STALE_RECORDS {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Rewrites the file with the records of the current state
+    fn compact(&mut self) -> Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        // Write to a temporary file first so that a crash doesn't corrupt the store
+        let tmp_path = path.with_extension("tmp");
+        let mut contents = Vec::new();
+        let mut records = 0;
+        for record in self.state.records() {
+            serde_json::to_writer(&mut contents, &record)?;
+            contents.push(b'\n');
+            records += 1;
+        }
+        fs::write(&tmp_path, contents)?;
+        fs::rename(tmp_path, path)?;
+        self.file = Some(OpenOptions::new().append(true).open(path)?);
+        self.records = records;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn envelope(to: &PeerId, payload: &str) -> Envelope {
+        Envelope {
+            to: to.to_base58(),
+            payload: payload.to_string(),
+        }
+    }
+
+    #[test]
+    fn fetch_pages_by_serialized_size() {
+        let (sender, recipient) = (PeerId::random(), PeerId::random());
+        let mut store = MailboxStore::open(None, 10, 10).unwrap();
+        store.register(&recipient).unwrap();
+        // Quotes are escaped, so the serialized envelope is larger than the payload
+        for _ in 0..3 {
+            store
+                .deposit(&sender, envelope(&recipient, &"\"".repeat(100)))
+                .unwrap();
+        }
+        let size = serde_json::to_vec(&store.fetch(&recipient, usize::MAX)[0])
+            .unwrap()
+            .len();
+        assert!(size > 200);
+
+        let page = store.fetch(&recipient, 2 * (size + 1));
+        assert_eq!(page.len(), 2);
+        assert_eq!(store.fetch(&recipient, 2 * (size + 1) - 1).len(), 1);
+        assert!(store.fetch(&recipient, size).is_empty());
+
+        store.ack(&recipient, &[page[0].id, page[1].id]).unwrap();
+        assert_eq!(store.fetch(&recipient, usize::MAX).len(), 1);
+    }
+
+    #[test]
+    fn deposit_requires_registration_and_room() {
+        let (sender, recipient) = (PeerId::random(), PeerId::random());
+        let mut store = MailboxStore::open(None, 1, 1).unwrap();
+        assert!(store.deposit(&sender, envelope(&recipient, "a")).is_err());
+        store.register(&recipient).unwrap();
+        store.deposit(&sender, envelope(&recipient, "a")).unwrap();
+        assert!(store.deposit(&sender, envelope(&recipient, "b")).is_err());
+    }
+
+    #[test]
+    fn store_is_restored_from_the_appended_records() {
+        let path =
+            std::env::temp_dir().join(format!("tata-mailbox-{}.jsonl", rand::random::<u64>()));
+        let (sender, recipient) = (PeerId::random(), PeerId::random());
+        let mut store = MailboxStore::open(Some(path.clone()), 10, 10).unwrap();
+        store.register(&recipient).unwrap();
+        let first = store.deposit(&sender, envelope(&recipient, "a")).unwrap();
+        let second = store.deposit(&sender, envelope(&recipient, "b")).unwrap();
+        store.ack(&recipient, &[second]).unwrap();
+        // The initial state, the registration, the deposits and the acknowledgement
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 5);
+        drop(store);
+
+        let mut store = MailboxStore::open(Some(path.clone()), 10, 10).unwrap();
+        let envelopes = store.fetch(&recipient, usize::MAX);
+        assert_eq!(envelopes.len(), 1);
+        assert_eq!(envelopes[0].id, first);
+        assert_eq!(envelopes[0].payload, "a");
+        // The ids of the acknowledged envelopes aren't reused
+        assert!(store.deposit(&sender, envelope(&recipient, "c")).unwrap() > second);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn sender_fills_only_its_share_of_the_mailbox() {
+        let (spammer, friend, recipient) = (PeerId::random(), PeerId::random(), PeerId::random());
+        let mut store = MailboxStore::open(None, 3, 2).unwrap();
+        store.register(&recipient).unwrap();
+        store.deposit(&spammer, envelope(&recipient, "a")).unwrap();
+        store.deposit(&spammer, envelope(&recipient, "b")).unwrap();
+        assert!(store.deposit(&spammer, envelope(&recipient, "c")).is_err());
+        store.deposit(&friend, envelope(&recipient, "hi")).unwrap();
+    }
+}
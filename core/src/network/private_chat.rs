@@ -1,6 +1,9 @@
 mod behavior;
+mod codec;
 mod handler;
 mod protocol;
+mod relay;
+mod scoring;
 
 pub use behavior::PrivateChatBehaviour;
 pub use protocol::HandshakeMetadata;
@@ -1,50 +1,557 @@
 //! Contains network behavior for private chat
 
 use super::{
-    handler::{InEvent, PrivateChatHandler},
-    protocol::HandshakeMetadata,
+    handler::{DisconnectReason, InEvent, OutEvent, PrivateChatHandler},
+    protocol::{Frame, HandshakeMetadata},
+    relay::{sign_relay, sign_relay_ack, verify_relay, verify_relay_ack},
+    scoring::{Misbehaviour, PeerScores},
 };
-use crate::error::Result;
+use crate::config::{ForwardingConfig, Limits, ScoringConfig};
+use crate::error::{Error, LimitExceeded, Result};
+use crate::utils::{decode_peer_id, unix_timestamp};
+use futures::FutureExt;
+use futures_timer::Delay;
 use libp2p::{
-    core::connection::ConnectionId,
+    core::{
+        connection::{ConnectionId, ListenerId},
+        ConnectedPoint,
+    },
+    identity::Keypair,
     swarm::DialPeerCondition,
     swarm::{
         NetworkBehaviour, NetworkBehaviourAction, NotifyHandler, PollParameters, ProtocolsHandler,
     },
     Multiaddr, PeerId,
 };
-use primitives::{ErrorMessage, Event, PeerEvent, PlainTextMessage};
+use primitives::{ErrorMessage, Event, PeerEvent, PlainTextMessage, PresenceStatus};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Max interval between expiring bans and cleaning up peer scores
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60);
+/// Max number of addresses kept for a single peer
+const MAX_ADDRESSES_PER_PEER: usize = 8;
 
 /// Network behaviour for private chat
 pub struct PrivateChatBehaviour {
+    /// Key signing the relayed messages and their delivery confirmations
+    local_key: Keypair,
+    local_peer_id: PeerId,
     local_metadata: HandshakeMetadata,
+    limits: Limits,
+    scores: PeerScores,
+    maintenance: Delay,
     pending_events: VecDeque<PeerEvent>,
-    pending_messages: VecDeque<(PeerId, PlainTextMessage)>,
-    pending_connections: HashMap<PeerId, Vec<(PeerId, PlainTextMessage)>>,
+    pending_frames: VecDeque<(PeerId, Frame)>,
+    pending_connections: HashMap<PeerId, Vec<Frame>>,
+    /// Errors of the failed attempts to reach the peers being dialed
+    dial_errors: HashMap<PeerId, Vec<String>>,
+    /// Connections to be closed. `None` closes all connections with the peer.
+    pending_disconnects: VecDeque<(PeerId, Option<ConnectionId>, DisconnectReason)>,
+    /// Connected peers to be notified about the changed local profile
+    pending_profile_updates: VecDeque<PeerId>,
     connected: HashSet<PeerId>,
+    /// Known addresses of the peers, used to dial them when they can't be discovered,
+    /// e.g. in invisible mode
+    addresses: HashMap<PeerId, Vec<Multiaddr>>,
+    blocked: HashSet<PeerId>,
+    /// Allowlist, i.e. contacts
+    allowed: HashSet<PeerId>,
+    contacts_only: bool,
+    /// Local presence status announced to contacts
+    presence: PresenceStatus,
+    /// Peers we sent contact requests to
+    outgoing_requests: HashSet<PeerId>,
+    /// Peers that sent us contact requests
+    incoming_requests: HashSet<PeerId>,
+    /// Messages waiting for the contact request to be accepted
+    awaiting_acceptance: HashMap<PeerId, Vec<PlainTextMessage>>,
+    /// Messages to peers that couldn't be dialed
+    undelivered: HashMap<PeerId, Vec<PlainTextMessage>>,
+    forwarding: ForwardingConfig,
+    /// Ids of recently seen relayed messages, oldest first
+    seen_relays: VecDeque<String>,
+    /// Previous hops of the relayed messages, used to send the delivery confirmations back
+    relay_routes: HashMap<String, Option<PeerId>>,
+    /// Recipients and timestamps of our messages sent through relays
+    relayed: HashMap<String, (PeerId, u64)>,
 }
 
 impl PrivateChatBehaviour {
     /// Create new behaviour
-    pub fn new(local_metadata: HandshakeMetadata) -> Self {
+    pub fn new(
+        local_key: Keypair,
+        local_metadata: HandshakeMetadata,
+        limits: Limits,
+        scoring: ScoringConfig,
+        forwarding: ForwardingConfig,
+    ) -> Self {
         Self {
+            scores: PeerScores::new(scoring),
+            maintenance: Delay::new(MAINTENANCE_INTERVAL),
             pending_events: VecDeque::new(),
-            pending_messages: VecDeque::new(),
+            pending_frames: VecDeque::new(),
             pending_connections: HashMap::new(),
+            dial_errors: HashMap::new(),
+            pending_disconnects: VecDeque::new(),
+            pending_profile_updates: VecDeque::new(),
             connected: HashSet::new(),
+            addresses: HashMap::new(),
+            blocked: HashSet::new(),
+            allowed: HashSet::new(),
+            contacts_only: true,
+            presence: PresenceStatus::Online,
+            outgoing_requests: HashSet::new(),
+            incoming_requests: HashSet::new(),
+            awaiting_acceptance: HashMap::new(),
+            undelivered: HashMap::new(),
+            seen_relays: VecDeque::new(),
+            relay_routes: HashMap::new(),
+            relayed: HashMap::new(),
+            forwarding,
+            local_peer_id: local_key.public().into_peer_id(),
+            local_key,
             local_metadata,
+            limits,
         }
     }
 
-    /// Send message to peer
+    /// Send message to peer. The message is sent only to contacts. If a contact request
+    /// to the peer is pending, the message is sent once the request is accepted.
     pub fn send_message(&mut self, message: PlainTextMessage) -> Result<()> {
-        let peer_bytes = bs58::decode(message.to.clone()).into_vec()?;
-        let peer_id = PeerId::from_bytes(peer_bytes)?;
-        self.pending_messages.push_back((peer_id, message));
+        if message.text.len() > self.limits.max_message_length {
+            return Err(LimitExceeded(format!(
+                "Message of {} bytes exceeds the limit of {} bytes",
+                message.text.len(),
+                self.limits.max_message_length
+            ))
+            .into());
+        }
+        let peer_id = decode_peer_id(&message.to)?;
+        if self.blocked.contains(&peer_id) {
+            return Err(Error::Msg(format!("Peer {} is blocked", message.to)));
+        }
+        if !self.allowed.contains(&peer_id) {
+            if !self.outgoing_requests.contains(&peer_id) {
+                return Err(Error::Msg(format!(
+                    "Peer {} is not a contact, send a contact request first",
+                    message.to
+                )));
+            }
+            self.awaiting_acceptance
+                .entry(peer_id)
+                .or_default()
+                .push(message);
+            return Ok(());
+        }
+        self.pending_frames
+            .push_back((peer_id, Frame::Message { message }));
+        Ok(())
+    }
+
+    /// Ask the peer to become a contact. `note` is a short introduction shown to the peer.
+    pub fn send_contact_request(&mut self, peer_id: PeerId, note: String) -> Result<()> {
+        if note.len() > self.limits.max_message_length {
+            return Err(LimitExceeded(format!(
+                "Note of {} bytes exceeds the limit of {} bytes",
+                note.len(),
+                self.limits.max_message_length
+            ))
+            .into());
+        }
+        if self.blocked.contains(&peer_id) {
+            return Err(Error::Msg(format!("Peer {} is blocked", peer_id)));
+        }
+        self.outgoing_requests.insert(peer_id.clone());
+        let frame = Frame::ContactRequest {
+            metadata: self.local_metadata.clone(),
+            note,
+        };
+        self.pending_frames.push_back((peer_id, frame));
+        Ok(())
+    }
+
+    /// Accept the contact request received from the peer
+    pub fn accept_contact_request(&mut self, peer_id: PeerId) -> Result<()> {
+        if !self.incoming_requests.remove(&peer_id) {
+            return Err(Error::Msg(format!("No contact request from {}", peer_id)));
+        }
+        self.allowed.insert(peer_id.clone());
+        self.pending_frames
+            .push_back((peer_id.clone(), Frame::ContactResponse { accepted: true }));
+        self.pending_events.push_back(PeerEvent {
+            peer_id: peer_id.to_base58(),
+            event: Event::ContactRequestAccepted,
+        });
+        Ok(())
+    }
+
+    /// Decline the contact request received from the peer
+    pub fn decline_contact_request(&mut self, peer_id: PeerId) -> Result<()> {
+        if !self.incoming_requests.remove(&peer_id) {
+            return Err(Error::Msg(format!("No contact request from {}", peer_id)));
+        }
+        self.pending_frames
+            .push_back((peer_id, Frame::ContactResponse { accepted: false }));
         Ok(())
     }
+
+    /// Block the peer. All connections with the peer are closed and new ones are refused.
+    pub fn block_peer(&mut self, peer_id: PeerId) {
+        log::debug!("Blocking peer {}", peer_id);
+        if self.connected.contains(&peer_id) {
+            self.pending_disconnects
+                .push_back((peer_id.clone(), None, DisconnectReason::Blocked));
+        }
+        self.pending_connections.remove(&peer_id);
+        self.blocked.insert(peer_id);
+    }
+
+    /// Unblock previously blocked peer
+    pub fn unblock_peer(&mut self, peer_id: &PeerId) {
+        log::debug!("Unblocking peer {}", peer_id);
+        self.blocked.remove(peer_id);
+    }
+
+    /// Add the peer to the allowlist (i.e. contacts)
+    pub fn allow_peer(&mut self, peer_id: PeerId) {
+        self.allowed.insert(peer_id);
+    }
+
+    /// Remove the peer from the allowlist
+    pub fn disallow_peer(&mut self, peer_id: &PeerId) {
+        self.allowed.remove(peer_id);
+    }
+
+    /// Peers with an open chat connection
+    pub fn connected_peers(&self) -> impl Iterator<Item = &PeerId> {
+        self.connected.iter()
+    }
+
+    /// Allowlisted peers, i.e. contacts
+    pub fn contacts(&self) -> impl Iterator<Item = &PeerId> {
+        self.allowed.iter()
+    }
+
+    /// Blocked peers
+    pub fn blocked_peers(&self) -> impl Iterator<Item = &PeerId> {
+        self.blocked.iter()
+    }
+
+    /// Changes the local profile. The new profile is sent to connected peers
+    /// and used in all subsequent handshakes. Client version and capabilities are kept.
+    pub fn update_profile(
+        &mut self,
+        name: String,
+        status: Option<String>,
+        avatar_hash: Option<String>,
+    ) -> Result<()> {
+        let metadata = HandshakeMetadata {
+            name,
+            status,
+            avatar_hash,
+            ..self.local_metadata.clone()
+        };
+        metadata.validate(&self.limits)?;
+        self.local_metadata = metadata;
+        self.pending_profile_updates.clear();
+        self.pending_profile_updates
+            .extend(self.connected.iter().cloned());
+        Ok(())
+    }
+
+    /// Takes the messages that couldn't be delivered because the peer wasn't reachable
+    pub fn take_undelivered(&mut self, peer_id: &PeerId) -> Vec<PlainTextMessage> {
+        self.undelivered.remove(peer_id).unwrap_or_default()
+    }
+
+    /// Delivers the message received from the peer indirectly, i.e. through the mailbox
+    /// or relays. The message is subject to the same checks as the messages received directly.
+    pub fn receive_indirect_message(&mut self, peer_id: PeerId, message: PlainTextMessage) {
+        if self.blocked.contains(&peer_id) || !self.is_accepted(&peer_id) {
+            log::debug!("Dropping indirect message from peer {}", peer_id);
+            return;
+        }
+        if message.text.len() > self.limits.max_message_length {
+            log::debug!("Dropping too long indirect message from peer {}", peer_id);
+            return;
+        }
+        self.pending_events.push_back(PeerEvent {
+            peer_id: peer_id.to_base58(),
+            event: Event::ReceivedPlainTextMessage { message },
+        });
+    }
+
+    /// Remembers the address of the peer for dialing it later
+    pub fn add_address(&mut self, peer_id: &PeerId, address: Multiaddr) {
+        let addresses = self.addresses.entry(peer_id.clone()).or_default();
+        if addresses.contains(&address) {
+            return;
+        }
+        if addresses.len() >= MAX_ADDRESSES_PER_PEER {
+            addresses.remove(0);
+        }
+        addresses.push(address);
+    }
+
+    /// Forgets the addresses of the peer unless it's a contact
+    pub fn expire_addresses(&mut self, peer_id: &PeerId) {
+        if !self.allowed.contains(peer_id) {
+            self.addresses.remove(peer_id);
+        }
+    }
+
+    /// Enable or disable forwarding of messages through connected peers
+    pub fn set_forwarding(&mut self, enabled: bool) {
+        self.forwarding.enabled = enabled;
+    }
+
+    /// Undelivered messages are relayed through connected peers
+    pub fn forwarding(&self) -> bool {
+        self.forwarding.enabled
+    }
+
+    /// Sends the message to all connected peers asking them to relay it to the target
+    fn relay_message(&mut self, target: &PeerId, message: PlainTextMessage) {
+        let origin = self.local_peer_id.to_base58();
+        let id = format!("{}:{}", origin, message.timestamp);
+        let signature = match sign_relay(&self.local_key, &id, &origin, &message) {
+            Ok(signature) => signature,
+            Err(e) => {
+                self.pending_events.push_back(PeerEvent {
+                    peer_id: target.to_base58(),
+                    event: Event::Error {
+                        error: ErrorMessage::MessageValidation {
+                            timestamp: message.timestamp,
+                            cause: e.to_string(),
+                        },
+                    },
+                });
+                return;
+            }
+        };
+        self.mark_relay_seen(&id, None);
+        self.relayed
+            .insert(id.clone(), (target.clone(), message.timestamp));
+        let frame = Frame::Relay {
+            id,
+            origin,
+            hops: self.forwarding.max_hops,
+            message,
+            signature,
+        };
+        for peer_id in self.connected.iter() {
+            self.pending_frames
+                .push_back((peer_id.clone(), frame.clone()));
+        }
+    }
+
+    /// Remembers the relayed message and the peer it came from. Returns `false`
+    /// if the message was already seen, e.g. it came back through a loop.
+    fn mark_relay_seen(&mut self, id: &str, from: Option<&PeerId>) -> bool {
+        if self.relay_routes.contains_key(id) {
+            return false;
+        }
+        self.relay_routes.insert(id.to_string(), from.cloned());
+        self.seen_relays.push_back(id.to_string());
+        while self.seen_relays.len() > self.forwarding.seen_capacity {
+            if let Some(old_id) = self.seen_relays.pop_front() {
+                self.relay_routes.remove(&old_id);
+                self.relayed.remove(&old_id);
+            }
+        }
+        true
+    }
+
+    /// Handles `Relay` and `RelayAck` frames received from the peer
+    fn handle_forwarding(&mut self, from: PeerId, frame: Frame) {
+        if !self.check_incoming(&from, Instant::now()) {
+            return;
+        }
+        match frame {
+            Frame::Relay {
+                id,
+                origin,
+                hops,
+                message,
+                signature,
+            } => {
+                // Check the signature before remembering the id, so that a forged copy
+                // can't shadow the genuine message
+                let origin_id = match decode_peer_id(&origin) {
+                    Ok(origin_id) if verify_relay(&id, &origin_id, &message, &signature) => {
+                        origin_id
+                    }
+                    _ => {
+                        log::debug!("Dropping relayed message {} with invalid signature", id);
+                        // Relays check the signature too, so the peer forged the message
+                        self.penalize(&from, Misbehaviour::ProtocolViolation);
+                        return;
+                    }
+                };
+                if !self.mark_relay_seen(&id, Some(&from)) {
+                    log::debug!("Dropping already seen relayed message {}", id);
+                    return;
+                }
+                if message.to == self.local_peer_id.to_base58() {
+                    match sign_relay_ack(&self.local_key, &id) {
+                        Ok(signature) => self
+                            .pending_frames
+                            .push_back((from, Frame::RelayAck { id, signature })),
+                        Err(e) => log::error!("Failed to confirm relayed message {}: {}", id, e),
+                    }
+                    self.receive_indirect_message(origin_id, message);
+                    return;
+                }
+                let hops = hops.min(self.forwarding.max_hops);
+                if !self.forwarding.enabled || hops <= 1 {
+                    log::debug!("Not forwarding relayed message {}", id);
+                    return;
+                }
+                let target = match decode_peer_id(&message.to) {
+                    Ok(target) => target,
+                    Err(e) => {
+                        log::debug!("Dropping relayed message {}: {}", id, e);
+                        return;
+                    }
+                };
+                let next_hops = if self.connected.contains(&target) {
+                    vec![target]
+                } else {
+                    self.connected
+                        .iter()
+                        .filter(|peer_id| **peer_id != from && peer_id.to_base58() != origin)
+                        .cloned()
+                        .collect()
+                };
+                let frame = Frame::Relay {
+                    id,
+                    origin,
+                    hops: hops - 1,
+                    message,
+                    signature,
+                };
+                for peer_id in next_hops {
+                    self.pending_frames.push_back((peer_id, frame.clone()));
+                }
+            }
+            Frame::RelayAck { id, signature } => {
+                if let Some((target, timestamp)) = self.relayed.get(&id).cloned() {
+                    // Only the recipient can confirm the delivery
+                    if !verify_relay_ack(&id, &target, &signature) {
+                        log::debug!("Dropping relay confirmation {} with invalid signature", id);
+                        return;
+                    }
+                    self.relayed.remove(&id);
+                    self.pending_events.push_back(PeerEvent {
+                        peer_id: target.to_base58(),
+                        event: Event::RelayedMessageDelivered { timestamp },
+                    });
+                } else if let Some(Some(previous_hop)) = self.relay_routes.get(&id) {
+                    self.pending_frames
+                        .push_back((previous_hop.clone(), Frame::RelayAck { id, signature }));
+                } else {
+                    log::debug!("Dropping unknown relay confirmation {}", id);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Changes the local presence status and announces it to connected contacts
+    pub fn set_presence(&mut self, status: PresenceStatus) {
+        self.presence = status;
+        let contacts = self
+            .connected
+            .iter()
+            .filter(|peer_id| self.allowed.contains(*peer_id))
+            .cloned()
+            .collect::<Vec<_>>();
+        for peer_id in contacts {
+            self.pending_frames
+                .push_back((peer_id, self.presence_frame()));
+        }
+    }
+
+    fn presence_frame(&self) -> Frame {
+        Frame::Presence {
+            status: self.presence,
+            last_seen: unix_timestamp(),
+        }
+    }
+
+    /// In contacts only mode (enabled by default) messages are accepted only from
+    /// allowlisted peers, i.e. contacts
+    pub fn set_contacts_only(&mut self, contacts_only: bool) {
+        self.contacts_only = contacts_only;
+    }
+
+    /// Returns `true` if the messages from the peer should be accepted
+    fn is_accepted(&self, peer_id: &PeerId) -> bool {
+        !self.contacts_only || self.allowed.contains(peer_id)
+    }
+
+    /// Checks that the peer isn't blocked or banned and doesn't exceed the rate limit
+    fn check_incoming(&mut self, peer_id: &PeerId, now: Instant) -> bool {
+        if self.blocked.contains(peer_id) {
+            log::debug!("Dropping frame from blocked peer {}", peer_id);
+            return false;
+        }
+        if self.scores.is_banned(peer_id, now) {
+            log::debug!("Dropping frame from banned peer {}", peer_id);
+            return false;
+        }
+        if let Err(ban) = self.scores.check_message(peer_id, now) {
+            log::debug!("Dropping frame from flooding peer {}", peer_id);
+            if let Some(until) = ban {
+                self.ban(peer_id, Misbehaviour::MessageFlood, until, now);
+            }
+            return false;
+        }
+        true
+    }
+
+    /// Lowers the score of the peer and bans it once the score drops to the threshold
+    fn penalize(&mut self, peer_id: &PeerId, misbehaviour: Misbehaviour) {
+        let now = Instant::now();
+        if let Some(until) = self.scores.penalize(peer_id, misbehaviour, now) {
+            self.ban(peer_id, misbehaviour, until, now);
+        }
+    }
+
+    /// Bans the peer for misbehaviour: closes all connections with it and notifies the host
+    fn ban(&mut self, peer_id: &PeerId, misbehaviour: Misbehaviour, until: Instant, now: Instant) {
+        log::warn!("Banning peer {} for {}", peer_id, misbehaviour.reason());
+        self.pending_disconnects
+            .push_back((peer_id.clone(), None, DisconnectReason::Banned));
+        self.pending_events.push_back(PeerEvent {
+            peer_id: peer_id.to_base58(),
+            event: Event::PeerBanned {
+                reason: misbehaviour.reason().to_string(),
+                duration_secs: until.saturating_duration_since(now).as_secs(),
+            },
+        });
+    }
+
+    /// Lifts expired bans and schedules the next maintenance
+    fn maintain(&mut self) {
+        let now = Instant::now();
+        for peer_id in self.scores.expire_bans(now) {
+            log::debug!("Ban of peer {} is expired", peer_id);
+            self.pending_events.push_back(PeerEvent {
+                peer_id: peer_id.to_base58(),
+                event: Event::PeerUnbanned,
+            });
+        }
+        let next = self
+            .scores
+            .next_expiration()
+            .map(|until| until.saturating_duration_since(now))
+            .unwrap_or(MAINTENANCE_INTERVAL)
+            .min(MAINTENANCE_INTERVAL);
+        self.maintenance.reset(next);
+    }
 }
 
 impl NetworkBehaviour for PrivateChatBehaviour {
@@ -52,51 +559,264 @@ impl NetworkBehaviour for PrivateChatBehaviour {
     type OutEvent = PeerEvent;
 
     fn new_handler(&mut self) -> Self::ProtocolsHandler {
-        PrivateChatHandler::new(self.local_metadata.clone())
+        PrivateChatHandler::new(self.local_metadata.clone(), self.limits.clone())
+    }
+
+    fn addresses_of_peer(&mut self, peer_id: &PeerId) -> Vec<Multiaddr> {
+        self.addresses.get(peer_id).cloned().unwrap_or_default()
     }
 
-    fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
-        Vec::new()
+    fn inject_connection_established(
+        &mut self,
+        peer_id: &PeerId,
+        connection_id: &ConnectionId,
+        endpoint: &ConnectedPoint,
+    ) {
+        if self.blocked.contains(peer_id) {
+            log::debug!("Refusing connection with blocked peer {}", peer_id);
+            self.pending_disconnects.push_back((
+                peer_id.clone(),
+                Some(*connection_id),
+                DisconnectReason::Blocked,
+            ));
+            return;
+        }
+        match endpoint {
+            ConnectedPoint::Dialer { address } => self.add_address(peer_id, address.clone()),
+            ConnectedPoint::Listener { .. } => {
+                let now = Instant::now();
+                if let Some(until) = self.scores.record_connection(peer_id, now) {
+                    self.ban(peer_id, Misbehaviour::ConnectionFlood, until, now);
+                } else if self.scores.is_banned(peer_id, now) {
+                    log::debug!("Refusing connection from banned peer {}", peer_id);
+                    self.pending_disconnects.push_back((
+                        peer_id.clone(),
+                        Some(*connection_id),
+                        DisconnectReason::Banned,
+                    ));
+                }
+            }
+        }
     }
 
     fn inject_connected(&mut self, peer_id: &PeerId) {
-        if let Some(messages) = self.pending_connections.get(peer_id).cloned() {
-            self.pending_messages.extend(messages)
+        self.dial_errors.remove(peer_id);
+        if let Some(frames) = self.pending_connections.remove(peer_id) {
+            self.pending_frames
+                .extend(frames.into_iter().map(|frame| (peer_id.clone(), frame)));
         }
-        self.pending_connections.remove(peer_id);
         self.connected.insert(peer_id.clone());
+        if self.allowed.contains(peer_id) {
+            self.pending_frames
+                .push_back((peer_id.clone(), self.presence_frame()));
+        }
     }
 
     fn inject_disconnected(&mut self, peer_id: &PeerId) {
         self.pending_connections.remove(peer_id);
         self.connected.remove(peer_id);
+        if self.allowed.contains(peer_id) {
+            self.pending_events.push_back(PeerEvent {
+                peer_id: peer_id.to_base58(),
+                event: Event::PresenceChanged {
+                    status: PresenceStatus::Offline,
+                    last_seen: unix_timestamp(),
+                },
+            });
+        }
+    }
+
+    fn inject_addr_reach_failure(
+        &mut self,
+        peer_id: Option<&PeerId>,
+        addr: &Multiaddr,
+        error: &dyn std::error::Error,
+    ) {
+        let peer_id = match peer_id {
+            Some(peer_id) if self.pending_connections.contains_key(peer_id) => peer_id,
+            _ => return,
+        };
+        self.dial_errors
+            .entry(peer_id.clone())
+            .or_default()
+            .push(format!("{}: {}", addr, error));
     }
 
     fn inject_dial_failure(&mut self, peer_id: &PeerId) {
-        self.pending_connections.remove(peer_id);
+        let cause = self
+            .dial_errors
+            .remove(peer_id)
+            .map(|errors| errors.join("; "))
+            .unwrap_or_else(|| "Unknown".to_string());
+        let messages = self
+            .pending_connections
+            .remove(peer_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|frame| match frame {
+                Frame::Message { message } => Some(message),
+                _ => None,
+            });
+        if self.forwarding.enabled && !self.connected.is_empty() {
+            for message in messages {
+                self.relay_message(peer_id, message);
+            }
+        } else {
+            self.undelivered
+                .entry(peer_id.clone())
+                .or_default()
+                .extend(messages);
+        }
         self.pending_events.push_back(PeerEvent {
-            peer_id: peer_id.to_string().to_string(),
+            peer_id: peer_id.to_string(),
             event: Event::Error {
-                error: ErrorMessage::FailedToDial {
-                    cause: "Unknown".to_string(),
-                },
+                error: ErrorMessage::FailedToDial { cause },
             },
         })
     }
 
+    fn inject_new_listen_addr(&mut self, addr: &Multiaddr) {
+        self.pending_events.push_back(PeerEvent {
+            peer_id: self.local_peer_id.to_base58(),
+            event: Event::ListenAddressAdded {
+                address: addr.to_string(),
+            },
+        });
+    }
+
+    fn inject_expired_listen_addr(&mut self, addr: &Multiaddr) {
+        self.pending_events.push_back(PeerEvent {
+            peer_id: self.local_peer_id.to_base58(),
+            event: Event::ListenAddressExpired {
+                address: addr.to_string(),
+            },
+        });
+    }
+
+    fn inject_listener_error(&mut self, id: ListenerId, err: &(dyn std::error::Error + 'static)) {
+        log::error!("Listener {:?} error: {}", id, err);
+    }
+
     fn inject_event(
         &mut self,
         peer_id: PeerId,
         _: ConnectionId,
         event: <Self::ProtocolsHandler as ProtocolsHandler>::OutEvent,
     ) {
-        let peer_id = peer_id.to_base58().to_string();
-        self.pending_events.push_back(PeerEvent { peer_id, event });
+        let event = match event {
+            OutEvent::Event(event) => event,
+            OutEvent::Forwarding(frame) => return self.handle_forwarding(peer_id, frame),
+        };
+        let now = Instant::now();
+        let misbehaviour = match &event {
+            Event::ReceivedMetadata { .. } => {
+                if !self.check_incoming(&peer_id, now) {
+                    return;
+                }
+                None
+            }
+            Event::PresenceChanged { .. } => {
+                if !self.check_incoming(&peer_id, now) {
+                    return;
+                }
+                if !self.allowed.contains(&peer_id) {
+                    log::debug!(
+                        "Dropping presence of peer {} that is not a contact",
+                        peer_id
+                    );
+                    return;
+                }
+                None
+            }
+            Event::ReceivedPlainTextMessage { .. } => {
+                if !self.check_incoming(&peer_id, now) {
+                    return;
+                }
+                if !self.is_accepted(&peer_id) {
+                    log::debug!(
+                        "Dropping message from peer {} that is not a contact",
+                        peer_id
+                    );
+                    return;
+                }
+                None
+            }
+            Event::ContactRequestReceived { .. } => {
+                if !self.check_incoming(&peer_id, now) {
+                    return;
+                }
+                if self.allowed.contains(&peer_id) {
+                    log::debug!(
+                        "Accepting contact request from existing contact {}",
+                        peer_id
+                    );
+                    self.pending_frames
+                        .push_back((peer_id, Frame::ContactResponse { accepted: true }));
+                    return;
+                }
+                self.incoming_requests.insert(peer_id.clone());
+                None
+            }
+            Event::ContactRequestAccepted => {
+                if !self.outgoing_requests.remove(&peer_id) {
+                    log::debug!("Ignoring unsolicited contact response from {}", peer_id);
+                    return;
+                }
+                self.allowed.insert(peer_id.clone());
+                for message in self
+                    .awaiting_acceptance
+                    .remove(&peer_id)
+                    .unwrap_or_default()
+                {
+                    self.pending_frames
+                        .push_back((peer_id.clone(), Frame::Message { message }));
+                }
+                None
+            }
+            Event::ContactRequestDeclined => {
+                if !self.outgoing_requests.remove(&peer_id) {
+                    log::debug!("Ignoring unsolicited contact response from {}", peer_id);
+                    return;
+                }
+                for message in self
+                    .awaiting_acceptance
+                    .remove(&peer_id)
+                    .unwrap_or_default()
+                {
+                    self.pending_events.push_back(PeerEvent {
+                        peer_id: peer_id.to_base58(),
+                        event: Event::Error {
+                            error: ErrorMessage::MessageValidation {
+                                timestamp: message.timestamp,
+                                cause: "Contact request was declined".to_string(),
+                            },
+                        },
+                    });
+                }
+                None
+            }
+            Event::Error {
+                error: ErrorMessage::MalformedMessage { .. },
+            } => Some(Misbehaviour::MalformedMessage),
+            Event::Error {
+                error: ErrorMessage::LimitExceeded { .. },
+            } => Some(Misbehaviour::ProtocolViolation),
+            _ => None,
+        };
+        self.pending_events.push_back(PeerEvent {
+            peer_id: peer_id.to_base58(),
+            event,
+        });
+        if let Some(misbehaviour) = misbehaviour {
+            if let Some(until) = self.scores.penalize(&peer_id, misbehaviour, now) {
+                self.ban(&peer_id, misbehaviour, until, now);
+            }
+        }
     }
 
     fn poll(
         &mut self,
-        _: &mut Context<'_>,
+        cx: &mut Context<'_>,
         _: &mut impl PollParameters,
     ) -> Poll<
         NetworkBehaviourAction<
@@ -104,21 +824,42 @@ impl NetworkBehaviour for PrivateChatBehaviour {
             Self::OutEvent,
         >,
     > {
-        // Handle all pending messages
-        for _ in 0..self.pending_messages.len() {
-            if let Some((peer_id, message)) = self.pending_messages.pop_front() {
+        while self.maintenance.poll_unpin(cx).is_ready() {
+            self.maintain();
+        }
+        if let Some((peer_id, connection, reason)) = self.pending_disconnects.pop_front() {
+            return Poll::Ready(NetworkBehaviourAction::NotifyHandler {
+                peer_id,
+                handler: connection
+                    .map(NotifyHandler::One)
+                    .unwrap_or(NotifyHandler::All),
+                event: InEvent::Disconnect(reason),
+            });
+        }
+        while let Some(peer_id) = self.pending_profile_updates.pop_front() {
+            if self.connected.contains(&peer_id) {
+                return Poll::Ready(NetworkBehaviourAction::NotifyHandler {
+                    peer_id,
+                    handler: NotifyHandler::All,
+                    event: InEvent::UpdateProfile(self.local_metadata.clone()),
+                });
+            }
+        }
+        // Handle all pending frames
+        for _ in 0..self.pending_frames.len() {
+            if let Some((peer_id, frame)) = self.pending_frames.pop_front() {
                 if self.connected.contains(&peer_id) {
                     return Poll::Ready(NetworkBehaviourAction::NotifyHandler {
                         peer_id: peer_id.clone(),
                         handler: NotifyHandler::Any,
-                        event: InEvent::SendMessage(message.clone()),
+                        event: InEvent::Send(frame),
                     });
                 }
                 let is_connecting = self.pending_connections.contains_key(&peer_id);
                 self.pending_connections
                     .entry(peer_id.clone())
-                    .or_insert(Vec::new())
-                    .push((peer_id.clone(), message));
+                    .or_default()
+                    .push(frame);
                 if !is_connecting {
                     return Poll::Ready(NetworkBehaviourAction::DialPeer {
                         peer_id: peer_id.clone(),
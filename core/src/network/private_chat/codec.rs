@@ -0,0 +1,142 @@
+//! Length prefixed codec with a limit on the frame size
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use derive_more::Display;
+use futures_codec::{Decoder, Encoder};
+use std::io::{Error, ErrorKind};
+
+const U64_LENGTH: usize = std::mem::size_of::<u64>();
+
+/// Same wire format as `futures_codec::LengthCodec` (big endian `u64` length followed by
+/// the payload), but refuses frames larger than `max_frame_size`. Otherwise a peer
+/// could make us allocate arbitrary amounts of memory just by sending a large length prefix.
+#[derive(Debug, Clone)]
+pub struct FrameCodec {
+    max_frame_size: usize,
+}
+
+impl FrameCodec {
+    pub fn new(max_frame_size: usize) -> Self {
+        Self { max_frame_size }
+    }
+}
+
+/// Marks the io errors caused by a frame exceeding the limit, so they can be told apart
+/// from the other invalid data
+#[derive(Debug, Display)]
+#[display(fmt = "Frame of {} bytes exceeds the limit of {} bytes", len, max)]
+struct FrameTooLarge {
+    len: u64,
+    max: usize,
+}
+
+impl std::error::Error for FrameTooLarge {}
+
+/// Returns `true` if the error was caused by a frame exceeding the limit
+pub fn is_frame_too_large(e: &Error) -> bool {
+    e.get_ref().is_some_and(|inner| inner.is::<FrameTooLarge>())
+}
+
+fn frame_too_large(len: u64, max: usize) -> Error {
+    Error::new(ErrorKind::InvalidData, FrameTooLarge { len, max })
+}
+
+impl Encoder for FrameCodec {
+    type Item = Bytes;
+    type Error = Error;
+
+    fn encode(&mut self, src: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        if src.len() > self.max_frame_size {
+            return Err(frame_too_large(src.len() as u64, self.max_frame_size));
+        }
+        dst.reserve(U64_LENGTH + src.len());
+        dst.put_u64(src.len() as u64);
+        dst.extend_from_slice(&src);
+        Ok(())
+    }
+}
+
+impl Decoder for FrameCodec {
+    type Item = Bytes;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.len() < U64_LENGTH {
+            return Ok(None);
+        }
+        let mut len_bytes = [0u8; U64_LENGTH];
+        len_bytes.copy_from_slice(&src[..U64_LENGTH]);
+        let len = u64::from_be_bytes(len_bytes);
+        if len > self.max_frame_size as u64 {
+            return Err(frame_too_large(len, self.max_frame_size));
+        }
+        let len = len as usize;
+        if src.len() - U64_LENGTH < len {
+            src.reserve(U64_LENGTH + len - src.len());
+            return Ok(None);
+        }
+        src.advance(U64_LENGTH);
+        Ok(Some(src.split_to(len).freeze()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_frames_within_the_limit() {
+        let mut codec = FrameCodec::new(16);
+        let mut buffer = BytesMut::new();
+        codec
+            .encode(Bytes::from_static(b"hello"), &mut buffer)
+            .unwrap();
+        assert_eq!(buffer.len(), U64_LENGTH + 5);
+        let frame = codec.decode(&mut buffer).unwrap();
+        assert_eq!(frame, Some(Bytes::from_static(b"hello")));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn waits_for_the_rest_of_the_frame() {
+        let mut codec = FrameCodec::new(16);
+        let mut buffer = BytesMut::new();
+        buffer.put_u64(5);
+        buffer.extend_from_slice(b"hel");
+        assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+        buffer.extend_from_slice(b"lo");
+        assert_eq!(
+            codec.decode(&mut buffer).unwrap(),
+            Some(Bytes::from_static(b"hello"))
+        );
+    }
+
+    #[test]
+    fn rejects_oversized_frames_before_reading_them() {
+        let mut codec = FrameCodec::new(16);
+        let mut buffer = BytesMut::new();
+        // Only the length prefix arrived, the payload is never buffered
+        buffer.put_u64(u64::MAX);
+        let e = codec.decode(&mut buffer).unwrap_err();
+        assert!(is_frame_too_large(&e));
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn refuses_to_encode_oversized_frames() {
+        let mut codec = FrameCodec::new(4);
+        let mut buffer = BytesMut::new();
+        let e = codec
+            .encode(Bytes::from_static(b"hello"), &mut buffer)
+            .unwrap_err();
+        assert!(is_frame_too_large(&e));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn other_invalid_data_is_not_an_oversized_frame() {
+        let e = Error::new(ErrorKind::InvalidData, "malformed json");
+        assert!(!is_frame_too_large(&e));
+        assert!(!is_frame_too_large(&Error::from(ErrorKind::InvalidData)));
+    }
+}
@@ -1,37 +1,76 @@
-use super::protocol::{HandshakeMetadata, PrivateChatProtocol};
-use crate::error::Error;
+use super::codec::{is_frame_too_large, FrameCodec};
+use super::protocol::{Frame, HandshakeMetadata, PrivateChatProtocol};
+use crate::config::Limits;
+use crate::error::{Error, LimitExceeded};
+use derive_more::Display;
 use futures::prelude::*;
-use futures_codec::{Framed, LengthCodec};
+use futures_codec::Framed;
+use libp2p::core::upgrade::UpgradeError;
 use libp2p::swarm::{
     KeepAlive, NegotiatedSubstream, ProtocolsHandler, ProtocolsHandlerEvent,
     ProtocolsHandlerUpgrErr, SubstreamProtocol,
 };
-use primitives::{ErrorMessage, Event, PlainTextMessage};
+use primitives::{ErrorMessage, Event};
 use std::collections::VecDeque;
+use std::io;
 use std::task::{Context, Poll};
 
+/// Maximum number of substreams kept open with the peer, the oldest one is dropped
+/// when the peer opens more
+const MAX_SUBSTREAMS: usize = 4;
+
 /// Protocol handler for private chat. Handles sending and receiving messages
 /// and sending peer metadata after the handshake.
 pub struct PrivateChatHandler {
     local_metadata: HandshakeMetadata,
-    framed_socket: Option<Framed<NegotiatedSubstream, LengthCodec>>,
+    limits: Limits,
+    /// Both peers may open a substream at the same time. Frames are received
+    /// from all open substreams and sent to the most recent one.
+    substreams: Vec<Framed<NegotiatedSubstream, FrameCodec>>,
     pending_metadata: Option<HandshakeMetadata>,
-    pending_sending_messages: VecDeque<PlainTextMessage>,
+    pending_frames: VecDeque<Frame>,
     pending_substream_open: bool,
-    outgoing_message: Option<u64>,
+    /// Timestamps of the messages written to the substream, but not flushed yet
+    unflushed_messages: VecDeque<u64>,
     errors: VecDeque<ErrorMessage>,
+    /// Set when the peer violated the protocol, the connection is closed
+    /// once all pending errors are reported
+    violation: Option<Error>,
+}
+
+/// Event sent from handler to behaviour
+#[derive(Debug)]
+pub enum OutEvent {
+    /// Event reported to the host
+    Event(Event),
+    /// `Relay` or `RelayAck` frame handled by the behaviour
+    Forwarding(Frame),
 }
 
 /// Event coming from behavior to notify about
-/// the new incoming message from user to be sent
+/// the new frame (e.g. message from user) to be sent
 #[derive(Debug, Clone)]
 pub enum InEvent {
-    SendMessage(PlainTextMessage),
+    Send(Frame),
+    /// Close the connection with a blocked or banned peer
+    Disconnect(DisconnectReason),
+    /// Local profile is changed. The new metadata is used for the next handshakes
+    /// and sent to the peer if the substream is already open.
+    UpdateProfile(HandshakeMetadata),
+}
+
+/// Reason of closing the connection with the peer
+#[derive(Debug, Clone, Copy, Display)]
+pub enum DisconnectReason {
+    #[display(fmt = "Peer is blocked")]
+    Blocked,
+    #[display(fmt = "Peer is temporarily banned")]
+    Banned,
 }
 
 impl ProtocolsHandler for PrivateChatHandler {
     type InEvent = InEvent;
-    type OutEvent = Event;
+    type OutEvent = OutEvent;
     type Error = Error;
     type InboundProtocol = PrivateChatProtocol;
     type OutboundProtocol = PrivateChatProtocol;
@@ -39,47 +78,58 @@ impl ProtocolsHandler for PrivateChatHandler {
     type InboundOpenInfo = ();
 
     fn listen_protocol(&self) -> SubstreamProtocol<PrivateChatProtocol, ()> {
-        SubstreamProtocol::new(PrivateChatProtocol::new(self.local_metadata.clone()), ())
+        SubstreamProtocol::new(
+            PrivateChatProtocol::new(self.local_metadata.clone(), self.limits.clone()),
+            (),
+        )
     }
 
     fn inject_fully_negotiated_inbound(
         &mut self,
-        protocol: (HandshakeMetadata, Framed<NegotiatedSubstream, LengthCodec>),
+        protocol: (HandshakeMetadata, Framed<NegotiatedSubstream, FrameCodec>),
         _: (),
     ) {
         log::debug!("Injected fully negotiated inbound");
-        self.pending_substream_open = false;
-        let (metadata, framed_socket) = protocol;
-        log::debug!("Received peer metadata: {:?}", metadata);
-        self.framed_socket = Some(framed_socket);
-        self.pending_metadata = Some(metadata);
+        self.add_substream(protocol);
     }
 
     fn inject_fully_negotiated_outbound(
         &mut self,
-        protocol: (HandshakeMetadata, Framed<NegotiatedSubstream, LengthCodec>),
+        protocol: (HandshakeMetadata, Framed<NegotiatedSubstream, FrameCodec>),
         _: (),
     ) {
         log::debug!("Injected fully negotiated outbound");
-        self.pending_substream_open = false;
-        let (metadata, framed_socket) = protocol;
-        log::debug!("Received peer metadata: {:?}", metadata);
-        self.framed_socket = Some(framed_socket);
-        self.pending_metadata = Some(metadata);
+        self.add_substream(protocol);
     }
 
     fn inject_event(&mut self, event: InEvent) {
         match event {
-            InEvent::SendMessage(message) => self.pending_sending_messages.push_back(message),
+            InEvent::Send(frame) => self.pending_frames.push_back(frame),
+            InEvent::Disconnect(reason) => self.violation = Some(reason.to_string().into()),
+            InEvent::UpdateProfile(metadata) => {
+                if !self.substreams.is_empty() {
+                    self.pending_frames.push_back(Frame::Profile {
+                        metadata: metadata.clone(),
+                    });
+                }
+                self.local_metadata = metadata;
+            }
         }
     }
 
     fn inject_dial_upgrade_error(&mut self, _info: (), error: ProtocolsHandlerUpgrErr<Error>) {
         log::error!("Error upgrading connection: {}", error);
-        self.framed_socket = None;
-        self.errors.push_back(ErrorMessage::FailedToDial {
-            cause: error.to_string(),
-        });
+        self.pending_substream_open = false;
+        if !self.handle_violation(&error) {
+            self.errors.push_back(ErrorMessage::FailedToDial {
+                cause: error.to_string(),
+            });
+        }
+    }
+
+    fn inject_listen_upgrade_error(&mut self, _info: (), error: ProtocolsHandlerUpgrErr<Error>) {
+        log::error!("Error upgrading inbound connection: {}", error);
+        self.handle_violation(&error);
     }
 
     fn connection_keep_alive(&self) -> KeepAlive {
@@ -91,116 +141,301 @@ impl ProtocolsHandler for PrivateChatHandler {
         cx: &mut Context<'_>,
     ) -> Poll<ProtocolsHandlerEvent<PrivateChatProtocol, (), Self::OutEvent, Self::Error>> {
         if let Some(e) = self.errors.pop_front() {
-            return Poll::Ready(ProtocolsHandlerEvent::Custom(Event::Error { error: e }));
+            return Poll::Ready(ProtocolsHandlerEvent::Custom(OutEvent::Event(
+                Event::Error { error: e },
+            )));
+        }
+        if let Some(e) = self.violation.take() {
+            log::warn!("Closing connection with misbehaving peer: {}", e);
+            return Poll::Ready(ProtocolsHandlerEvent::Close(e));
         }
         if let Some(metadata) = self.pending_metadata.take() {
-            return Poll::Ready(ProtocolsHandlerEvent::Custom(Event::ReceivedMetadata {
-                name: metadata.name,
-            }));
+            return Poll::Ready(ProtocolsHandlerEvent::Custom(OutEvent::Event(
+                Event::ReceivedMetadata {
+                    metadata: metadata.into(),
+                },
+            )));
         }
-        if !self.pending_sending_messages.is_empty()
-            && self.framed_socket.is_none()
+        if !self.pending_frames.is_empty()
+            && self.substreams.is_empty()
             && !self.pending_substream_open
         {
             log::debug!("Opening substream");
             self.pending_substream_open = true;
             return Poll::Ready(ProtocolsHandlerEvent::OutboundSubstreamRequest {
                 protocol: SubstreamProtocol::new(
-                    PrivateChatProtocol::new(self.local_metadata.clone()),
+                    PrivateChatProtocol::new(self.local_metadata.clone(), self.limits.clone()),
                     (),
                 ),
             });
         }
-        if let Some(framed_socket) = self.framed_socket.as_mut() {
+        // Write as many pending frames as the substream accepts
+        while let Some(framed_socket) = self.substreams.last_mut() {
+            if self.pending_frames.is_empty() {
+                break;
+            }
             match framed_socket.poll_ready_unpin(cx) {
-                Poll::Ready(_) => {
-                    if let Some(message) = self.pending_sending_messages.pop_front() {
-                        log::debug!("Sending message with timestamp: {}", message.timestamp);
-                        self.outgoing_message = Some(message.timestamp);
-                        let bytes = match serde_json::to_vec(&message) {
-                            Ok(b) => b,
-                            Err(e) => {
-                                return Poll::Ready(ProtocolsHandlerEvent::Custom(Event::Error {
-                                    error: ErrorMessage::MessageValidation {
-                                        timestamp: message.timestamp,
-                                        cause: e.to_string(),
-                                    },
-                                }))
-                            }
-                        };
-                        if let Err(e) = framed_socket.start_send_unpin(bytes.into()) {
-                            return Poll::Ready(ProtocolsHandlerEvent::Custom(Event::Error {
-                                error: ErrorMessage::Network {
-                                    cause: e.to_string(),
-                                },
-                            }));
-                        }
-                    }
+                Poll::Ready(Ok(())) => (),
+                Poll::Ready(Err(e)) => {
+                    self.substreams.pop();
+                    return self.substream_failed(e);
                 }
-                Poll::Pending => (),
+                Poll::Pending => break,
             }
-            // poll for sent message
-            match framed_socket.poll_flush_unpin(cx) {
-                Poll::Ready(_) => {
-                    if let Some(timestamp) = self.outgoing_message.take() {
-                        log::debug!("Sent message with timestamp: {}", timestamp);
-                        return Poll::Ready(ProtocolsHandlerEvent::Custom(
-                            Event::SentPlainTextMessage { timestamp },
-                        ));
-                    }
+            let frame = match self.pending_frames.pop_front() {
+                Some(frame) => frame,
+                None => break,
+            };
+            let bytes = match serde_json::to_vec(&frame) {
+                Ok(b) => b,
+                Err(e) => {
+                    let error = match frame {
+                        Frame::Message { message } => ErrorMessage::MessageValidation {
+                            timestamp: message.timestamp,
+                            cause: e.to_string(),
+                        },
+                        _ => ErrorMessage::Other {
+                            cause: e.to_string(),
+                        },
+                    };
+                    return Poll::Ready(ProtocolsHandlerEvent::Custom(OutEvent::Event(
+                        Event::Error { error },
+                    )));
                 }
-                _ => (),
+            };
+            if let Frame::Message { message } = &frame {
+                log::debug!("Sending message with timestamp: {}", message.timestamp);
+                self.unflushed_messages.push_back(message.timestamp);
             }
-            match framed_socket.poll_next_unpin(cx) {
-                Poll::Pending => (),
-                Poll::Ready(Some(Ok(bytes))) => {
-                    match serde_json::from_slice::<PlainTextMessage>(&bytes) {
-                        Ok(message) => {
-                            log::debug!("Received message: {:?}", message);
-                            return Poll::Ready(ProtocolsHandlerEvent::Custom(
-                                Event::ReceivedPlainTextMessage { message },
-                            ));
-                        }
-                        Err(e) => {
-                            return Poll::Ready(ProtocolsHandlerEvent::Custom(Event::Error {
-                                error: ErrorMessage::Other {
-                                    cause: format!(
-                                    "Failed to deserialize incoming message: {:02x?}. Reason: {}",
-                                    bytes, e
-                                ),
-                                },
-                            }))
+            if let Err(e) = framed_socket.start_send_unpin(bytes.into()) {
+                self.substreams.pop();
+                return self.substream_failed(e);
+            }
+        }
+        // poll for sent messages
+        let mut flushed = true;
+        let mut index = 0;
+        while index < self.substreams.len() {
+            match self.substreams[index].poll_flush_unpin(cx) {
+                Poll::Ready(Ok(())) => index += 1,
+                Poll::Ready(Err(e)) => {
+                    self.substreams.remove(index);
+                    return self.substream_failed(e);
+                }
+                Poll::Pending => {
+                    flushed = false;
+                    index += 1;
+                }
+            }
+        }
+        if flushed {
+            if let Some(timestamp) = self.unflushed_messages.pop_front() {
+                log::debug!("Sent message with timestamp: {}", timestamp);
+                if !self.unflushed_messages.is_empty() {
+                    cx.waker().wake_by_ref();
+                }
+                return Poll::Ready(ProtocolsHandlerEvent::Custom(OutEvent::Event(
+                    Event::SentPlainTextMessage { timestamp },
+                )));
+            }
+        }
+        let mut index = 0;
+        while index < self.substreams.len() && self.violation.is_none() {
+            match self.substreams[index].poll_next_unpin(cx) {
+                Poll::Pending => index += 1,
+                Poll::Ready(Some(Ok(bytes))) => match serde_json::from_slice::<Frame>(&bytes) {
+                    Ok(frame) => {
+                        if let Some(event) = self.received_frame(frame) {
+                            return Poll::Ready(ProtocolsHandlerEvent::Custom(event));
                         }
                     }
+                    Err(e) => {
+                        let cause = format!(
+                            "Failed to deserialize incoming frame of {} bytes. Reason: {}",
+                            bytes.len(),
+                            e
+                        );
+                        return Poll::Ready(ProtocolsHandlerEvent::Custom(OutEvent::Event(
+                            Event::Error {
+                                error: ErrorMessage::MalformedMessage { cause },
+                            },
+                        )));
+                    }
+                },
+                Poll::Ready(Some(Err(e))) if is_frame_too_large(&e) => {
+                    self.substreams.clear();
+                    self.report_violation(LimitExceeded(e.to_string()));
                 }
                 Poll::Ready(Some(Err(e))) => {
                     log::error!("Error on the receiving stream: {}", e);
-                    return Poll::Ready(ProtocolsHandlerEvent::Custom(Event::Error {
-                        error: ErrorMessage::Network {
-                            cause: e.to_string(),
+                    self.substreams.remove(index);
+                    return Poll::Ready(ProtocolsHandlerEvent::Custom(OutEvent::Event(
+                        Event::Error {
+                            error: ErrorMessage::Network {
+                                cause: e.to_string(),
+                            },
                         },
-                    }));
+                    )));
                 }
                 Poll::Ready(None) => {
-                    log::warn!("Stream is closed");
+                    log::debug!("Substream is closed by the peer");
+                    self.substreams.remove(index);
                 }
             }
         }
+        // Report the errors and reopen the substream if the peer closed all of them
+        if !self.errors.is_empty()
+            || self.violation.is_some()
+            || (self.substreams.is_empty()
+                && !self.pending_frames.is_empty()
+                && !self.pending_substream_open)
+        {
+            cx.waker().wake_by_ref();
+        }
         Poll::Pending
     }
 }
 
 impl PrivateChatHandler {
     /// Creates a new handler. `local_metadata` is required for the initial exchange with a peer.
-    pub fn new(local_metadata: HandshakeMetadata) -> PrivateChatHandler {
+    /// `limits` are enforced on everything received from the peer.
+    pub fn new(local_metadata: HandshakeMetadata, limits: Limits) -> PrivateChatHandler {
         PrivateChatHandler {
             local_metadata,
+            limits,
             pending_metadata: None,
-            pending_sending_messages: VecDeque::new(),
-            outgoing_message: None,
+            pending_frames: VecDeque::new(),
+            unflushed_messages: VecDeque::new(),
             pending_substream_open: false,
-            framed_socket: None,
+            substreams: Vec::new(),
             errors: VecDeque::new(),
+            violation: None,
+        }
+    }
+
+    /// Keeps the negotiated substream and reports the metadata received in the handshake
+    fn add_substream(
+        &mut self,
+        protocol: (HandshakeMetadata, Framed<NegotiatedSubstream, FrameCodec>),
+    ) {
+        let (metadata, framed_socket) = protocol;
+        log::debug!("Received peer metadata: {:?}", metadata);
+        self.pending_substream_open = false;
+        if self.substreams.len() >= MAX_SUBSTREAMS {
+            self.substreams.remove(0);
+        }
+        self.substreams.push(framed_socket);
+        self.pending_metadata = Some(metadata);
+    }
+
+    /// Fails the messages not flushed yet and reports the broken substream
+    fn substream_failed(
+        &mut self,
+        error: io::Error,
+    ) -> Poll<ProtocolsHandlerEvent<PrivateChatProtocol, (), OutEvent, Error>> {
+        log::error!("Error on the sending stream: {}", error);
+        for timestamp in self.unflushed_messages.drain(..) {
+            self.errors.push_back(ErrorMessage::MessageValidation {
+                timestamp,
+                cause: error.to_string(),
+            });
         }
+        Poll::Ready(ProtocolsHandlerEvent::Custom(OutEvent::Event(
+            Event::Error {
+                error: ErrorMessage::Network {
+                    cause: error.to_string(),
+                },
+            },
+        )))
+    }
+
+    /// Converts the received frame into the event for behaviour, checking the limits
+    fn received_frame(&mut self, frame: Frame) -> Option<OutEvent> {
+        let event = match frame {
+            Frame::Message { message } => {
+                if message.text.len() > self.limits.max_message_length {
+                    self.report_violation(LimitExceeded(format!(
+                        "Message of {} bytes exceeds the limit of {} bytes",
+                        message.text.len(),
+                        self.limits.max_message_length
+                    )));
+                    return None;
+                }
+                log::debug!("Received message: {:?}", message);
+                Some(Event::ReceivedPlainTextMessage { message })
+            }
+            Frame::ContactRequest { metadata, note } => {
+                if note.len() > self.limits.max_message_length {
+                    self.report_violation(LimitExceeded(format!(
+                        "Contact request note of {} bytes exceeds the limit of {} bytes",
+                        note.len(),
+                        self.limits.max_message_length,
+                    )));
+                    return None;
+                }
+                if let Err(Error::Limit(limit)) = metadata.validate(&self.limits) {
+                    self.report_violation(limit);
+                    return None;
+                }
+                log::debug!("Received contact request from {}", metadata.name);
+                Some(Event::ContactRequestReceived {
+                    metadata: metadata.into(),
+                    note,
+                })
+            }
+            Frame::Profile { metadata } => {
+                if let Err(Error::Limit(limit)) = metadata.validate(&self.limits) {
+                    self.report_violation(limit);
+                    return None;
+                }
+                log::debug!("Received profile update: {:?}", metadata);
+                Some(Event::ReceivedMetadata {
+                    metadata: metadata.into(),
+                })
+            }
+            Frame::Presence { status, last_seen } => {
+                Some(Event::PresenceChanged { status, last_seen })
+            }
+            Frame::ContactResponse { accepted: true } => Some(Event::ContactRequestAccepted),
+            Frame::ContactResponse { accepted: false } => Some(Event::ContactRequestDeclined),
+            Frame::Relay { ref message, .. } => {
+                if message.text.len() > self.limits.max_message_length {
+                    self.report_violation(LimitExceeded(format!(
+                        "Relayed message of {} bytes exceeds the limit of {} bytes",
+                        message.text.len(),
+                        self.limits.max_message_length
+                    )));
+                    return None;
+                }
+                return Some(OutEvent::Forwarding(frame));
+            }
+            Frame::RelayAck { .. } => return Some(OutEvent::Forwarding(frame)),
+        };
+        event.map(OutEvent::Event)
+    }
+
+    /// Reports the violated limit and schedules the connection to be closed
+    fn report_violation(&mut self, limit: LimitExceeded) {
+        self.errors.push_back(ErrorMessage::LimitExceeded {
+            cause: limit.to_string(),
+        });
+        self.violation = Some(limit.into());
+    }
+
+    /// Checks whether the upgrade failed because the peer violated the limits.
+    /// If so, reports the violation and returns `true`.
+    fn handle_violation(&mut self, error: &ProtocolsHandlerUpgrErr<Error>) -> bool {
+        let cause = match error {
+            ProtocolsHandlerUpgrErr::Timeout
+            | ProtocolsHandlerUpgrErr::Upgrade(UpgradeError::Apply(Error::Timeout(_))) => {
+                "Protocol handshake timed out".to_string()
+            }
+            ProtocolsHandlerUpgrErr::Upgrade(UpgradeError::Apply(Error::Limit(limit))) => {
+                limit.to_string()
+            }
+            _ => return false,
+        };
+        self.report_violation(LimitExceeded(cause));
+        true
     }
 }
@@ -1,8 +1,13 @@
-use crate::error::{Error, Result};
+use super::codec::{is_frame_too_large, FrameCodec};
+use crate::config::Limits;
+use crate::error::{Error, LimitExceeded, Result};
 use futures::prelude::*;
 use futures::{AsyncRead, AsyncWrite};
-use futures_codec::{Framed, LengthCodec};
+use futures_codec::Framed;
 use libp2p::{core::UpgradeInfo, InboundUpgrade, OutboundUpgrade};
+use primitives::{
+    deserialize_capabilities, Capability, Metadata, PlainTextMessage, PresenceStatus,
+};
 use serde::{Deserialize, Serialize};
 use std::future::Future;
 use std::pin::Pin;
@@ -11,11 +16,122 @@ use std::pin::Pin;
 pub struct HandshakeMetadata {
     /// Human name of the peer
     pub name: String,
+    /// Client name and version, e.g. `tata-core/0.1.0`
+    #[serde(default)]
+    pub client_version: String,
+    /// Optional status text
+    #[serde(default)]
+    pub status: Option<String>,
+    /// Hash of the avatar image
+    #[serde(default)]
+    pub avatar_hash: Option<String>,
+    /// Optional features supported by the peer
+    #[serde(default, deserialize_with = "deserialize_capabilities")]
+    pub capabilities: Vec<Capability>,
+}
+
+impl HandshakeMetadata {
+    /// Creates metadata of the local peer with the current client version
+    pub fn new(
+        name: String,
+        status: Option<String>,
+        avatar_hash: Option<String>,
+        capabilities: Vec<Capability>,
+    ) -> Self {
+        HandshakeMetadata {
+            name,
+            client_version: format!("tata-core/{}", env!("CARGO_PKG_VERSION")),
+            status,
+            avatar_hash,
+            capabilities,
+        }
+    }
+
+    /// Checks the metadata against the limits
+    pub fn validate(&self, limits: &Limits) -> Result<()> {
+        let fields = [
+            ("Peer name", self.name.len(), limits.max_name_length),
+            (
+                "Client version",
+                self.client_version.len(),
+                limits.max_client_version_length,
+            ),
+            (
+                "Avatar hash",
+                self.avatar_hash.as_ref().map(String::len).unwrap_or(0),
+                limits.max_avatar_hash_length,
+            ),
+            (
+                "Status",
+                self.status.as_ref().map(String::len).unwrap_or(0),
+                limits.max_status_length,
+            ),
+        ];
+        for (field, len, max) in fields.iter() {
+            if len > max {
+                return Err(LimitExceeded(format!(
+                    "{} of {} bytes exceeds the limit of {} bytes",
+                    field, len, max
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<HandshakeMetadata> for Metadata {
+    fn from(metadata: HandshakeMetadata) -> Self {
+        Metadata {
+            name: metadata.name,
+            client_version: metadata.client_version,
+            status: metadata.status,
+            avatar_hash: metadata.avatar_hash,
+            capabilities: metadata.capabilities,
+        }
+    }
+}
+
+/// Frame sent over the substream after the handshake
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Frame {
+    /// Plain text message. Delivered only if the peers are contacts.
+    Message { message: PlainTextMessage },
+    /// Request to become contacts sent on the first contact
+    ContactRequest {
+        metadata: HandshakeMetadata,
+        note: String,
+    },
+    /// Response to the contact request
+    ContactResponse { accepted: bool },
+    /// Updated metadata of the peer, sent to connected peers when the profile is changed
+    Profile { metadata: HandshakeMetadata },
+    /// Presence status sent to contacts on connection and whenever it's changed.
+    /// `last_seen` is a unix timestamp in milliseconds.
+    Presence {
+        status: PresenceStatus,
+        last_seen: u64,
+    },
+    /// Message forwarded through intermediate peers when the target can't be reached directly.
+    /// `id` identifies the message on all hops, `hops` is decremented on every hop.
+    /// `signature` is made by `origin` over the id, the origin and the message.
+    Relay {
+        id: String,
+        origin: String,
+        hops: u8,
+        message: PlainTextMessage,
+        signature: String,
+    },
+    /// Delivery confirmation of the relayed message, sent back along the path.
+    /// `signature` is made by the recipient of the message over the id.
+    RelayAck { id: String, signature: String },
 }
 
 /// Protocol struct that knows how to upgrade
 pub struct PrivateChatProtocol {
     local_metadata: HandshakeMetadata,
+    limits: Limits,
 }
 
 impl UpgradeInfo for PrivateChatProtocol {
@@ -23,7 +139,7 @@ impl UpgradeInfo for PrivateChatProtocol {
     type InfoIter = std::iter::Once<Self::Info>;
 
     fn protocol_info(&self) -> Self::InfoIter {
-        std::iter::once(b"/private_chat/1.0.0")
+        std::iter::once(b"/private_chat/2.0.0")
     }
 }
 
@@ -31,18 +147,23 @@ impl<TSocket> InboundUpgrade<TSocket> for PrivateChatProtocol
 where
     TSocket: AsyncRead + AsyncWrite + Send + Unpin + 'static,
 {
-    type Output = (HandshakeMetadata, Framed<TSocket, LengthCodec>);
+    type Output = (HandshakeMetadata, Framed<TSocket, FrameCodec>);
     type Error = Error;
     type Future = Pin<Box<dyn Future<Output = Result<Self::Output>> + Send>>;
 
     fn upgrade_inbound(self, socket: TSocket, _: Self::Info) -> Self::Future {
         log::trace!("Upgrade inbound for private chat");
-        Box::pin(async move {
-            let mut framed_socket = Framed::new(socket, LengthCodec {});
-            let metadata = receive_metadata(&mut framed_socket).await?;
-            send_metadata(&mut framed_socket, self.local_metadata).await?;
-            Ok((metadata, framed_socket))
-        })
+        let timeout = self.limits.handshake_timeout();
+        Box::pin(
+            async_std::future::timeout(timeout, async move {
+                let mut framed_socket =
+                    Framed::new(socket, FrameCodec::new(self.limits.max_frame_size));
+                let metadata = receive_metadata(&mut framed_socket, &self.limits).await?;
+                send_metadata(&mut framed_socket, self.local_metadata).await?;
+                Ok::<_, Error>((metadata, framed_socket))
+            })
+            .map(|res| res?),
+        )
     }
 }
 
@@ -50,29 +171,40 @@ impl<TSocket> OutboundUpgrade<TSocket> for PrivateChatProtocol
 where
     TSocket: AsyncRead + AsyncWrite + Send + Unpin + 'static,
 {
-    type Output = (HandshakeMetadata, Framed<TSocket, LengthCodec>);
+    type Output = (HandshakeMetadata, Framed<TSocket, FrameCodec>);
     type Error = Error;
     type Future =
         Pin<Box<dyn Future<Output = std::result::Result<Self::Output, Self::Error>> + Send>>;
 
     fn upgrade_outbound(self, socket: TSocket, _: Self::Info) -> Self::Future {
         log::trace!("Upgrade outbound for private chat");
-        Box::pin(async move {
-            let mut framed_socket = Framed::new(socket, LengthCodec {});
-            send_metadata(&mut framed_socket, self.local_metadata).await?;
-            let metadata = receive_metadata(&mut framed_socket).await?;
-            Ok((metadata, framed_socket))
-        })
+        let timeout = self.limits.handshake_timeout();
+        Box::pin(
+            async_std::future::timeout(timeout, async move {
+                let mut framed_socket =
+                    Framed::new(socket, FrameCodec::new(self.limits.max_frame_size));
+                send_metadata(&mut framed_socket, self.local_metadata).await?;
+                let metadata = receive_metadata(&mut framed_socket, &self.limits).await?;
+                Ok::<_, Error>((metadata, framed_socket))
+            })
+            .map(|res| res?),
+        )
     }
 }
 
 impl PrivateChatProtocol {
-    pub fn new(local_metadata: HandshakeMetadata) -> Self {
-        Self { local_metadata }
+    pub fn new(local_metadata: HandshakeMetadata, limits: Limits) -> Self {
+        Self {
+            local_metadata,
+            limits,
+        }
     }
 }
 
-async fn receive_metadata<T>(socket: &mut Framed<T, LengthCodec>) -> Result<HandshakeMetadata>
+async fn receive_metadata<T>(
+    socket: &mut Framed<T, FrameCodec>,
+    limits: &Limits,
+) -> Result<HandshakeMetadata>
 where
     T: AsyncRead + Unpin,
 {
@@ -81,14 +213,21 @@ where
         .await
         .ok_or("Private chat: upgrade stream is closed")?;
     log::trace!("Received metadata");
-    let bytes = metadata_res?;
+    let bytes = metadata_res.map_err(|e| {
+        if is_frame_too_large(&e) {
+            LimitExceeded(e.to_string()).into()
+        } else {
+            Error::Io(e)
+        }
+    })?;
     let s = String::from_utf8(bytes.to_vec())?;
-    let metadata = serde_json::from_str(&s)?;
+    let metadata: HandshakeMetadata = serde_json::from_str(&s)?;
+    metadata.validate(limits)?;
     Ok(metadata)
 }
 
 async fn send_metadata<T>(
-    socket: &mut Framed<T, LengthCodec>,
+    socket: &mut Framed<T, FrameCodec>,
     metadata: HandshakeMetadata,
 ) -> Result<()>
 where
@@ -99,3 +238,90 @@ where
     log::trace!("Sent metadata");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> HandshakeMetadata {
+        HandshakeMetadata::new(
+            "alice".to_string(),
+            Some("at lunch".to_string()),
+            Some("f00d".to_string()),
+            vec![Capability::Receipts, Capability::Encryption],
+        )
+    }
+
+    #[test]
+    fn local_metadata_has_the_client_version() {
+        assert_eq!(
+            metadata().client_version,
+            format!("tata-core/{}", env!("CARGO_PKG_VERSION"))
+        );
+    }
+
+    #[test]
+    fn metadata_is_converted_with_all_fields() {
+        let converted: Metadata = metadata().into();
+        assert_eq!(converted.name, "alice");
+        assert_eq!(converted.client_version, metadata().client_version);
+        assert_eq!(converted.status.as_deref(), Some("at lunch"));
+        assert_eq!(converted.avatar_hash.as_deref(), Some("f00d"));
+        assert_eq!(
+            converted.capabilities,
+            vec![Capability::Receipts, Capability::Encryption]
+        );
+    }
+
+    #[test]
+    fn metadata_of_old_clients_is_accepted() {
+        let metadata: HandshakeMetadata = serde_json::from_str(r#"{"name":"bob"}"#).unwrap();
+        assert!(metadata.validate(&Limits::default()).is_ok());
+        assert!(metadata.capabilities.is_empty());
+    }
+
+    #[test]
+    fn metadata_exceeding_the_limits_is_refused() {
+        let limits = Limits {
+            max_name_length: 5,
+            max_client_version_length: 4,
+            max_avatar_hash_length: 4,
+            max_status_length: 8,
+            ..Limits::default()
+        };
+        let valid = HandshakeMetadata {
+            client_version: "v1".to_string(),
+            ..metadata()
+        };
+        assert!(valid.validate(&limits).is_ok());
+        let long_name = HandshakeMetadata {
+            name: "alice!".to_string(),
+            ..valid.clone()
+        };
+        assert!(matches!(long_name.validate(&limits), Err(Error::Limit(_))));
+        let long_status = HandshakeMetadata {
+            status: Some("out of office".to_string()),
+            ..valid.clone()
+        };
+        assert!(matches!(
+            long_status.validate(&limits),
+            Err(Error::Limit(_))
+        ));
+        let long_version = HandshakeMetadata {
+            client_version: "tata-core/1.0.0".to_string(),
+            ..valid.clone()
+        };
+        assert!(matches!(
+            long_version.validate(&limits),
+            Err(Error::Limit(_))
+        ));
+        let long_avatar = HandshakeMetadata {
+            avatar_hash: Some("deadbeef".to_string()),
+            ..valid
+        };
+        assert!(matches!(
+            long_avatar.validate(&limits),
+            Err(Error::Limit(_))
+        ));
+    }
+}
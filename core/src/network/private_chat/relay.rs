@@ -0,0 +1,136 @@
+//! Signatures of the relayed messages.
+//!
+//! Relays can't be trusted to report the origin of the message or the delivery, so the
+//! origin signs the message and the recipient signs the delivery confirmation. Both are
+//! verified against the keys inlined into the peer ids.
+use crate::error::{Error, Result};
+use crate::utils::public_key_of;
+use libp2p::identity::Keypair;
+use libp2p::PeerId;
+use primitives::PlainTextMessage;
+
+const RELAY_DOMAIN: &[u8] = b"tata/relay";
+const RELAY_ACK_DOMAIN: &[u8] = b"tata/relay-ack";
+
+/// Signs the relayed message on behalf of the origin
+pub fn sign_relay(
+    key: &Keypair,
+    id: &str,
+    origin: &str,
+    message: &PlainTextMessage,
+) -> Result<String> {
+    sign(key, &relay_bytes(id, origin, message))
+}
+
+/// Checks that the relayed message is signed by the origin
+pub fn verify_relay(
+    id: &str,
+    origin: &PeerId,
+    message: &PlainTextMessage,
+    signature: &str,
+) -> bool {
+    verify(
+        origin,
+        &relay_bytes(id, &origin.to_base58(), message),
+        signature,
+    )
+}
+
+/// Signs the delivery confirmation on behalf of the recipient
+pub fn sign_relay_ack(key: &Keypair, id: &str) -> Result<String> {
+    sign(key, &signed_bytes(&[RELAY_ACK_DOMAIN, id.as_bytes()]))
+}
+
+/// Checks that the delivery confirmation is signed by the recipient
+pub fn verify_relay_ack(id: &str, recipient: &PeerId, signature: &str) -> bool {
+    verify(
+        recipient,
+        &signed_bytes(&[RELAY_ACK_DOMAIN, id.as_bytes()]),
+        signature,
+    )
+}
+
+fn relay_bytes(id: &str, origin: &str, message: &PlainTextMessage) -> Vec<u8> {
+    signed_bytes(&[
+        RELAY_DOMAIN,
+        id.as_bytes(),
+        origin.as_bytes(),
+        message.to.as_bytes(),
+        &message.timestamp.to_be_bytes(),
+        message.text.as_bytes(),
+    ])
+}
+
+/// Length prefixed fields, so that the fields can't be shifted into each other
+fn signed_bytes(fields: &[&[u8]]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for field in fields {
+        bytes.extend_from_slice(&(field.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(field);
+    }
+    bytes
+}
+
+fn sign(key: &Keypair, bytes: &[u8]) -> Result<String> {
+    let signature = key
+        .sign(bytes)
+        .map_err(|e| Error::Msg(format!("Failed to sign: {}", e)))?;
+    Ok(base64::encode(signature))
+}
+
+fn verify(signer: &PeerId, bytes: &[u8], signature: &str) -> bool {
+    match (public_key_of(signer), base64::decode(signature)) {
+        (Ok(public_key), Ok(signature)) => public_key.verify(bytes, &signature),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> PlainTextMessage {
+        PlainTextMessage {
+            to: PeerId::random().to_base58(),
+            timestamp: 42,
+            text: "hello".to_string(),
+        }
+    }
+
+    #[test]
+    fn relay_is_verified_against_the_origin() {
+        let key = Keypair::generate_secp256k1();
+        let origin = key.public().into_peer_id();
+        let id = format!("{}:42", origin);
+        let message = message();
+        let signature = sign_relay(&key, &id, &origin.to_base58(), &message).unwrap();
+        assert!(verify_relay(&id, &origin, &message, &signature));
+
+        let forger = Keypair::generate_secp256k1();
+        assert!(!verify_relay(
+            &id,
+            &forger.public().into_peer_id(),
+            &message,
+            &signature
+        ));
+        let forged = sign_relay(&forger, &id, &origin.to_base58(), &message).unwrap();
+        assert!(!verify_relay(&id, &origin, &message, &forged));
+
+        let mut changed = message.clone();
+        changed.text = "bye".to_string();
+        assert!(!verify_relay(&id, &origin, &changed, &signature));
+        assert!(!verify_relay(&id, &origin, &message, "not base64"));
+    }
+
+    #[test]
+    fn relay_ack_is_verified_against_the_recipient() {
+        let key = Keypair::generate_secp256k1();
+        let recipient = key.public().into_peer_id();
+        let signature = sign_relay_ack(&key, "id").unwrap();
+        assert!(verify_relay_ack("id", &recipient, &signature));
+        assert!(!verify_relay_ack("other", &recipient, &signature));
+        let relay = Keypair::generate_secp256k1();
+        let forged = sign_relay_ack(&relay, "id").unwrap();
+        assert!(!verify_relay_ack("id", &recipient, &forged));
+    }
+}
@@ -0,0 +1,331 @@
+//! Rate limiting and misbehaviour scoring of peers
+
+use crate::config::ScoringConfig;
+use libp2p::PeerId;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+const CONNECTIONS_WINDOW: Duration = Duration::from_secs(60);
+
+/// Kinds of peer misbehaviour lowering its score
+#[derive(Debug, Clone, Copy)]
+pub enum Misbehaviour {
+    /// Sent a message that couldn't be parsed
+    MalformedMessage,
+    /// Exceeded the limits or violated the protocol
+    ProtocolViolation,
+    /// Sent messages faster than allowed
+    MessageFlood,
+    /// Reconnected faster than allowed
+    ConnectionFlood,
+}
+
+impl Misbehaviour {
+    /// Human readable reason shown to the user
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Misbehaviour::MalformedMessage => "malformed messages",
+            Misbehaviour::ProtocolViolation => "protocol violations",
+            Misbehaviour::MessageFlood => "message flood",
+            Misbehaviour::ConnectionFlood => "connection flood",
+        }
+    }
+}
+
+struct PeerScore {
+    score: i32,
+    score_updated: Instant,
+    tokens: f64,
+    tokens_updated: Instant,
+    connections: VecDeque<Instant>,
+    banned_until: Option<Instant>,
+}
+
+impl PeerScore {
+    fn new(config: &ScoringConfig, now: Instant) -> Self {
+        PeerScore {
+            score: 0,
+            score_updated: now,
+            tokens: config.message_burst as f64,
+            tokens_updated: now,
+            connections: VecDeque::new(),
+            banned_until: None,
+        }
+    }
+
+    fn recover(&mut self, config: &ScoringConfig, now: Instant) {
+        let secs = now.saturating_duration_since(self.score_updated).as_secs();
+        if secs == 0 {
+            return;
+        }
+        let recovered = (secs as i64 * config.recovery_per_sec as i64).min(i32::MAX as i64);
+        self.score = (self.score as i64 + recovered).min(0) as i32;
+        self.score_updated += Duration::from_secs(secs);
+    }
+
+    fn refill(&mut self, config: &ScoringConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.tokens_updated);
+        let refilled = elapsed.as_secs_f64() * config.messages_per_sec as f64;
+        self.tokens = (self.tokens + refilled).min(config.message_burst as f64);
+        self.tokens_updated = now;
+    }
+}
+
+/// Keeps track of peers misbehaviour. Peers with a low score are temporarily banned.
+pub struct PeerScores {
+    config: ScoringConfig,
+    peers: HashMap<PeerId, PeerScore>,
+}
+
+impl PeerScores {
+    pub fn new(config: ScoringConfig) -> Self {
+        PeerScores {
+            config,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Returns `true` if the peer is currently banned
+    pub fn is_banned(&self, peer_id: &PeerId, now: Instant) -> bool {
+        self.peers
+            .get(peer_id)
+            .and_then(|peer| peer.banned_until)
+            .map(|until| until > now)
+            .unwrap_or(false)
+    }
+
+    /// Takes a token for an incoming message. If the peer is out of tokens the message
+    /// should be dropped, in this case the peer is penalized and `Err` is returned
+    /// with `Some` ban deadline if the peer has just been banned.
+    pub fn check_message(
+        &mut self,
+        peer_id: &PeerId,
+        now: Instant,
+    ) -> std::result::Result<(), Option<Instant>> {
+        let config = &self.config;
+        let peer = self
+            .peers
+            .entry(peer_id.clone())
+            .or_insert_with(|| PeerScore::new(config, now));
+        peer.refill(config, now);
+        if peer.tokens >= 1.0 {
+            peer.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(self.penalize(peer_id, Misbehaviour::MessageFlood, now))
+    }
+
+    /// Records an inbound connection from the peer. Returns `Some` ban deadline
+    /// if the peer has just been banned for reconnecting too often.
+    pub fn record_connection(&mut self, peer_id: &PeerId, now: Instant) -> Option<Instant> {
+        let config = &self.config;
+        let peer = self
+            .peers
+            .entry(peer_id.clone())
+            .or_insert_with(|| PeerScore::new(config, now));
+        while let Some(time) = peer.connections.front() {
+            if now.saturating_duration_since(*time) < CONNECTIONS_WINDOW {
+                break;
+            }
+            peer.connections.pop_front();
+        }
+        peer.connections.push_back(now);
+        if peer.connections.len() > config.connections_per_min as usize {
+            return self.penalize(peer_id, Misbehaviour::ConnectionFlood, now);
+        }
+        None
+    }
+
+    /// Lowers the score of the peer. Returns `Some` ban deadline if the peer has just been banned.
+    pub fn penalize(
+        &mut self,
+        peer_id: &PeerId,
+        misbehaviour: Misbehaviour,
+        now: Instant,
+    ) -> Option<Instant> {
+        let config = &self.config;
+        let peer = self
+            .peers
+            .entry(peer_id.clone())
+            .or_insert_with(|| PeerScore::new(config, now));
+        if peer.banned_until.map(|until| until > now).unwrap_or(false) {
+            return None;
+        }
+        peer.recover(config, now);
+        let penalty = match misbehaviour {
+            Misbehaviour::MalformedMessage => config.malformed_message_penalty,
+            Misbehaviour::ProtocolViolation => config.protocol_violation_penalty,
+            Misbehaviour::MessageFlood => config.message_flood_penalty,
+            Misbehaviour::ConnectionFlood => config.connection_flood_penalty,
+        };
+        peer.score = peer.score.saturating_sub(penalty);
+        log::debug!(
+            "Peer {} penalized for {}, score: {}",
+            peer_id,
+            misbehaviour.reason(),
+            peer.score
+        );
+        if peer.score > config.ban_threshold {
+            return None;
+        }
+        let until = now + config.ban_duration();
+        peer.banned_until = Some(until);
+        Some(until)
+    }
+
+    /// Lifts expired bans and returns the peers that were unbanned
+    pub fn expire_bans(&mut self, now: Instant) -> Vec<PeerId> {
+        let mut unbanned = vec![];
+        for (peer_id, peer) in self.peers.iter_mut() {
+            if let Some(until) = peer.banned_until {
+                if until <= now {
+                    peer.banned_until = None;
+                    peer.score = 0;
+                    peer.score_updated = now;
+                    unbanned.push(peer_id.clone());
+                }
+            }
+        }
+        // Forget peers that are in good standing to keep the memory bounded
+        let config = &self.config;
+        self.peers.retain(|_, peer| {
+            peer.recover(config, now);
+            peer.refill(config, now);
+            peer.connections
+                .retain(|time| now.saturating_duration_since(*time) < CONNECTIONS_WINDOW);
+            peer.banned_until.is_some()
+                || peer.score < 0
+                || !peer.connections.is_empty()
+                || peer.tokens < config.message_burst as f64
+        });
+        unbanned
+    }
+
+    /// The earliest moment one of the bans expires
+    pub fn next_expiration(&self) -> Option<Instant> {
+        self.peers
+            .values()
+            .filter_map(|peer| peer.banned_until)
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ScoringConfig {
+        ScoringConfig {
+            messages_per_sec: 2,
+            message_burst: 4,
+            connections_per_min: 2,
+            message_flood_penalty: 10,
+            connection_flood_penalty: 30,
+            recovery_per_sec: 1,
+            ban_threshold: -50,
+            ban_duration_secs: 60,
+            ..ScoringConfig::default()
+        }
+    }
+
+    #[test]
+    fn allows_a_burst_then_limits_messages() {
+        let mut scores = PeerScores::new(config());
+        let peer_id = PeerId::random();
+        let now = Instant::now();
+        for _ in 0..4 {
+            assert_eq!(scores.check_message(&peer_id, now), Ok(()));
+        }
+        assert_eq!(scores.check_message(&peer_id, now), Err(None));
+        assert!(!scores.is_banned(&peer_id, now));
+    }
+
+    #[test]
+    fn refills_tokens_at_the_sustained_rate() {
+        let mut scores = PeerScores::new(config());
+        let peer_id = PeerId::random();
+        let now = Instant::now();
+        for _ in 0..4 {
+            assert_eq!(scores.check_message(&peer_id, now), Ok(()));
+        }
+        let later = now + Duration::from_secs(1);
+        assert_eq!(scores.check_message(&peer_id, later), Ok(()));
+        assert_eq!(scores.check_message(&peer_id, later), Ok(()));
+        assert_eq!(scores.check_message(&peer_id, later), Err(None));
+    }
+
+    #[test]
+    fn bans_flooding_peers_and_lifts_the_ban() {
+        let mut scores = PeerScores::new(config());
+        let peer_id = PeerId::random();
+        let other = PeerId::random();
+        let now = Instant::now();
+        for _ in 0..4 {
+            assert_eq!(scores.check_message(&peer_id, now), Ok(()));
+        }
+        for _ in 0..4 {
+            assert_eq!(scores.check_message(&peer_id, now), Err(None));
+        }
+        let until = now + Duration::from_secs(60);
+        assert_eq!(scores.check_message(&peer_id, now), Err(Some(until)));
+        assert!(scores.is_banned(&peer_id, now));
+        assert!(!scores.is_banned(&other, now));
+        assert_eq!(scores.next_expiration(), Some(until));
+        // Already banned peers are not banned again
+        assert_eq!(scores.check_message(&peer_id, now), Err(None));
+
+        assert!(scores
+            .expire_bans(until - Duration::from_secs(1))
+            .is_empty());
+        assert_eq!(scores.expire_bans(until), vec![peer_id.clone()]);
+        assert!(!scores.is_banned(&peer_id, until));
+        assert_eq!(scores.next_expiration(), None);
+        assert_eq!(scores.check_message(&peer_id, until), Ok(()));
+    }
+
+    #[test]
+    fn score_recovers_over_time() {
+        let mut scores = PeerScores::new(config());
+        let peer_id = PeerId::random();
+        let now = Instant::now();
+        assert_eq!(
+            scores.penalize(&peer_id, Misbehaviour::ConnectionFlood, now),
+            None
+        );
+        // -30 recovers to -10 after 20 seconds, so another -30 stays above the threshold
+        let later = now + Duration::from_secs(20);
+        assert_eq!(
+            scores.penalize(&peer_id, Misbehaviour::ConnectionFlood, later),
+            None
+        );
+        assert!(scores
+            .penalize(&peer_id, Misbehaviour::ConnectionFlood, later)
+            .is_some());
+    }
+
+    #[test]
+    fn penalizes_reconnecting_too_often() {
+        let mut scores = PeerScores::new(config());
+        let peer_id = PeerId::random();
+        let now = Instant::now();
+        assert_eq!(scores.record_connection(&peer_id, now), None);
+        assert_eq!(scores.record_connection(&peer_id, now), None);
+        assert_eq!(scores.record_connection(&peer_id, now), None);
+        assert!(scores.record_connection(&peer_id, now).is_some());
+        assert!(scores.is_banned(&peer_id, now));
+        // Connections outside of the window are forgotten
+        let later = now + Duration::from_secs(120);
+        scores.expire_bans(later);
+        assert_eq!(scores.record_connection(&peer_id, later), None);
+    }
+
+    #[test]
+    fn forgets_peers_in_good_standing() {
+        let mut scores = PeerScores::new(config());
+        let peer_id = PeerId::random();
+        let now = Instant::now();
+        assert_eq!(scores.check_message(&peer_id, now), Ok(()));
+        scores.expire_bans(now + Duration::from_secs(10));
+        assert!(scores.peers.is_empty());
+    }
+}
@@ -0,0 +1,14 @@
+//! Rendezvous discovery.
+//!
+//! Peers register their addresses under a namespace (e.g. a team name) at a rendezvous
+//! server and query it for the addresses of the other peers in the namespace. Works across
+//! network segments where mDNS can't reach.
+mod client;
+mod protocol;
+mod server;
+
+pub use client::RendezvousClient;
+pub use protocol::{
+    Registration, RendezvousCodec, RendezvousProtocol, RendezvousRequest, RendezvousResponse,
+};
+pub use server::RendezvousServer;
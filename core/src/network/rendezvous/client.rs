@@ -0,0 +1,116 @@
+use super::protocol::RendezvousRequest;
+use futures::FutureExt;
+use futures_timer::Delay;
+use libp2p::{Multiaddr, PeerId};
+use std::collections::HashSet;
+use std::task::Context;
+use std::time::{Duration, Instant};
+
+/// Keeps track of the namespaces we are registered in and decides
+/// when to refresh the registrations and discover the other peers
+pub struct RendezvousClient {
+    server: Option<PeerId>,
+    namespaces: HashSet<String>,
+    ttl_secs: u64,
+    discover_interval: Duration,
+    discover_timer: Delay,
+    /// Addresses sent in the last registration
+    registered_addresses: Vec<String>,
+    /// `None` if the registrations should be sent as soon as the addresses are known
+    next_registration: Option<Instant>,
+    /// Peers already reported as discovered
+    discovered: HashSet<PeerId>,
+}
+
+impl RendezvousClient {
+    pub fn new(ttl_secs: u64, discover_interval: Duration) -> Self {
+        RendezvousClient {
+            server: None,
+            namespaces: HashSet::new(),
+            ttl_secs,
+            discover_interval,
+            discover_timer: Delay::new(discover_interval),
+            registered_addresses: vec![],
+            next_registration: None,
+            discovered: HashSet::new(),
+        }
+    }
+
+    /// Rendezvous server in use
+    pub fn server(&self) -> Option<&PeerId> {
+        self.server.as_ref()
+    }
+
+    /// Use the rendezvous server. Registrations are sent to the new server right away.
+    pub fn set_server(&mut self, peer_id: PeerId) {
+        self.server = Some(peer_id);
+        self.next_registration = None;
+    }
+
+    /// Register in the namespace and keep the registration refreshed
+    pub fn register(&mut self, namespace: String) {
+        self.namespaces.insert(namespace);
+        self.next_registration = None;
+    }
+
+    /// Stop refreshing the registration in the namespace.
+    /// Returns `false` if we weren't registered in it.
+    pub fn unregister(&mut self, namespace: &str) -> bool {
+        self.namespaces.remove(namespace)
+    }
+
+    /// Returns `true` if the peer is discovered for the first time
+    pub fn discovered(&mut self, peer_id: &PeerId) -> bool {
+        self.discovered.insert(peer_id.clone())
+    }
+
+    /// Returns the requests to be sent to the server: registrations when they are due
+    /// or our addresses change, and periodic discovery of the namespaces.
+    pub fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+        addresses: Vec<Multiaddr>,
+    ) -> Vec<(PeerId, RendezvousRequest)> {
+        let server = match &self.server {
+            Some(server) => server.clone(),
+            None => return vec![],
+        };
+        let mut requests = vec![];
+        let now = Instant::now();
+        let addresses = addresses
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let registration_due = self
+            .next_registration
+            .map(|next| next <= now)
+            .unwrap_or(true)
+            || addresses != self.registered_addresses;
+        if registration_due && !addresses.is_empty() && !self.namespaces.is_empty() {
+            for namespace in self.namespaces.iter() {
+                requests.push(RendezvousRequest::Register {
+                    namespace: namespace.clone(),
+                    addresses: addresses.clone(),
+                    ttl_secs: self.ttl_secs,
+                });
+                requests.push(RendezvousRequest::Discover {
+                    namespace: namespace.clone(),
+                });
+            }
+            self.registered_addresses = addresses;
+            self.next_registration = Some(now + Duration::from_secs(self.ttl_secs / 2));
+        }
+        while self.discover_timer.poll_unpin(cx).is_ready() {
+            for namespace in self.namespaces.iter() {
+                requests.push(RendezvousRequest::Discover {
+                    namespace: namespace.clone(),
+                });
+            }
+            self.discover_timer.reset(self.discover_interval);
+        }
+        requests
+            .into_iter()
+            .map(|request| (server.clone(), request))
+            .collect()
+    }
+}
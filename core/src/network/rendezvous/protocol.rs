@@ -0,0 +1,58 @@
+use crate::network::json_codec::JsonCodec;
+use libp2p::core::ProtocolName;
+use serde::{Deserialize, Serialize};
+
+/// Addresses of a peer registered in a namespace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Registration {
+    /// Base58 peer id
+    pub peer_id: String,
+    /// Multiaddresses the peer can be dialed at
+    pub addresses: Vec<String>,
+}
+
+/// Request sent to the rendezvous server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum RendezvousRequest {
+    /// Register the requesting peer in the namespace for `ttl_secs`
+    Register {
+        namespace: String,
+        addresses: Vec<String>,
+        ttl_secs: u64,
+    },
+    /// Remove the registration of the requesting peer
+    Unregister { namespace: String },
+    /// Get the peers registered in the namespace
+    Discover { namespace: String },
+}
+
+/// Response of the rendezvous server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum RendezvousResponse {
+    /// The peer is registered for `ttl_secs`
+    Registered { namespace: String, ttl_secs: u64 },
+    /// The registration is removed
+    Unregistered { namespace: String },
+    /// Peers registered in the namespace
+    Registrations {
+        namespace: String,
+        registrations: Vec<Registration>,
+    },
+    /// The request is refused
+    Error { cause: String },
+}
+
+/// Rendezvous protocol name
+#[derive(Debug, Clone)]
+pub struct RendezvousProtocol;
+
+impl ProtocolName for RendezvousProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        b"/tata/rendezvous/1.0.0"
+    }
+}
+
+/// Json codec for rendezvous requests and responses
+pub type RendezvousCodec = JsonCodec<RendezvousProtocol, RendezvousRequest, RendezvousResponse>;
@@ -0,0 +1,203 @@
+use super::protocol::{
+    Registration, RendezvousCodec, RendezvousProtocol, RendezvousRequest, RendezvousResponse,
+};
+use crate::config::RendezvousServerConfig;
+use crate::transport::parse_address;
+use libp2p::{
+    ping::{Ping, PingConfig, PingEvent},
+    request_response::{
+        ProtocolSupport, RequestResponse, RequestResponseConfig, RequestResponseEvent,
+        RequestResponseMessage,
+    },
+    swarm::NetworkBehaviourEventProcess,
+    NetworkBehaviour, PeerId,
+};
+use std::collections::HashMap;
+use std::iter;
+use std::time::{Duration, Instant};
+
+struct Entry {
+    addresses: Vec<String>,
+    expires: Instant,
+}
+
+/// Network behaviour of the rendezvous server
+#[derive(NetworkBehaviour)]
+pub struct RendezvousServer {
+    /// Answers the pings of the nodes, which otherwise close the connection
+    ping: Ping,
+    request_response: RequestResponse<RendezvousCodec>,
+    #[behaviour(ignore)]
+    namespaces: HashMap<String, HashMap<PeerId, Entry>>,
+    #[behaviour(ignore)]
+    config: RendezvousServerConfig,
+}
+
+impl RendezvousServer {
+    pub fn new(config: RendezvousServerConfig) -> Self {
+        let request_response = RequestResponse::new(
+            RendezvousCodec::new(config.max_frame_size),
+            iter::once((RendezvousProtocol, ProtocolSupport::Full)),
+            RequestResponseConfig::default(),
+        );
+        RendezvousServer {
+            ping: Ping::new(PingConfig::new()),
+            request_response,
+            namespaces: HashMap::new(),
+            config,
+        }
+    }
+
+    fn handle_request(
+        &mut self,
+        peer_id: &PeerId,
+        request: RendezvousRequest,
+    ) -> RendezvousResponse {
+        let now = Instant::now();
+        self.expire(now);
+        match request {
+            RendezvousRequest::Register {
+                namespace,
+                addresses,
+                ttl_secs,
+            } => {
+                if let Err(cause) = self.validate(&namespace, &addresses) {
+                    return RendezvousResponse::Error { cause };
+                }
+                let registered = self.namespaces_of(peer_id);
+                let renewal = self
+                    .namespaces
+                    .get(&namespace)
+                    .is_some_and(|entries| entries.contains_key(peer_id));
+                if !renewal && registered >= self.config.max_namespaces_per_peer {
+                    return RendezvousResponse::Error {
+                        cause: format!(
+                            "Peer is registered in the max of {} namespaces",
+                            self.config.max_namespaces_per_peer
+                        ),
+                    };
+                }
+                let entries = self.namespaces.entry(namespace.clone()).or_default();
+                if !entries.contains_key(peer_id)
+                    && entries.len() >= self.config.max_registrations_per_namespace
+                {
+                    return RendezvousResponse::Error {
+                        cause: format!("Namespace {} is full", namespace),
+                    };
+                }
+                let ttl_secs = ttl_secs.min(self.config.max_ttl_secs);
+                log::debug!("Registering {} in {} for {}s", peer_id, namespace, ttl_secs);
+                entries.insert(
+                    peer_id.clone(),
+                    Entry {
+                        addresses,
+                        expires: now + Duration::from_secs(ttl_secs),
+                    },
+                );
+                RendezvousResponse::Registered {
+                    namespace,
+                    ttl_secs,
+                }
+            }
+            RendezvousRequest::Unregister { namespace } => {
+                if let Some(entries) = self.namespaces.get_mut(&namespace) {
+                    entries.remove(peer_id);
+                    if entries.is_empty() {
+                        self.namespaces.remove(&namespace);
+                    }
+                }
+                RendezvousResponse::Unregistered { namespace }
+            }
+            RendezvousRequest::Discover { namespace } => {
+                let registrations = self
+                    .namespaces
+                    .get(&namespace)
+                    .map(|entries| {
+                        entries
+                            .iter()
+                            .filter(|(registered, _)| *registered != peer_id)
+                            .map(|(registered, entry)| Registration {
+                                peer_id: registered.to_base58(),
+                                addresses: entry.addresses.clone(),
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                RendezvousResponse::Registrations {
+                    namespace,
+                    registrations,
+                }
+            }
+        }
+    }
+
+    fn validate(&self, namespace: &str, addresses: &[String]) -> Result<(), String> {
+        if namespace.is_empty() || namespace.len() > self.config.max_namespace_length {
+            return Err(format!(
+                "Namespace must be from 1 to {} bytes long",
+                self.config.max_namespace_length
+            ));
+        }
+        if addresses.is_empty() || addresses.len() > self.config.max_addresses {
+            return Err(format!(
+                "Registration must have from 1 to {} addresses",
+                self.config.max_addresses
+            ));
+        }
+        for address in addresses {
+            if let Err(e) = parse_address(address) {
+                return Err(format!("Invalid address {}: {}", address, e));
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of namespaces the peer is registered in
+    fn namespaces_of(&self, peer_id: &PeerId) -> usize {
+        self.namespaces
+            .values()
+            .filter(|entries| entries.contains_key(peer_id))
+            .count()
+    }
+
+    /// Removes expired registrations
+    fn expire(&mut self, now: Instant) {
+        for entries in self.namespaces.values_mut() {
+            entries.retain(|_, entry| entry.expires > now);
+        }
+        self.namespaces.retain(|_, entries| !entries.is_empty());
+    }
+}
+
+impl NetworkBehaviourEventProcess<RequestResponseEvent<RendezvousRequest, RendezvousResponse>>
+    for RendezvousServer
+{
+    fn inject_event(&mut self, event: RequestResponseEvent<RendezvousRequest, RendezvousResponse>) {
+        match event {
+            RequestResponseEvent::Message {
+                peer,
+                message:
+                    RequestResponseMessage::Request {
+                        request, channel, ..
+                    },
+            } => {
+                let response = self.handle_request(&peer, request);
+                self.request_response.send_response(channel, response);
+            }
+            RequestResponseEvent::Message {
+                peer,
+                message: RequestResponseMessage::Response { .. },
+            } => log::warn!("Unexpected response from {}", peer),
+            RequestResponseEvent::OutboundFailure { peer, error, .. } => {
+                log::error!("Outbound failure with {}: {:?}", peer, error)
+            }
+            RequestResponseEvent::InboundFailure { peer, error, .. } => {
+                log::error!("Inbound failure with {}: {:?}", peer, error)
+            }
+        }
+    }
+}
+
+impl NetworkBehaviourEventProcess<PingEvent> for RendezvousServer {
+    fn inject_event(&mut self, _: PingEvent) {}
+}
@@ -0,0 +1,557 @@
+//! Native Rust API of the node.
+//!
+//! [Node](./struct.Node.html) is a cheap to clone handle of the swarm running in the background.
+//! The events come from the [EventReceiver](./struct.EventReceiver.html) returned by
+//! [Node::start](./struct.Node.html#method.start), or filtered ones from
+//! [Node::subscribe](./struct.Node.html#method.subscribe). The swarm is stopped once all
+//! the handles are dropped.
+
+use crate::error::{Error, Result};
+use crate::event_queue::EventReceiver;
+use crate::network::CoreNetworkBehaviour;
+use crate::subscriptions::EventFilter;
+use crate::utils::{decode_peer_id, unix_timestamp};
+use crate::Config;
+use derive_more::Display;
+use futures::channel::{mpsc, oneshot};
+use futures::{FutureExt, SinkExt, StreamExt};
+use futures_timer::Delay;
+use libp2p::identity::secp256k1::SecretKey;
+use libp2p::swarm::AddressScore;
+use libp2p::{Multiaddr, PeerId, Swarm};
+use primitives::{PlainTextMessage, PresenceStatus};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+const COMMANDS_BUFFER_SIZE: usize = 10;
+/// Time to wait for the delivery of a message sent with `Node::send`
+pub const DEFAULT_DELIVERY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How the message reached the peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// Sent directly to the peer
+    Sent,
+    /// Stored in the mailbox until the peer fetches it
+    Deposited,
+    /// Relayed through other peers, the peer confirmed the delivery
+    Relayed,
+}
+
+/// Reason the message wasn't delivered
+#[derive(Debug, Clone, Display)]
+pub enum DeliveryError {
+    /// The peer refused the message, it couldn't be reached or the node is stopped
+    #[display(fmt = "Delivery failed: {}", _0)]
+    Failed(String),
+    /// No confirmation of the delivery in time. The message may still be delivered later.
+    #[display(fmt = "Delivery timed out")]
+    Timeout,
+}
+
+impl std::error::Error for DeliveryError {}
+
+pub type DeliveryResult = std::result::Result<Delivery, DeliveryError>;
+
+/// Future resolved once the message is delivered, the delivery fails or times out
+pub struct DeliveryFuture {
+    timestamp: u64,
+    result: oneshot::Receiver<DeliveryResult>,
+    timeout: Delay,
+}
+
+impl DeliveryFuture {
+    /// Timestamp of the message, i.e. its id in the events
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
+
+impl Future for DeliveryFuture {
+    type Output = DeliveryResult;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<DeliveryResult> {
+        match self.result.poll_unpin(cx) {
+            Poll::Ready(Ok(result)) => return Poll::Ready(result),
+            Poll::Ready(Err(_)) => {
+                return Poll::Ready(Err(DeliveryError::Failed("Node is stopped".to_string())))
+            }
+            Poll::Pending => (),
+        }
+        match self.timeout.poll_unpin(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(DeliveryError::Timeout)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Commands executed by the swarm task
+pub(crate) enum Command {
+    Send {
+        message: PlainTextMessage,
+        delivery: Option<oneshot::Sender<DeliveryResult>>,
+    },
+    BlockPeer(PeerId),
+    UnblockPeer(PeerId),
+    AllowPeer(PeerId),
+    DisallowPeer(PeerId),
+    AddAddress(PeerId, Multiaddr),
+    SetContactsOnly(bool),
+    ContactRequest(PeerId, String),
+    AcceptContactRequest(PeerId),
+    DeclineContactRequest(PeerId),
+    SetPresence(PresenceStatus),
+    SetForwarding(bool),
+    SetVisible(bool),
+    SetDiscovery(bool),
+    SetMailbox(PeerId, Multiaddr),
+    FetchMailbox,
+    SetRendezvous(PeerId, Multiaddr),
+    RegisterNamespace(String),
+    UnregisterNamespace(String),
+    DiscoverNamespace(String),
+    UpdateProfile {
+        name: String,
+        status: Option<String>,
+        avatar_hash: Option<String>,
+    },
+    ListenAddresses(oneshot::Sender<Vec<Multiaddr>>),
+    ExternalAddresses(oneshot::Sender<Vec<Multiaddr>>),
+    ConnectedPeers(oneshot::Sender<Vec<PeerId>>),
+    Contacts(oneshot::Sender<Vec<PeerId>>),
+    BlockedPeers(oneshot::Sender<Vec<PeerId>>),
+    Subscribe(EventFilter, oneshot::Sender<EventReceiver>),
+}
+
+/// Command and the sender of its result. Errors of the commands without the sender are logged.
+type Request = (Command, Option<oneshot::Sender<Result<()>>>);
+
+/// Handle of the node running in the background
+#[derive(Clone)]
+pub struct Node {
+    local_peer_id: PeerId,
+    commands: mpsc::Sender<Request>,
+    /// Timestamp of the last message sent with `send`, timestamps are ids of the messages
+    last_timestamp: Arc<AtomicU64>,
+}
+
+impl Node {
+    /// Create the swarm and run it in the background. Returns the handle of the node
+    /// and the stream of the events.
+    ///
+    /// # Arguments
+    /// `secret` - secret key for the current peer
+    ///
+    /// `name` - The username for the current user
+    ///
+    /// `config` - Network configuration, see [Config](./struct.Config.html)
+    pub fn start(secret: SecretKey, name: String, config: Config) -> Result<(Node, EventReceiver)> {
+        let (swarm, events) = crate::create_swarm(secret, name, config)?;
+        let local_peer_id = Swarm::local_peer_id(&swarm).clone();
+        let (commands_tx, commands_rx) = mpsc::channel(COMMANDS_BUFFER_SIZE);
+        async_std::task::spawn(NodeTask {
+            swarm,
+            commands: commands_rx,
+        });
+        let node = Node {
+            local_peer_id,
+            commands: commands_tx,
+            last_timestamp: Arc::new(AtomicU64::new(0)),
+        };
+        Ok((node, events))
+    }
+
+    /// Peer id of the node
+    pub fn local_peer_id(&self) -> &PeerId {
+        &self.local_peer_id
+    }
+
+    /// Send a message to the contact. Resolves once the message is queued and returns
+    /// the future of its delivery, which times out after `DEFAULT_DELIVERY_TIMEOUT`.
+    pub async fn send(&self, to: &PeerId, text: String) -> Result<DeliveryFuture> {
+        let message = PlainTextMessage {
+            to: to.to_base58(),
+            timestamp: self.next_timestamp(),
+            text,
+        };
+        self.send_message(message, DEFAULT_DELIVERY_TIMEOUT).await
+    }
+
+    /// Send a message with the timestamp chosen by the caller. The timestamp is the id
+    /// of the message and should be unique for the recipient: the delivery of a message
+    /// with the timestamp of another one still being delivered fails.
+    pub async fn send_message(
+        &self,
+        message: PlainTextMessage,
+        timeout: Duration,
+    ) -> Result<DeliveryFuture> {
+        let timestamp = message.timestamp;
+        let (delivery_tx, delivery_rx) = oneshot::channel();
+        self.request(Command::Send {
+            message,
+            delivery: Some(delivery_tx),
+        })
+        .await?;
+        Ok(DeliveryFuture {
+            timestamp,
+            result: delivery_rx,
+            timeout: Delay::new(timeout),
+        })
+    }
+
+    /// Block the peer. Connections with blocked peers are refused and messages to them are not sent.
+    pub async fn block_peer(&self, peer_id: PeerId) -> Result<()> {
+        self.request(Command::BlockPeer(peer_id)).await
+    }
+
+    /// Unblock previously blocked peer
+    pub async fn unblock_peer(&self, peer_id: PeerId) -> Result<()> {
+        self.request(Command::UnblockPeer(peer_id)).await
+    }
+
+    /// Add the peer to the allowlist (contacts)
+    pub async fn allow_peer(&self, peer_id: PeerId) -> Result<()> {
+        self.request(Command::AllowPeer(peer_id)).await
+    }
+
+    /// Remove the peer from the allowlist
+    pub async fn disallow_peer(&self, peer_id: PeerId) -> Result<()> {
+        self.request(Command::DisallowPeer(peer_id)).await
+    }
+
+    /// Remember the address of the peer to dial it when it can't be discovered,
+    /// e.g. without mDNS and rendezvous
+    pub async fn add_address(&self, peer_id: PeerId, address: Multiaddr) -> Result<()> {
+        self.request(Command::AddAddress(peer_id, address)).await
+    }
+
+    /// Accept messages only from contacts (enabled by default)
+    pub async fn set_contacts_only(&self, enabled: bool) -> Result<()> {
+        self.request(Command::SetContactsOnly(enabled)).await
+    }
+
+    /// Ask the peer to become a contact. `note` is a short introduction shown to the peer.
+    pub async fn send_contact_request(&self, peer_id: PeerId, note: String) -> Result<()> {
+        self.request(Command::ContactRequest(peer_id, note)).await
+    }
+
+    /// Accept the contact request received from the peer
+    pub async fn accept_contact_request(&self, peer_id: PeerId) -> Result<()> {
+        self.request(Command::AcceptContactRequest(peer_id)).await
+    }
+
+    /// Decline the contact request received from the peer
+    pub async fn decline_contact_request(&self, peer_id: PeerId) -> Result<()> {
+        self.request(Command::DeclineContactRequest(peer_id)).await
+    }
+
+    /// Set the presence status announced to contacts
+    pub async fn set_presence(&self, status: PresenceStatus) -> Result<()> {
+        self.request(Command::SetPresence(status)).await
+    }
+
+    /// Enable or disable forwarding of messages through connected peers
+    pub async fn set_forwarding(&self, enabled: bool) -> Result<()> {
+        self.request(Command::SetForwarding(enabled)).await
+    }
+
+    /// Start or stop advertising the node on the local network with mDNS
+    pub async fn set_visible(&self, visible: bool) -> Result<()> {
+        self.request(Command::SetVisible(visible)).await
+    }
+
+    /// Start or stop reporting the peers discovered on the local network
+    pub async fn set_discovery(&self, enabled: bool) -> Result<()> {
+        self.request(Command::SetDiscovery(enabled)).await
+    }
+
+    /// Use the mailbox node for messages to offline contacts
+    pub async fn set_mailbox(&self, peer_id: PeerId, address: Multiaddr) -> Result<()> {
+        self.request(Command::SetMailbox(peer_id, address)).await
+    }
+
+    /// Fetch the messages held by the mailbox
+    pub async fn fetch_mailbox(&self) -> Result<()> {
+        self.request(Command::FetchMailbox).await
+    }
+
+    /// Use the rendezvous server for discovery of peers outside of the local network
+    pub async fn set_rendezvous(&self, peer_id: PeerId, address: Multiaddr) -> Result<()> {
+        self.request(Command::SetRendezvous(peer_id, address)).await
+    }
+
+    /// Register in the namespace at the rendezvous server
+    pub async fn register_namespace(&self, namespace: String) -> Result<()> {
+        self.request(Command::RegisterNamespace(namespace)).await
+    }
+
+    /// Remove the registration in the namespace from the rendezvous server
+    pub async fn unregister_namespace(&self, namespace: String) -> Result<()> {
+        self.request(Command::UnregisterNamespace(namespace)).await
+    }
+
+    /// Query the rendezvous server for the peers in the namespace without registering in it
+    pub async fn discover_namespace(&self, namespace: String) -> Result<()> {
+        self.request(Command::DiscoverNamespace(namespace)).await
+    }
+
+    /// Update the profile announced to peers
+    pub async fn update_profile(
+        &self,
+        name: String,
+        status: Option<String>,
+        avatar_hash: Option<String>,
+    ) -> Result<()> {
+        self.request(Command::UpdateProfile {
+            name,
+            status,
+            avatar_hash,
+        })
+        .await
+    }
+
+    /// Addresses the node listens on
+    pub async fn listen_addresses(&self) -> Result<Vec<Multiaddr>> {
+        self.query(Command::ListenAddresses).await
+    }
+
+    /// Addresses of the node as observed by the peers
+    pub async fn external_addresses(&self) -> Result<Vec<Multiaddr>> {
+        self.query(Command::ExternalAddresses).await
+    }
+
+    /// Peers with an open chat connection
+    pub async fn connected_peers(&self) -> Result<Vec<PeerId>> {
+        self.query(Command::ConnectedPeers).await
+    }
+
+    /// Allowlisted peers, i.e. contacts
+    pub async fn contacts(&self) -> Result<Vec<PeerId>> {
+        self.query(Command::Contacts).await
+    }
+
+    /// Blocked peers
+    pub async fn blocked_peers(&self) -> Result<Vec<PeerId>> {
+        self.query(Command::BlockedPeers).await
+    }
+
+    /// Stream of the events matching the filter, in addition to the stream returned by
+    /// `start`. Drop the stream to unsubscribe.
+    pub async fn subscribe(&self, filter: EventFilter) -> Result<EventReceiver> {
+        self.query(|reply| Command::Subscribe(filter, reply)).await
+    }
+
+    /// Queue the message without waiting for the result. Errors of sending are reported
+    /// as failed delivery.
+    pub(crate) fn try_send_message(
+        &self,
+        message: PlainTextMessage,
+        timeout: Duration,
+    ) -> Option<DeliveryFuture> {
+        let timestamp = message.timestamp;
+        let (delivery_tx, delivery_rx) = oneshot::channel();
+        if !self.try_command(Command::Send {
+            message,
+            delivery: Some(delivery_tx),
+        }) {
+            return None;
+        }
+        Some(DeliveryFuture {
+            timestamp,
+            result: delivery_rx,
+            timeout: Delay::new(timeout),
+        })
+    }
+
+    /// Queue the command without waiting for the result. Errors are logged.
+    pub(crate) fn try_command(&self, command: Command) -> bool {
+        match self.commands.clone().try_send((command, None)) {
+            Ok(()) => true,
+            Err(e) => {
+                log::error!("Error sending command to the node: {}", e);
+                false
+            }
+        }
+    }
+
+    async fn request(&self, command: Command) -> Result<()> {
+        let (result_tx, result_rx) = oneshot::channel();
+        let mut commands = self.commands.clone();
+        commands
+            .send((command, Some(result_tx)))
+            .await
+            .map_err(|_| stopped())?;
+        result_rx.await.map_err(|_| stopped())?
+    }
+
+    async fn query<T>(&self, command: impl FnOnce(oneshot::Sender<T>) -> Command) -> Result<T> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.request(command(reply_tx)).await?;
+        reply_rx.await.map_err(|_| stopped())
+    }
+
+    fn next_timestamp(&self) -> u64 {
+        let now = unix_timestamp();
+        let mut last = self.last_timestamp.load(Ordering::Relaxed);
+        loop {
+            let next = now.max(last + 1);
+            match self.last_timestamp.compare_exchange(
+                last,
+                next,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return next,
+                Err(actual) => last = actual,
+            }
+        }
+    }
+}
+
+fn stopped() -> Error {
+    Error::Msg("Node is stopped".to_string())
+}
+
+/// Polls the swarm and executes the commands of the handles
+struct NodeTask {
+    swarm: Swarm<CoreNetworkBehaviour>,
+    commands: mpsc::Receiver<Request>,
+}
+
+impl NodeTask {
+    fn execute(&mut self, command: Command) -> Result<()> {
+        let swarm = &mut self.swarm;
+        match command {
+            Command::Send { message, delivery } => {
+                let timestamp = message.timestamp;
+                let sent = decode_peer_id(&message.to).and_then(|peer_id| {
+                    let to = peer_id.to_base58();
+                    if delivery.is_some() && swarm.is_delivery_pending(&to, timestamp) {
+                        return Err(Error::Msg(format!(
+                            "Message to {} with timestamp {} is already being delivered",
+                            to, timestamp
+                        )));
+                    }
+                    swarm.private_chat.send_message(message)?;
+                    Ok(to)
+                });
+                match (sent, delivery) {
+                    (Ok(to), Some(delivery)) => swarm.track_delivery(to, timestamp, delivery),
+                    (Err(e), Some(delivery)) => {
+                        let _ = delivery.send(Err(DeliveryError::Failed(e.to_string())));
+                        return Err(e);
+                    }
+                    (sent, None) => {
+                        sent?;
+                    }
+                }
+            }
+            Command::BlockPeer(peer_id) => {
+                Swarm::ban_peer_id(swarm, peer_id.clone());
+                swarm.private_chat.block_peer(peer_id);
+            }
+            Command::UnblockPeer(peer_id) => {
+                Swarm::unban_peer_id(swarm, peer_id.clone());
+                swarm.private_chat.unblock_peer(&peer_id);
+            }
+            Command::AllowPeer(peer_id) => swarm.private_chat.allow_peer(peer_id),
+            Command::DisallowPeer(peer_id) => swarm.private_chat.disallow_peer(&peer_id),
+            Command::AddAddress(peer_id, address) => {
+                swarm.private_chat.add_address(&peer_id, address)
+            }
+            Command::SetContactsOnly(enabled) => swarm.private_chat.set_contacts_only(enabled),
+            Command::ContactRequest(peer_id, note) => {
+                swarm.private_chat.send_contact_request(peer_id, note)?
+            }
+            Command::AcceptContactRequest(peer_id) => {
+                swarm.private_chat.accept_contact_request(peer_id)?
+            }
+            Command::DeclineContactRequest(peer_id) => {
+                swarm.private_chat.decline_contact_request(peer_id)?
+            }
+            Command::SetPresence(status) => swarm.private_chat.set_presence(status),
+            Command::SetForwarding(enabled) => swarm.private_chat.set_forwarding(enabled),
+            Command::SetVisible(visible) => swarm.set_visible(visible)?,
+            Command::SetDiscovery(enabled) => swarm.set_discovery(enabled)?,
+            Command::SetMailbox(peer_id, address) => swarm.set_mailbox(peer_id, address),
+            Command::FetchMailbox => swarm.fetch_mailbox()?,
+            Command::SetRendezvous(peer_id, address) => swarm.set_rendezvous(peer_id, address),
+            Command::RegisterNamespace(namespace) => swarm.register_namespace(namespace),
+            Command::UnregisterNamespace(namespace) => swarm.unregister_namespace(namespace)?,
+            Command::DiscoverNamespace(namespace) => swarm.discover_namespace(namespace)?,
+            Command::UpdateProfile {
+                name,
+                status,
+                avatar_hash,
+            } => swarm
+                .private_chat
+                .update_profile(name, status, avatar_hash)?,
+            Command::ListenAddresses(reply) => {
+                let _ = reply.send(Swarm::listeners(swarm).cloned().collect());
+            }
+            Command::ExternalAddresses(reply) => {
+                let addresses = Swarm::external_addresses(swarm)
+                    .map(|record| record.addr.clone())
+                    .collect();
+                let _ = reply.send(addresses);
+            }
+            Command::ConnectedPeers(reply) => {
+                let _ = reply.send(swarm.private_chat.connected_peers().cloned().collect());
+            }
+            Command::Contacts(reply) => {
+                let _ = reply.send(swarm.private_chat.contacts().cloned().collect());
+            }
+            Command::BlockedPeers(reply) => {
+                let _ = reply.send(swarm.private_chat.blocked_peers().cloned().collect());
+            }
+            Command::Subscribe(filter, reply) => {
+                let _ = reply.send(swarm.subscribe(filter));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Future for NodeTask {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let task = &mut *self;
+        loop {
+            match task.commands.poll_next_unpin(cx) {
+                Poll::Ready(Some((command, result_tx))) => {
+                    let result = task.execute(command);
+                    match result_tx {
+                        Some(result_tx) => {
+                            let _ = result_tx.send(result);
+                        }
+                        None => {
+                            if let Err(e) = result {
+                                log::error!("Error executing command: {}", e);
+                            }
+                        }
+                    }
+                }
+                Poll::Ready(None) => {
+                    log::debug!("All node handles are dropped, stopping the swarm");
+                    return Poll::Ready(());
+                }
+                Poll::Pending => break,
+            }
+        }
+        if let Poll::Ready(None) = task.swarm.poll_next_unpin(cx) {
+            log::error!("Swarm is finished");
+            return Poll::Ready(());
+        }
+        // The swarm keeps the addresses reported by identify only if they translate to
+        // the IP ports we listen on, so the observed addresses are added as they are
+        for address in task.swarm.take_observed_addresses() {
+            Swarm::add_external_address(&mut task.swarm, address, AddressScore::Finite(1));
+        }
+        Poll::Pending
+    }
+}
@@ -0,0 +1,59 @@
+//! Subscriptions of the host to the events it's interested in.
+use primitives::PeerEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Events a subscriber receives. Empty lists match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventFilter {
+    /// Event kinds, same as the `type` tag of the event, e.g. `ReceivedPlainTextMessage`
+    pub kinds: Vec<String>,
+    /// Base58 peer ids the events come from
+    pub peer_ids: Vec<String>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &PeerEvent) -> bool {
+        (self.kinds.is_empty() || self.kinds.iter().any(|kind| kind == event.event.kind()))
+            && (self.peer_ids.is_empty() || self.peer_ids.contains(&event.peer_id))
+    }
+}
+
+/// Independent subscribers, e.g. callbacks or queues, each with its own filter
+pub struct Subscriptions<T> {
+    subscribers: BTreeMap<u64, (EventFilter, T)>,
+}
+
+impl<T> Default for Subscriptions<T> {
+    fn default() -> Self {
+        Subscriptions {
+            subscribers: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T> Subscriptions<T> {
+    pub fn subscribe(&mut self, id: u64, filter: EventFilter, subscriber: T) {
+        self.subscribers.insert(id, (filter, subscriber));
+    }
+
+    /// Returns `false` if there's no such subscription
+    pub fn unsubscribe(&mut self, id: u64) -> bool {
+        self.subscribers.remove(&id).is_some()
+    }
+
+    /// Keeps only the subscribers for which the predicate holds
+    pub fn retain(&mut self, mut predicate: impl FnMut(&T) -> bool) {
+        self.subscribers
+            .retain(|_, (_, subscriber)| predicate(subscriber));
+    }
+
+    /// Subscribers whose filters match the event
+    pub fn matching<'a>(&'a self, event: &'a PeerEvent) -> impl Iterator<Item = &'a T> {
+        self.subscribers
+            .values()
+            .filter(move |(filter, _)| filter.matches(event))
+            .map(|(_, subscriber)| subscriber)
+    }
+}
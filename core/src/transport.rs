@@ -0,0 +1,570 @@
+//! Transport configuration
+
+use crate::config::{ProxyConfig, TransportConfig};
+use crate::error::{Error, Result};
+use async_std::net::TcpStream;
+use futures::future::BoxFuture;
+use futures::prelude::*;
+use futures::stream::BoxStream;
+use libp2p::core::either::EitherOutput;
+use libp2p::core::muxing::StreamMuxerBox;
+use libp2p::core::transport::{Boxed, ListenerEvent, MemoryTransport, TransportError};
+use libp2p::core::upgrade::{SelectUpgrade, Version};
+use libp2p::dns::DnsConfig;
+use libp2p::mplex::MplexConfig;
+use libp2p::multiaddr::Protocol;
+use libp2p::noise::{self, NoiseConfig, X25519Spec};
+use libp2p::pnet::{PnetConfig, PreSharedKey};
+use libp2p::tcp::TcpConfig;
+#[cfg(unix)]
+use libp2p::uds::UdsConfig;
+use libp2p::websocket::WsConfig;
+use libp2p::yamux::YamuxConfig;
+use libp2p::{identity, Multiaddr, PeerId, Transport};
+use std::io;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+const PSK_HEADER: &str = "/key/swarm/psk/1.0.0/\n/base16/\n";
+const TRANSPORT_TIMEOUT: Duration = Duration::from_secs(20);
+
+const SOCKS5_VERSION: u8 = 5;
+/// Version of the username/password sub-negotiation
+const SOCKS5_AUTH_VERSION: u8 = 1;
+const SOCKS5_NO_AUTH: u8 = 0;
+const SOCKS5_USERNAME_PASSWORD: u8 = 2;
+const SOCKS5_NO_ACCEPTABLE_METHODS: u8 = 0xff;
+const SOCKS5_CONNECT: u8 = 1;
+const SOCKS5_IPV4: u8 = 1;
+const SOCKS5_DOMAIN: u8 = 3;
+const SOCKS5_IPV6: u8 = 4;
+
+/// Parse the pre-shared key of a private network. Accepts either 64 hex characters
+/// or the `swarm.key` file format used by go-libp2p.
+pub fn parse_psk(psk: &str) -> Result<PreSharedKey> {
+    let psk = psk.trim();
+    let key_file = if psk.starts_with("/key/") {
+        psk.to_string()
+    } else {
+        format!("{}{}", PSK_HEADER, psk)
+    };
+    PreSharedKey::from_str(&key_file)
+        .map_err(|e| Error::Msg(format!("Invalid pre-shared key: {:?}", e)))
+}
+
+/// Generate a random pre-shared key for a private network, hex encoded
+pub fn generate_psk() -> String {
+    let bytes: [u8; 32] = rand::random();
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Parses the multiaddress. Unlike `Multiaddr::from_str`, accepts the absolute paths of
+/// Unix domain sockets, e.g. `/unix/tmp/tata.sock`, including the `/unix//tmp/tata.sock`
+/// form the addresses are displayed in.
+pub fn parse_address(address: &str) -> Result<Multiaddr> {
+    match address.strip_prefix("/unix/") {
+        Some(path) => Ok(Multiaddr::from(Protocol::Unix(
+            format!("/{}", path.trim_start_matches('/')).into(),
+        ))),
+        None => Ok(address.parse()?),
+    }
+}
+
+/// Builds the transport enabled in the config. Outbound TCP connections (including
+/// WebSocket) are dialed through the proxy if it's set.
+pub fn build_transport(
+    keypair: identity::Keypair,
+    psk: Option<PreSharedKey>,
+    proxy: Option<ProxyConfig>,
+    config: &TransportConfig,
+) -> Result<Boxed<(PeerId, StreamMuxerBox)>> {
+    let tcp = match proxy {
+        Some(proxy) => box_socket(Socks5Transport::new(proxy)),
+        None => box_socket(DnsConfig::new(TcpConfig::new().nodelay(true))?),
+    };
+    let mut transports = vec![];
+    if config.websocket {
+        transports.push(box_socket(WsConfig::new(tcp.clone())));
+    }
+    if config.tcp {
+        transports.push(tcp);
+    }
+    if config.unix {
+        transports.push(unix_transport()?);
+    }
+    if config.memory {
+        transports.push(box_socket(MemoryTransport));
+    }
+    let mut transports = transports.into_iter();
+    let transport = transports.next().ok_or("No transport is enabled")?;
+    let transport = transports.fold(transport, |transport, next| {
+        box_socket(transport.or_transport(next))
+    });
+    let transport = match psk {
+        Some(psk) => {
+            box_socket(transport.and_then(move |socket, _| PnetConfig::new(psk).handshake(socket)))
+        }
+        None => transport,
+    };
+    let noise_keys = noise::Keypair::<X25519Spec>::new()
+        .into_authentic(&keypair)
+        .expect("Signing libp2p-noise static DH keypair failed.");
+    Ok(transport
+        .upgrade(Version::V1)
+        .authenticate(NoiseConfig::xx(noise_keys).into_authenticated())
+        .multiplex(SelectUpgrade::new(
+            YamuxConfig::default(),
+            MplexConfig::new(),
+        ))
+        .timeout(TRANSPORT_TIMEOUT)
+        .boxed())
+}
+
+#[cfg(unix)]
+fn unix_transport() -> Result<Boxed<Socket>> {
+    Ok(box_socket(UdsConfig::new()))
+}
+
+#[cfg(not(unix))]
+fn unix_transport() -> Result<Boxed<Socket>> {
+    Err("Unix domain sockets are not supported on this platform".into())
+}
+
+/// Connection of any of the transports before the upgrade
+type Socket = Box<dyn AsyncSocket>;
+
+trait AsyncSocket: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> AsyncSocket for T {}
+
+/// Erases the type of the transport, so that any set of transports can be combined
+fn box_socket<T>(transport: T) -> Boxed<Socket>
+where
+    T: Transport + Clone + Send + Sync + 'static,
+    T::Output: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    T::Error: std::error::Error + Send + Sync + 'static,
+    T::Dial: Send + 'static,
+    T::Listener: Send + 'static,
+    T::ListenerUpgrade: Send + 'static,
+{
+    transport
+        .map(|socket, _| Box::new(socket) as Socket)
+        .boxed()
+}
+
+/// TCP transport dialing through a SOCKS5 proxy. Host names are resolved by the proxy,
+/// so no DNS queries leak around it. Listening is done directly with TCP, so inbound
+/// connections still work where the network allows them.
+#[derive(Debug, Clone)]
+pub struct Socks5Transport {
+    proxy: ProxyConfig,
+    tcp: TcpConfig,
+}
+
+impl Socks5Transport {
+    pub fn new(proxy: ProxyConfig) -> Self {
+        Socks5Transport {
+            proxy,
+            tcp: TcpConfig::new().nodelay(true),
+        }
+    }
+}
+
+impl Transport for Socks5Transport {
+    type Output = EitherOutput<<TcpConfig as Transport>::Output, TcpStream>;
+    type Error = io::Error;
+    type Listener = BoxStream<
+        'static,
+        std::result::Result<ListenerEvent<Self::ListenerUpgrade, io::Error>, io::Error>,
+    >;
+    type ListenerUpgrade = BoxFuture<'static, io::Result<Self::Output>>;
+    type Dial = BoxFuture<'static, io::Result<Self::Output>>;
+
+    fn listen_on(
+        self,
+        addr: Multiaddr,
+    ) -> std::result::Result<Self::Listener, TransportError<io::Error>> {
+        let listener = self.tcp.listen_on(addr)?;
+        Ok(listener
+            .map_ok(|event| event.map(|upgrade| upgrade.map_ok(EitherOutput::First).boxed()))
+            .boxed())
+    }
+
+    fn dial(self, addr: Multiaddr) -> std::result::Result<Self::Dial, TransportError<io::Error>> {
+        let (host, port) = match socks5_target(&addr) {
+            Some(target) => target,
+            None => return Err(TransportError::MultiaddrNotSupported(addr)),
+        };
+        let proxy = self.proxy;
+        Ok(async move {
+            let stream = socks5_connect(&proxy, host, port).await?;
+            Ok(EitherOutput::Second(stream))
+        }
+        .boxed())
+    }
+}
+
+/// Destination host as sent to the proxy
+enum Host {
+    Ip(IpAddr),
+    Domain(String),
+}
+
+/// Extracts the destination of `/ip4|ip6|dns4|dns6/<host>/tcp/<port>[/p2p/<peer id>]`
+fn socks5_target(addr: &Multiaddr) -> Option<(Host, u16)> {
+    let mut protocols = addr.iter();
+    let host = match protocols.next()? {
+        Protocol::Ip4(ip) => Host::Ip(ip.into()),
+        Protocol::Ip6(ip) => Host::Ip(ip.into()),
+        Protocol::Dns4(name) | Protocol::Dns6(name) => Host::Domain(name.into_owned()),
+        _ => return None,
+    };
+    let port = match protocols.next()? {
+        Protocol::Tcp(port) => port,
+        _ => return None,
+    };
+    match protocols.next() {
+        None | Some(Protocol::P2p(_)) => Some((host, port)),
+        _ => None,
+    }
+}
+
+/// Opens a connection to the destination through the proxy (RFC 1928, RFC 1929)
+async fn socks5_connect(proxy: &ProxyConfig, host: Host, port: u16) -> io::Result<TcpStream> {
+    let mut stream = TcpStream::connect(proxy.address.as_str())
+        .await
+        .map_err(|e| socks5_error(format!("can't connect to {}: {}", proxy.address, e)))?;
+    stream.set_nodelay(true)?;
+
+    let credentials = match (&proxy.username, &proxy.password) {
+        (Some(username), Some(password)) => Some((username, password)),
+        _ => None,
+    };
+    let method = if credentials.is_some() {
+        SOCKS5_USERNAME_PASSWORD
+    } else {
+        SOCKS5_NO_AUTH
+    };
+    stream.write_all(&[SOCKS5_VERSION, 1, method]).await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != SOCKS5_VERSION {
+        return Err(socks5_error(format!("unsupported version {}", reply[0])));
+    }
+    match (reply[1], credentials) {
+        (SOCKS5_NO_AUTH, _) => (),
+        (SOCKS5_USERNAME_PASSWORD, Some((username, password))) => {
+            if username.len() > 255 || password.len() > 255 {
+                return Err(socks5_error("username or password is too long".to_string()));
+            }
+            let mut request = vec![SOCKS5_AUTH_VERSION, username.len() as u8];
+            request.extend_from_slice(username.as_bytes());
+            request.push(password.len() as u8);
+            request.extend_from_slice(password.as_bytes());
+            stream.write_all(&request).await?;
+            stream.read_exact(&mut reply).await?;
+            if reply[0] != SOCKS5_AUTH_VERSION {
+                return Err(socks5_error(format!(
+                    "unsupported authentication version {}",
+                    reply[0]
+                )));
+            }
+            if reply[1] != 0 {
+                return Err(socks5_error("authentication failed".to_string()));
+            }
+        }
+        (SOCKS5_NO_ACCEPTABLE_METHODS, _) => {
+            return Err(socks5_error(
+                "no acceptable authentication method".to_string(),
+            ))
+        }
+        (method, _) => {
+            return Err(socks5_error(format!(
+                "unexpected authentication method {}",
+                method
+            )))
+        }
+    }
+
+    let mut request = vec![SOCKS5_VERSION, SOCKS5_CONNECT, 0];
+    match &host {
+        Host::Ip(IpAddr::V4(ip)) => {
+            request.push(SOCKS5_IPV4);
+            request.extend_from_slice(&ip.octets());
+        }
+        Host::Ip(IpAddr::V6(ip)) => {
+            request.push(SOCKS5_IPV6);
+            request.extend_from_slice(&ip.octets());
+        }
+        Host::Domain(name) => {
+            if name.len() > 255 {
+                return Err(socks5_error(format!("host name {} is too long", name)));
+            }
+            request.push(SOCKS5_DOMAIN);
+            request.push(name.len() as u8);
+            request.extend_from_slice(name.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != SOCKS5_VERSION {
+        return Err(socks5_error(format!("unsupported version {}", reply[0])));
+    }
+    if reply[1] != 0 {
+        return Err(socks5_error(socks5_reply_message(reply[1]).to_string()));
+    }
+    // Skip the address the proxy bound for the connection
+    let address_length = match reply[3] {
+        SOCKS5_IPV4 => 4,
+        SOCKS5_IPV6 => 16,
+        SOCKS5_DOMAIN => {
+            let mut length = [0u8; 1];
+            stream.read_exact(&mut length).await?;
+            length[0] as usize
+        }
+        address_type => {
+            return Err(socks5_error(format!(
+                "unexpected address type {}",
+                address_type
+            )))
+        }
+    };
+    let mut bound_address = vec![0u8; address_length + 2];
+    stream.read_exact(&mut bound_address).await?;
+    Ok(stream)
+}
+
+fn socks5_reply_message(reply: u8) -> &'static str {
+    match reply {
+        1 => "general failure",
+        2 => "connection not allowed by ruleset",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+fn socks5_error(cause: String) -> io::Error {
+    io::Error::other(format!("SOCKS5 proxy: {}", cause))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::net::TcpListener;
+    use async_std::task::{self, block_on, JoinHandle};
+    use std::env;
+
+    /// Starts a proxy answering each request of the client with the next reply.
+    /// Returns the address of the proxy and the requests it received.
+    async fn scripted_proxy(replies: Vec<Vec<u8>>) -> (String, JoinHandle<Vec<Vec<u8>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let requests = task::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut requests = vec![];
+            for reply in replies {
+                let mut request = vec![0u8; 512];
+                let size = stream.read(&mut request).await.unwrap();
+                request.truncate(size);
+                requests.push(request);
+                stream.write_all(&reply).await.unwrap();
+            }
+            requests
+        });
+        (address, requests)
+    }
+
+    /// Dials a node listening on the address over the transports in the config.
+    /// Returns the peer ids each side authenticated the other with.
+    async fn connect(config: TransportConfig, address: &str) -> (PeerId, PeerId) {
+        let listener_key = identity::Keypair::generate_secp256k1();
+        let dialer_key = identity::Keypair::generate_secp256k1();
+        let mut listener = build_transport(listener_key.clone(), None, None, &config)
+            .unwrap()
+            .listen_on(parse_address(address).unwrap())
+            .unwrap();
+        let address = match listener.next().await {
+            Some(Ok(ListenerEvent::NewAddress(address))) => address,
+            _ => panic!("Listener didn't report its address"),
+        };
+        let accepted = task::spawn(async move {
+            loop {
+                if let ListenerEvent::Upgrade { upgrade, .. } =
+                    listener.next().await.unwrap().unwrap()
+                {
+                    return upgrade.await.unwrap().0;
+                }
+            }
+        });
+        let dialed = build_transport(dialer_key.clone(), None, None, &config)
+            .unwrap()
+            .dial(address)
+            .unwrap()
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(dialed, listener_key.public().into_peer_id());
+        (dialed, accepted.await)
+    }
+
+    fn only(configure: impl FnOnce(&mut TransportConfig)) -> TransportConfig {
+        let mut config = TransportConfig {
+            tcp: false,
+            websocket: false,
+            unix: false,
+            memory: false,
+            listen_addresses: vec![],
+        };
+        configure(&mut config);
+        config
+    }
+
+    #[test]
+    fn unix_socket_paths_are_parsed() {
+        for address in &["/unix/tmp/tata.sock", "/unix//tmp/tata.sock"] {
+            let address = parse_address(address).unwrap();
+            assert_eq!(
+                address,
+                Multiaddr::from(Protocol::Unix("/tmp/tata.sock".into()))
+            );
+            assert_eq!(parse_address(&address.to_string()).unwrap(), address);
+        }
+        assert!(parse_address("/ip4/127.0.0.1/tcp/0").is_ok());
+        assert!(parse_address("/ip4/tata").is_err());
+    }
+
+    #[test]
+    fn websocket_is_off_by_default() {
+        assert!(!TransportConfig::default().websocket);
+    }
+
+    #[test]
+    fn connects_over_memory() {
+        block_on(async {
+            let address = format!("/memory/{}", rand::random::<u64>() | 1);
+            connect(only(|config| config.memory = true), &address).await;
+        });
+    }
+
+    #[test]
+    fn connects_over_websocket() {
+        block_on(async {
+            let config = only(|config| config.websocket = true);
+            connect(config, "/ip4/127.0.0.1/tcp/0/ws").await;
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn connects_over_unix_socket() {
+        block_on(async {
+            let path = env::temp_dir().join(format!("tata-{}.sock", rand::random::<u64>()));
+            let address = format!("/unix{}", path.display());
+            connect(only(|config| config.unix = true), &address).await;
+            let _ = std::fs::remove_file(path);
+        });
+    }
+
+    #[test]
+    fn transport_must_be_enabled() {
+        let key = identity::Keypair::generate_secp256k1();
+        assert!(build_transport(key, None, None, &only(|_| ())).is_err());
+    }
+
+    fn proxy_config(address: String, credentials: bool) -> ProxyConfig {
+        ProxyConfig {
+            address,
+            username: Some("user").filter(|_| credentials).map(String::from),
+            password: Some("secret").filter(|_| credentials).map(String::from),
+        }
+    }
+
+    #[test]
+    fn connects_through_the_proxy() {
+        block_on(async {
+            let mut connected = vec![SOCKS5_VERSION, 0, 0, SOCKS5_IPV4, 10, 0, 0, 1, 0, 80];
+            connected.extend_from_slice(b"hello");
+            let (address, requests) = scripted_proxy(vec![
+                vec![SOCKS5_VERSION, SOCKS5_USERNAME_PASSWORD],
+                vec![SOCKS5_AUTH_VERSION, 0],
+                connected,
+            ])
+            .await;
+            let host = Host::Domain("example.com".to_string());
+            let mut stream = socks5_connect(&proxy_config(address, true), host, 4001)
+                .await
+                .unwrap();
+            let mut data = [0u8; 5];
+            stream.read_exact(&mut data).await.unwrap();
+            assert_eq!(&data, b"hello");
+
+            let requests = requests.await;
+            assert_eq!(requests[0], [SOCKS5_VERSION, 1, SOCKS5_USERNAME_PASSWORD]);
+            assert_eq!(requests[1], b"\x01\x04user\x06secret");
+            let mut connect = vec![SOCKS5_VERSION, SOCKS5_CONNECT, 0, SOCKS5_DOMAIN, 11];
+            connect.extend_from_slice(b"example.com");
+            connect.extend_from_slice(&4001u16.to_be_bytes());
+            assert_eq!(requests[2], connect);
+        });
+    }
+
+    #[test]
+    fn replies_of_other_versions_are_refused() {
+        block_on(async {
+            let connected = vec![SOCKS5_VERSION, 0, 0, SOCKS5_IPV4, 10, 0, 0, 1, 0, 80];
+            let mut wrong_version = connected.clone();
+            wrong_version[0] = 4;
+            let scripts = vec![
+                (false, vec![vec![4, SOCKS5_NO_AUTH]]),
+                (
+                    true,
+                    vec![
+                        vec![SOCKS5_VERSION, SOCKS5_USERNAME_PASSWORD],
+                        vec![SOCKS5_VERSION, 0],
+                    ],
+                ),
+                (
+                    false,
+                    vec![vec![SOCKS5_VERSION, SOCKS5_NO_AUTH], wrong_version],
+                ),
+            ];
+            for (credentials, replies) in scripts {
+                let (address, _) = scripted_proxy(replies).await;
+                let host = Host::Ip([10, 0, 0, 1].into());
+                let error = socks5_connect(&proxy_config(address, credentials), host, 4001)
+                    .await
+                    .unwrap_err();
+                assert!(error.to_string().contains("version"), "{}", error);
+            }
+        });
+    }
+
+    #[test]
+    fn failure_reply_is_reported() {
+        block_on(async {
+            let (address, _) = scripted_proxy(vec![
+                vec![SOCKS5_VERSION, SOCKS5_NO_AUTH],
+                vec![SOCKS5_VERSION, 5, 0, SOCKS5_IPV4, 0, 0, 0, 0, 0, 0],
+            ])
+            .await;
+            let host = Host::Ip([10, 0, 0, 1].into());
+            let error = socks5_connect(&proxy_config(address, false), host, 4001)
+                .await
+                .unwrap_err();
+            assert!(
+                error.to_string().contains("connection refused"),
+                "{}",
+                error
+            );
+        });
+    }
+}
@@ -4,7 +4,12 @@ use libp2p::identity::{
     secp256k1::{Keypair, SecretKey},
     PublicKey,
 };
+use libp2p::multihash::{Code, Multihash};
 use libp2p::PeerId;
+use std::convert::TryFrom;
+use std::time::SystemTime;
+
+use crate::error::{Error, Result};
 
 /// Generate `libp2p` SecretKey and derive PeerId from it.
 pub fn generate_secret() -> (SecretKey, PeerId) {
@@ -13,3 +18,29 @@ pub fn generate_secret() -> (SecretKey, PeerId) {
     let peer_id = PeerId::from_public_key(public_key);
     (keypair.into(), peer_id)
 }
+
+/// Current unix timestamp in milliseconds
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Decode base58 encoded `PeerId`
+pub fn decode_peer_id(peer_id: &str) -> Result<PeerId> {
+    let peer_bytes = bs58::decode(peer_id).into_vec()?;
+    Ok(PeerId::from_bytes(peer_bytes)?)
+}
+
+/// Extract the public key inlined into the `PeerId`. Secp256k1 keys are small enough
+/// to be inlined, so the key of any peer of this network can be recovered.
+pub fn public_key_of(peer_id: &PeerId) -> Result<PublicKey> {
+    let multihash = Multihash::from_bytes(peer_id.as_bytes())
+        .map_err(|e| Error::Msg(format!("Invalid peer id: {}", e)))?;
+    if Code::try_from(multihash.code()).ok() != Some(Code::Identity) {
+        return Err(format!("Peer id {} doesn't contain the public key", peer_id).into());
+    }
+    PublicKey::from_protobuf_encoding(multihash.digest())
+        .map_err(|e| Error::Msg(format!("Invalid public key in peer id {}: {}", peer_id, e)))
+}
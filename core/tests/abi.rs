@@ -0,0 +1,51 @@
+//! The ABI version is the same in the library, the header and the soname
+
+use std::path::Path;
+use tata_core::{ffi, TATA_ABI_VERSION};
+
+#[test]
+fn header_has_the_abi_version() {
+    let header =
+        std::fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("headers/tata_core.h"))
+            .unwrap();
+    let version = header
+        .lines()
+        .find_map(|line| line.strip_prefix("#define TATA_ABI_VERSION "))
+        .expect("No TATA_ABI_VERSION in the header");
+    assert_eq!(version.trim().parse::<u32>().unwrap(), TATA_ABI_VERSION);
+}
+
+#[test]
+fn library_reports_the_abi_version() {
+    assert_eq!(ffi::tata_abi_version(), TATA_ABI_VERSION);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn soname_has_the_abi_version() {
+    use std::process::Command;
+
+    // The tests are in `target/<profile>/deps`, next to the library
+    let exe = std::env::current_exe().unwrap();
+    let library = exe
+        .parent()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .join("libtata_core.so");
+    let output = Command::new("readelf")
+        .arg("-d")
+        .arg(&library)
+        .output()
+        .expect("Error running readelf");
+    let dynamic = String::from_utf8(output.stdout).unwrap();
+    let soname = format!("[libtata_core.so.{}]", TATA_ABI_VERSION);
+    assert!(
+        dynamic
+            .lines()
+            .any(|line| line.contains("(SONAME)") && line.contains(&soname)),
+        "No soname {} in {}",
+        soname,
+        library.display()
+    );
+}
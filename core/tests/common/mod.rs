@@ -0,0 +1,113 @@
+//! Helpers running nodes connected over the in-memory transport
+
+#![allow(dead_code)]
+
+use async_std::future::timeout;
+use futures::StreamExt;
+use primitives::{Event, PeerEvent};
+use std::time::Duration;
+use tata_core::{
+    generate_secret, Config, DiscoveryConfig, EventReceiver, Multiaddr, Node, PeerId,
+    TransportConfig,
+};
+
+/// Max time to wait for an event
+pub const EVENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct TestNode {
+    pub node: Node,
+    pub events: EventReceiver,
+    pub address: Multiaddr,
+}
+
+impl TestNode {
+    pub fn peer_id(&self) -> PeerId {
+        self.node.local_peer_id().clone()
+    }
+
+    /// Waits for the first event matching the predicate, skipping the other events
+    pub async fn wait_for(&mut self, mut predicate: impl FnMut(&PeerEvent) -> bool) -> PeerEvent {
+        let events = &mut self.events;
+        let wait = async {
+            while let Some(event) = events.next().await {
+                if predicate(&event) {
+                    return event;
+                }
+            }
+            panic!("Node is stopped");
+        };
+        timeout(EVENT_TIMEOUT, wait)
+            .await
+            .expect("Timed out waiting for an event")
+    }
+
+    /// Waits for the event of the peer matching the predicate
+    pub async fn wait_for_event(
+        &mut self,
+        peer_id: &PeerId,
+        mut predicate: impl FnMut(&Event) -> bool,
+    ) -> Event {
+        let peer_id = peer_id.to_base58();
+        self.wait_for(|event| event.peer_id == peer_id && predicate(&event.event))
+            .await
+            .event
+    }
+}
+
+/// Config of a node listening on a random in-memory address, without mDNS
+pub fn memory_config() -> Config {
+    let port: u64 = rand::random::<u64>() | 1;
+    Config {
+        transport: TransportConfig {
+            tcp: false,
+            websocket: false,
+            unix: false,
+            memory: true,
+            listen_addresses: vec![format!("/memory/{}", port)],
+        },
+        discovery: DiscoveryConfig {
+            visible: false,
+            discover: false,
+        },
+        ..Config::default()
+    }
+}
+
+/// Starts a node with the config
+pub async fn start_with(name: &str, config: Config) -> TestNode {
+    let address = config.transport.listen_addresses[0].parse().unwrap();
+    let (secret, _) = generate_secret();
+    let (node, events) = Node::start(secret, name.to_string(), config).unwrap();
+    let mut node = TestNode {
+        node,
+        events,
+        address,
+    };
+    let local_peer_id = node.peer_id().to_base58();
+    node.wait_for(|event| {
+        event.peer_id == local_peer_id && matches!(event.event, Event::ListenAddressAdded { .. })
+    })
+    .await;
+    node
+}
+
+/// Starts a node listening on the in-memory transport
+pub async fn start(name: &str) -> TestNode {
+    start_with(name, memory_config()).await
+}
+
+/// Lets `a` dial `b`
+pub async fn add_address(a: &TestNode, b: &TestNode) {
+    a.node
+        .add_address(b.peer_id(), b.address.clone())
+        .await
+        .unwrap();
+}
+
+/// Makes the nodes contacts of each other and lets them dial each other
+pub async fn make_contacts(a: &TestNode, b: &TestNode) {
+    add_address(a, b).await;
+    add_address(b, a).await;
+    a.node.allow_peer(b.peer_id()).await.unwrap();
+    b.node.allow_peer(a.peer_id()).await.unwrap();
+}
@@ -0,0 +1,159 @@
+//! Contact requests and the contacts only mode
+
+mod common;
+
+use async_std::task::block_on;
+use primitives::Event;
+use tata_core::{Delivery, DeliveryError};
+
+#[test]
+fn contacts_only_refuses_messages_from_strangers() {
+    block_on(async {
+        let alice = common::start("alice").await;
+        let mut bob = common::start("bob").await;
+        common::add_address(&alice, &bob).await;
+        // Bob is a contact of alice, but alice isn't a contact of bob
+        alice.node.allow_peer(bob.peer_id()).await.unwrap();
+
+        let refused = alice.node.send(&bob.peer_id(), "refused".into()).await;
+        assert_eq!(refused.unwrap().await.unwrap(), Delivery::Sent);
+        // Frames are handled in order, so the message is handled once the request is received
+        alice
+            .node
+            .send_contact_request(bob.peer_id(), String::new())
+            .await
+            .unwrap();
+        bob.wait_for_event(&alice.peer_id(), |event| {
+            matches!(event, Event::ContactRequestReceived { .. })
+        })
+        .await;
+
+        bob.node.set_contacts_only(false).await.unwrap();
+        let accepted = alice.node.send(&bob.peer_id(), "accepted".into()).await;
+        assert_eq!(accepted.unwrap().await.unwrap(), Delivery::Sent);
+
+        // The first message bob receives is the one sent after contacts only was disabled
+        let event = bob
+            .wait_for_event(&alice.peer_id(), |event| {
+                matches!(event, Event::ReceivedPlainTextMessage { .. })
+            })
+            .await;
+        match event {
+            Event::ReceivedPlainTextMessage { message } => assert_eq!(message.text, "accepted"),
+            _ => unreachable!(),
+        }
+    });
+}
+
+#[test]
+fn messages_to_strangers_are_refused_locally() {
+    block_on(async {
+        let alice = common::start("alice").await;
+        let bob = common::start("bob").await;
+        let result = alice.node.send(&bob.peer_id(), "hi".into()).await;
+        assert!(result.is_err());
+    });
+}
+
+#[test]
+fn accepted_contact_request_delivers_pending_messages() {
+    block_on(async {
+        let mut alice = common::start("alice").await;
+        let mut bob = common::start("bob").await;
+        common::add_address(&alice, &bob).await;
+
+        alice
+            .node
+            .send_contact_request(bob.peer_id(), "it's alice".into())
+            .await
+            .unwrap();
+        // Waits for the acceptance
+        let delivery = alice
+            .node
+            .send(&bob.peer_id(), "hello".into())
+            .await
+            .unwrap();
+
+        let request = bob
+            .wait_for_event(&alice.peer_id(), |event| {
+                matches!(event, Event::ContactRequestReceived { .. })
+            })
+            .await;
+        match request {
+            Event::ContactRequestReceived { metadata, note } => {
+                assert_eq!(metadata.name, "alice");
+                assert_eq!(note, "it's alice");
+            }
+            _ => unreachable!(),
+        }
+        bob.node
+            .accept_contact_request(alice.peer_id())
+            .await
+            .unwrap();
+
+        alice
+            .wait_for_event(&bob.peer_id(), |event| {
+                matches!(event, Event::ContactRequestAccepted)
+            })
+            .await;
+        assert_eq!(delivery.await.unwrap(), Delivery::Sent);
+        let event = bob
+            .wait_for_event(&alice.peer_id(), |event| {
+                matches!(event, Event::ReceivedPlainTextMessage { .. })
+            })
+            .await;
+        match event {
+            Event::ReceivedPlainTextMessage { message } => assert_eq!(message.text, "hello"),
+            _ => unreachable!(),
+        }
+        assert_eq!(alice.node.contacts().await.unwrap(), vec![bob.peer_id()]);
+        assert_eq!(bob.node.contacts().await.unwrap(), vec![alice.peer_id()]);
+        // The request can be answered only once
+        assert!(bob
+            .node
+            .accept_contact_request(alice.peer_id())
+            .await
+            .is_err());
+    });
+}
+
+#[test]
+fn declined_contact_request_fails_pending_messages() {
+    block_on(async {
+        let mut alice = common::start("alice").await;
+        let mut bob = common::start("bob").await;
+        common::add_address(&alice, &bob).await;
+
+        alice
+            .node
+            .send_contact_request(bob.peer_id(), "it's alice".into())
+            .await
+            .unwrap();
+        let delivery = alice
+            .node
+            .send(&bob.peer_id(), "hello".into())
+            .await
+            .unwrap();
+
+        bob.wait_for_event(&alice.peer_id(), |event| {
+            matches!(event, Event::ContactRequestReceived { .. })
+        })
+        .await;
+        bob.node
+            .decline_contact_request(alice.peer_id())
+            .await
+            .unwrap();
+
+        alice
+            .wait_for_event(&bob.peer_id(), |event| {
+                matches!(event, Event::ContactRequestDeclined)
+            })
+            .await;
+        match delivery.await {
+            Err(DeliveryError::Failed(cause)) => assert_eq!(cause, "Contact request was declined"),
+            result => panic!("Unexpected delivery result: {:?}", result),
+        }
+        assert!(alice.node.contacts().await.unwrap().is_empty());
+        assert!(bob.node.contacts().await.unwrap().is_empty());
+    });
+}
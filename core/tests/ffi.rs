@@ -0,0 +1,32 @@
+//! The C API runs a single node per process
+
+use primitives::{ffi::ByteArray, LogLevel};
+use tata_core::{ffi, generate_secret};
+
+extern "C" fn ignore_event(event: ByteArray) {
+    drop(Vec::<u8>::from(event));
+}
+
+fn start() -> bool {
+    let (secret, _) = generate_secret();
+    let port: u64 = rand::random::<u64>() | 1;
+    let config = format!(
+        r#"{{"transport": {{"tcp": false, "memory": true, "listen_addresses": ["/memory/{}"]}},
+            "discovery": {{"visible": false, "discover": false}}}}"#,
+        port
+    );
+    ffi::start_network_with_config(
+        secret.to_bytes().to_vec().into(),
+        "alice".to_string().into(),
+        config.into(),
+        ignore_event,
+        false,
+        LogLevel::Off,
+    )
+}
+
+#[test]
+fn second_start_is_refused() {
+    assert!(start());
+    assert!(!start());
+}
@@ -0,0 +1,58 @@
+//! Messages relayed through connected peers
+
+mod common;
+
+use async_std::task::block_on;
+use primitives::Event;
+use tata_core::{Delivery, DeliveryError};
+
+#[test]
+fn relayed_message_is_delivered_from_the_origin() {
+    block_on(async {
+        let alice = common::start("alice").await;
+        let mut bob = common::start("bob").await;
+        let carol = common::start("carol").await;
+        common::make_contacts(&alice, &carol).await;
+        common::make_contacts(&bob, &carol).await;
+        alice.node.set_forwarding(true).await.unwrap();
+        carol.node.set_forwarding(true).await.unwrap();
+        // Connects alice and bob to carol
+        for node in &[&alice, &bob] {
+            let delivery = node.node.send(&carol.peer_id(), "hi".into()).await;
+            assert_eq!(delivery.unwrap().await.unwrap(), Delivery::Sent);
+        }
+
+        // Alice doesn't know the address of bob, so the message goes through carol
+        alice.node.allow_peer(bob.peer_id()).await.unwrap();
+        bob.node.allow_peer(alice.peer_id()).await.unwrap();
+        let delivery = alice.node.send(&bob.peer_id(), "relayed".into()).await;
+        assert_eq!(delivery.unwrap().await.unwrap(), Delivery::Relayed);
+
+        let event = bob
+            .wait_for_event(&alice.peer_id(), |event| {
+                matches!(event, Event::ReceivedPlainTextMessage { .. })
+            })
+            .await;
+        match event {
+            Event::ReceivedPlainTextMessage { message } => assert_eq!(message.text, "relayed"),
+            _ => unreachable!(),
+        }
+    });
+}
+
+#[test]
+fn message_without_peers_to_relay_through_fails() {
+    block_on(async {
+        let alice = common::start("alice").await;
+        let bob = common::start("bob").await;
+        alice.node.set_forwarding(true).await.unwrap();
+        alice.node.allow_peer(bob.peer_id()).await.unwrap();
+
+        // Alice doesn't know the address of bob and has neither peers nor a mailbox
+        let delivery = alice.node.send(&bob.peer_id(), "lost".into()).await;
+        assert!(matches!(
+            delivery.unwrap().await,
+            Err(DeliveryError::Failed(_))
+        ));
+    });
+}
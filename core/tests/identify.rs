@@ -0,0 +1,45 @@
+//! Identification of the connected peers
+
+mod common;
+
+use async_std::task::block_on;
+use primitives::Event;
+
+#[test]
+fn connected_peers_are_identified() {
+    block_on(async {
+        let alice = common::start("alice").await;
+        let mut bob = common::start("bob").await;
+        // Alice dials bob, so bob learns the address she listens on from identify only
+        common::add_address(&alice, &bob).await;
+        alice.node.allow_peer(bob.peer_id()).await.unwrap();
+        bob.node.allow_peer(alice.peer_id()).await.unwrap();
+        let delivery = alice.node.send(&bob.peer_id(), "hi".into()).await.unwrap();
+        delivery.await.unwrap();
+
+        let event = bob
+            .wait_for_event(&alice.peer_id(), |event| {
+                matches!(event, Event::PeerInfo { .. })
+            })
+            .await;
+        match event {
+            Event::PeerInfo {
+                agent_version,
+                protocol_version,
+                protocols,
+                listen_addresses,
+                observed_address,
+            } => {
+                assert!(agent_version.starts_with("tata-core/"));
+                assert_eq!(protocol_version, "/tata/1.0.0");
+                assert!(protocols.iter().any(|p| p == "/private_chat/2.0.0"));
+                assert!(protocols.iter().any(|p| p == "/ipfs/ping/1.0.0"));
+                assert_eq!(listen_addresses, vec![alice.address.to_string()]);
+                assert!(!observed_address.is_empty());
+                let external = bob.node.external_addresses().await.unwrap();
+                assert!(external.iter().any(|a| a.to_string() == observed_address));
+            }
+            _ => unreachable!(),
+        }
+    });
+}
@@ -0,0 +1,145 @@
+//! Round-trip times and dead connections detected with pings
+
+mod common;
+
+use async_std::net::{TcpListener, TcpStream};
+use async_std::task::{self, block_on};
+use futures::{future, AsyncReadExt, AsyncWriteExt};
+use primitives::Event;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tata_core::{
+    create_mailbox_swarm, generate_secret, Config, LivenessConfig, MailboxServerConfig,
+    TransportConfig,
+};
+
+/// Config pinging every second and giving up after two lost pongs
+fn pinging_config() -> Config {
+    Config {
+        liveness: LivenessConfig {
+            interval_secs: 1,
+            timeout_secs: 1,
+            max_failures: 2,
+        },
+        ..common::memory_config()
+    }
+}
+
+#[test]
+fn connected_peers_report_round_trip_time() {
+    block_on(async {
+        let mut alice = common::start_with("alice", pinging_config()).await;
+        let bob = common::start("bob").await;
+        common::make_contacts(&alice, &bob).await;
+        let delivery = alice.node.send(&bob.peer_id(), "hi".into()).await.unwrap();
+        delivery.await.unwrap();
+
+        let event = alice
+            .wait_for_event(&bob.peer_id(), |event| {
+                matches!(event, Event::PeerRtt { .. })
+            })
+            .await;
+        match event {
+            Event::PeerRtt { rtt_ms } => assert!(rtt_ms < common::EVENT_TIMEOUT.as_millis() as u64),
+            _ => unreachable!(),
+        }
+    });
+}
+
+#[test]
+fn connection_to_unresponsive_peer_is_closed() {
+    block_on(async {
+        let (secret, mailbox_peer_id) = generate_secret();
+        let mailbox_port = free_port();
+        let mut swarm = create_mailbox_swarm(
+            secret,
+            MailboxServerConfig {
+                listen_address: format!("/ip4/127.0.0.1/tcp/{}", mailbox_port),
+                transport: TransportConfig {
+                    listen_addresses: vec![],
+                    ..TransportConfig::default()
+                },
+                ..MailboxServerConfig::default()
+            },
+        )
+        .unwrap();
+        task::spawn(async move {
+            loop {
+                swarm.next().await;
+            }
+        });
+        // The mailbox is reached through the relay, which stops passing the pongs when paused
+        let paused = Arc::new(AtomicBool::new(false));
+        let relay_port = relay(mailbox_port, paused.clone()).await;
+
+        let mut config = pinging_config();
+        config.transport.tcp = true;
+        let alice = common::start_with("alice", config).await;
+        let relay_address = format!("/ip4/127.0.0.1/tcp/{}", relay_port);
+        alice
+            .node
+            .set_mailbox(mailbox_peer_id.clone(), relay_address.parse().unwrap())
+            .await
+            .unwrap();
+        let connected = |peers: Vec<_>| peers.contains(&mailbox_peer_id);
+        wait_until(|| async { connected(alice.node.connected_peers().await.unwrap()) }).await;
+
+        paused.store(true, Ordering::SeqCst);
+        wait_until(|| async { !connected(alice.node.connected_peers().await.unwrap()) }).await;
+    });
+}
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Forwards the connections to the port until paused. Paused connections are kept open.
+async fn relay(port: u16, paused: Arc<AtomicBool>) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let relay_port = listener.local_addr().unwrap().port();
+    task::spawn(async move {
+        while let Ok((inbound, _)) = listener.accept().await {
+            let outbound = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+            task::spawn(forward(inbound.clone(), outbound.clone(), paused.clone()));
+            task::spawn(forward(outbound, inbound, paused.clone()));
+        }
+    });
+    relay_port
+}
+
+async fn forward(mut from: TcpStream, mut to: TcpStream, paused: Arc<AtomicBool>) {
+    let mut buffer = [0u8; 4096];
+    loop {
+        let size = match from.read(&mut buffer).await {
+            Ok(0) | Err(_) => return,
+            Ok(size) => size,
+        };
+        if paused.load(Ordering::SeqCst) {
+            return future::pending().await;
+        }
+        if to.write_all(&buffer[..size]).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Polls the condition until it holds
+async fn wait_until<F, Fut>(condition: F)
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    let started = Instant::now();
+    while !condition().await {
+        assert!(
+            started.elapsed() < common::EVENT_TIMEOUT,
+            "Timed out waiting for the condition"
+        );
+        task::sleep(Duration::from_millis(100)).await;
+    }
+}
@@ -0,0 +1,79 @@
+//! Messages held by the mailbox while the recipient is offline
+
+mod common;
+
+use async_std::task::{self, block_on};
+use primitives::Event;
+use std::time::Duration;
+use tata_core::{
+    create_mailbox_swarm, generate_secret, Delivery, MailboxServerConfig, TransportConfig,
+};
+
+#[test]
+fn deposited_message_is_fetched_by_the_recipient() {
+    block_on(async {
+        let mailbox_address = common::memory_config().transport.listen_addresses[0].clone();
+        let (secret, mailbox_peer_id) = generate_secret();
+        let mut swarm = create_mailbox_swarm(
+            secret,
+            MailboxServerConfig {
+                listen_address: mailbox_address.clone(),
+                transport: TransportConfig {
+                    tcp: false,
+                    websocket: false,
+                    memory: true,
+                    listen_addresses: vec![],
+                    ..TransportConfig::default()
+                },
+                ..MailboxServerConfig::default()
+            },
+        )
+        .unwrap();
+        task::spawn(async move {
+            loop {
+                swarm.next().await;
+            }
+        });
+
+        let alice = common::start("alice").await;
+        let mut bob = common::start("bob").await;
+        alice.node.allow_peer(bob.peer_id()).await.unwrap();
+        bob.node.allow_peer(alice.peer_id()).await.unwrap();
+        for node in &[&alice, &bob] {
+            node.node
+                .set_mailbox(mailbox_peer_id.clone(), mailbox_address.parse().unwrap())
+                .await
+                .unwrap();
+        }
+
+        // Alice doesn't know the address of bob, so the message is deposited once bob
+        // is registered with the mailbox
+        let mut deposited = false;
+        for _ in 0..50 {
+            let delivery = alice
+                .node
+                .send(&bob.peer_id(), "while you were out".into())
+                .await
+                .unwrap();
+            if let Ok(Delivery::Deposited) = delivery.await {
+                deposited = true;
+                break;
+            }
+            task::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(deposited);
+
+        bob.node.fetch_mailbox().await.unwrap();
+        let event = bob
+            .wait_for_event(&alice.peer_id(), |event| {
+                matches!(event, Event::ReceivedPlainTextMessage { .. })
+            })
+            .await;
+        match event {
+            Event::ReceivedPlainTextMessage { message } => {
+                assert_eq!(message.text, "while you were out")
+            }
+            _ => unreachable!(),
+        }
+    });
+}
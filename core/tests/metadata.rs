@@ -0,0 +1,67 @@
+//! Handshake metadata exchanged by connected peers
+
+mod common;
+
+use async_std::task::block_on;
+use primitives::{Capability, Event};
+use tata_core::Config;
+
+#[test]
+fn peers_receive_the_full_metadata() {
+    block_on(async {
+        let mut alice = common::start_with(
+            "alice",
+            Config {
+                status: Some("at lunch".to_string()),
+                avatar_hash: Some("f00d".to_string()),
+                capabilities: vec![Capability::Receipts, Capability::Encryption],
+                ..common::memory_config()
+            },
+        )
+        .await;
+        let mut bob = common::start_with(
+            "bob",
+            Config {
+                capabilities: vec![Capability::Encryption],
+                ..common::memory_config()
+            },
+        )
+        .await;
+        common::make_contacts(&alice, &bob).await;
+        alice.node.send(&bob.peer_id(), "hi".into()).await.unwrap();
+
+        let event = bob
+            .wait_for_event(&alice.peer_id(), |event| {
+                matches!(event, Event::ReceivedMetadata { .. })
+            })
+            .await;
+        let alice_metadata = match event {
+            Event::ReceivedMetadata { metadata } => metadata,
+            _ => unreachable!(),
+        };
+        assert_eq!(alice_metadata.name, "alice");
+        assert!(alice_metadata.client_version.starts_with("tata-core/"));
+        assert_eq!(alice_metadata.status.as_deref(), Some("at lunch"));
+        assert_eq!(alice_metadata.avatar_hash.as_deref(), Some("f00d"));
+        assert_eq!(
+            alice_metadata.capabilities,
+            vec![Capability::Receipts, Capability::Encryption]
+        );
+
+        let event = alice
+            .wait_for_event(&bob.peer_id(), |event| {
+                matches!(event, Event::ReceivedMetadata { .. })
+            })
+            .await;
+        let bob_metadata = match event {
+            Event::ReceivedMetadata { metadata } => metadata,
+            _ => unreachable!(),
+        };
+        assert_eq!(bob_metadata.name, "bob");
+        assert_eq!(bob_metadata.status, None);
+        assert_eq!(
+            alice_metadata.common_capabilities(&bob_metadata),
+            vec![Capability::Encryption]
+        );
+    });
+}
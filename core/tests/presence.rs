@@ -0,0 +1,55 @@
+//! Presence announced to the connected contacts
+
+mod common;
+
+use async_std::task::block_on;
+use primitives::{Event, PresenceStatus};
+
+#[test]
+fn contacts_see_presence_changes_and_disconnects() {
+    block_on(async {
+        let alice = common::start("alice").await;
+        let mut bob = common::start("bob").await;
+        common::make_contacts(&alice, &bob).await;
+        let delivery = alice.node.send(&bob.peer_id(), "hi".into()).await.unwrap();
+        delivery.await.unwrap();
+        let alice_peer_id = alice.peer_id();
+        bob.wait_for_event(&alice_peer_id, |event| {
+            matches!(
+                event,
+                Event::PresenceChanged {
+                    status: PresenceStatus::Online,
+                    ..
+                }
+            )
+        })
+        .await;
+
+        alice.node.set_presence(PresenceStatus::Away).await.unwrap();
+        bob.wait_for_event(&alice_peer_id, |event| {
+            matches!(
+                event,
+                Event::PresenceChanged {
+                    status: PresenceStatus::Away,
+                    ..
+                }
+            )
+        })
+        .await;
+
+        // The swarm of alice is stopped once her handles are dropped
+        drop(alice);
+        let event = bob
+            .wait_for_event(&alice_peer_id, |event| {
+                matches!(event, Event::PresenceChanged { .. })
+            })
+            .await;
+        match event {
+            Event::PresenceChanged { status, last_seen } => {
+                assert_eq!(status, PresenceStatus::Offline);
+                assert!(last_seen > 0);
+            }
+            _ => unreachable!(),
+        }
+    });
+}
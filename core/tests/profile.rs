@@ -0,0 +1,58 @@
+//! Profile updates propagated to connected peers
+
+mod common;
+
+use async_std::task::block_on;
+use primitives::Event;
+
+#[test]
+fn connected_peers_receive_profile_updates() {
+    block_on(async {
+        let alice = common::start("alice").await;
+        let mut bob = common::start("bob").await;
+        common::make_contacts(&alice, &bob).await;
+        let delivery = alice.node.send(&bob.peer_id(), "hi".into()).await.unwrap();
+        delivery.await.unwrap();
+        bob.wait_for_event(&alice.peer_id(), |event| match event {
+            Event::ReceivedMetadata { metadata } => metadata.name == "alice",
+            _ => false,
+        })
+        .await;
+
+        alice
+            .node
+            .update_profile(
+                "Alice".to_string(),
+                Some("on holiday".to_string()),
+                Some("beef".to_string()),
+            )
+            .await
+            .unwrap();
+
+        // Metadata of the handshakes may still come before the update
+        let event = bob
+            .wait_for_event(&alice.peer_id(), |event| match event {
+                Event::ReceivedMetadata { metadata } => metadata.name != "alice",
+                _ => false,
+            })
+            .await;
+        match event {
+            Event::ReceivedMetadata { metadata } => {
+                assert_eq!(metadata.name, "Alice");
+                assert_eq!(metadata.status.as_deref(), Some("on holiday"));
+                assert_eq!(metadata.avatar_hash.as_deref(), Some("beef"));
+                assert!(metadata.client_version.starts_with("tata-core/"));
+            }
+            _ => unreachable!(),
+        }
+    });
+}
+
+#[test]
+fn invalid_profile_is_refused() {
+    block_on(async {
+        let alice = common::start("alice").await;
+        let name = "a".repeat(10_000);
+        assert!(alice.node.update_profile(name, None, None).await.is_err());
+    });
+}
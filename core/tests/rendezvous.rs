@@ -0,0 +1,96 @@
+//! Discovery of peers registered at the rendezvous server
+
+mod common;
+
+use async_std::task::{self, block_on};
+use primitives::{ErrorMessage, Event};
+use tata_core::{
+    create_rendezvous_swarm, generate_secret, Config, DiscoveryConfig, PeerId, RendezvousConfig,
+    RendezvousServerConfig, TransportConfig,
+};
+
+/// Starts a rendezvous server on the in-memory transport
+fn start_server(config: RendezvousServerConfig) -> (PeerId, String) {
+    let address = common::memory_config().transport.listen_addresses[0].clone();
+    let (secret, peer_id) = generate_secret();
+    let mut swarm = create_rendezvous_swarm(
+        secret,
+        RendezvousServerConfig {
+            listen_address: address.clone(),
+            transport: TransportConfig {
+                tcp: false,
+                websocket: false,
+                memory: true,
+                listen_addresses: vec![],
+                ..TransportConfig::default()
+            },
+            ..config
+        },
+    )
+    .unwrap();
+    task::spawn(async move {
+        loop {
+            swarm.next().await;
+        }
+    });
+    (peer_id, address)
+}
+
+/// Config of a node registered in the namespace and discovering its peers every second
+fn registered_config(server: &PeerId, address: &str, namespace: &str) -> Config {
+    Config {
+        discovery: DiscoveryConfig {
+            visible: false,
+            discover: true,
+        },
+        rendezvous: Some(RendezvousConfig {
+            peer_id: server.to_base58(),
+            address: address.to_string(),
+            namespaces: vec![namespace.to_string()],
+            ttl_secs: 60,
+            discover_interval_secs: 1,
+        }),
+        ..common::memory_config()
+    }
+}
+
+#[test]
+fn peers_registered_in_a_namespace_discover_each_other() {
+    block_on(async {
+        let (server, address) = start_server(RendezvousServerConfig::default());
+        let alice = common::start_with("alice", registered_config(&server, &address, "team")).await;
+        let mut bob = common::start_with("bob", registered_config(&server, &address, "team")).await;
+
+        bob.wait_for_event(&alice.peer_id(), |event| {
+            matches!(event, Event::PeerDiscovered)
+        })
+        .await;
+    });
+}
+
+#[test]
+fn registrations_of_a_peer_are_capped() {
+    block_on(async {
+        let (server, address) = start_server(RendezvousServerConfig {
+            max_namespaces_per_peer: 1,
+            ..RendezvousServerConfig::default()
+        });
+        let mut alice =
+            common::start_with("alice", registered_config(&server, &address, "team")).await;
+        alice
+            .node
+            .register_namespace("another team".to_string())
+            .await
+            .unwrap();
+
+        let event = alice
+            .wait_for_event(&server, |event| matches!(event, Event::Error { .. }))
+            .await;
+        assert!(matches!(
+            event,
+            Event::Error {
+                error: ErrorMessage::Rendezvous { .. }
+            }
+        ));
+    });
+}
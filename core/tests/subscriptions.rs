@@ -0,0 +1,53 @@
+//! Events delivered to the subscribers matching their filters
+
+mod common;
+
+use async_std::future::timeout;
+use async_std::task::block_on;
+use futures::StreamExt;
+use primitives::Event;
+use tata_core::EventFilter;
+
+#[test]
+fn subscribers_receive_matching_events() {
+    block_on(async {
+        let alice = common::start("alice").await;
+        let bob = common::start("bob").await;
+        let carol = common::start("carol").await;
+        let mut messages = bob
+            .node
+            .subscribe(EventFilter {
+                kinds: vec!["ReceivedPlainTextMessage".to_string()],
+                peer_ids: vec![],
+            })
+            .await
+            .unwrap();
+        let mut from_carol = bob
+            .node
+            .subscribe(EventFilter {
+                kinds: vec![],
+                peer_ids: vec![carol.peer_id().to_base58()],
+            })
+            .await
+            .unwrap();
+        common::make_contacts(&alice, &bob).await;
+        let delivery = alice.node.send(&bob.peer_id(), "hi".into()).await.unwrap();
+        delivery.await.unwrap();
+
+        // Connection events of alice come before the message, but only the message matches
+        let event = timeout(common::EVENT_TIMEOUT, messages.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.peer_id, alice.peer_id().to_base58());
+        match event.event {
+            Event::ReceivedPlainTextMessage { message } => assert_eq!(message.text, "hi"),
+            event => panic!("Unexpected event {:?}", event),
+        }
+        assert!(
+            timeout(std::time::Duration::from_millis(500), from_carol.next())
+                .await
+                .is_err()
+        );
+    });
+}
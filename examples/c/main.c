@@ -2,6 +2,7 @@
 #include <unistd.h>
 #include <stdlib.h>
 #include <pthread.h>
+#include <string.h>
 #include "tata_core.h"
 
 void callback(ByteArray bytes)
@@ -16,6 +17,17 @@ void callback(ByteArray bytes)
 
 int main(int argc, char const *argv[])
 {
+    if (tata_abi_version() != TATA_ABI_VERSION)
+    {
+        fprintf(stderr, "ABI version of the library %u doesn't match the header %u\n",
+                tata_abi_version(), TATA_ABI_VERSION);
+        return 1;
+    }
+    if (argc > 1 && strcmp(argv[1], "--abi-check") == 0)
+    {
+        printf("ABI version %u\n", TATA_ABI_VERSION);
+        return 0;
+    }
     KeyPair key_pair = generate_keypair();
     unsigned char *p1 = malloc(4 * sizeof(char));
     p1[0] = 'T';
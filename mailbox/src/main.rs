@@ -0,0 +1,69 @@
+//! Headless mailbox node. Holds messages for registered peers while they are offline.
+
+use libp2p::identity::{
+    secp256k1::{Keypair, SecretKey},
+    PublicKey,
+};
+use libp2p::PeerId;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use tata_core::{create_mailbox_swarm, MailboxServerConfig};
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "mailbox")]
+struct Opt {
+    /// Multiaddress to listen on
+    #[structopt(short, long, default_value = "/ip4/0.0.0.0/tcp/4001")]
+    listen: String,
+    /// File with the secret key of the node. A new key is generated if the file doesn't exist.
+    #[structopt(short, long, default_value = "mailbox.key", parse(from_os_str))]
+    key_file: PathBuf,
+    /// File the envelopes are stored in, one json record per line
+    #[structopt(short, long, default_value = "mailbox.jsonl", parse(from_os_str))]
+    store: PathBuf,
+    /// Base58 peer id allowed to register with the mailbox, can be repeated.
+    /// Any peer can register if omitted.
+    #[structopt(short, long)]
+    allow: Vec<String>,
+    /// Pre-shared key of the private network, 64 hex characters. Only the nodes
+    /// with the same key can connect.
+    #[structopt(long)]
+    psk: Option<String>,
+}
+
+fn load_or_generate_secret(path: &Path) -> Result<SecretKey, Box<dyn std::error::Error>> {
+    if path.exists() {
+        return Ok(SecretKey::from_bytes(fs::read(path)?)?);
+    }
+    let secret: SecretKey = Keypair::generate().into();
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    // Only the owner can read the key
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(&secret.to_bytes())?;
+    Ok(secret)
+}
+
+#[async_std::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let opt = Opt::from_args();
+    let secret = load_or_generate_secret(&opt.key_file)?;
+    let keypair: Keypair = secret.clone().into();
+    let peer_id = PeerId::from_public_key(PublicKey::Secp256k1(keypair.public().clone()));
+    let config = MailboxServerConfig {
+        listen_address: opt.listen.clone(),
+        store_path: Some(opt.store),
+        allowed_peers: opt.allow,
+        psk: opt.psk,
+        ..Default::default()
+    };
+    let mut swarm = create_mailbox_swarm(secret, config)?;
+    log::info!("Mailbox {} is listening on {}", peer_id, opt.listen);
+    loop {
+        swarm.next().await;
+    }
+}
@@ -0,0 +1,318 @@
+// Generated by schema-gen, do not edit.
+
+export const WIRE_VERSION = 1;
+
+/**
+ * Optional feature supported by a peer
+ */
+export type Capability = "Receipts" | "FileTransfer" | "Groups" | "Encryption";
+
+/**
+ * Error at the network level
+ */
+export type ErrorMessage =
+  | ErrorMessageFailedToDial
+  | ErrorMessageNetwork
+  | ErrorMessageMessageValidation
+  | ErrorMessageLimitExceeded
+  | ErrorMessageMalformedMessage
+  | ErrorMessageMailbox
+  | ErrorMessageRendezvous
+  | ErrorMessageOther;
+
+/**
+ * Dial failed for some reason
+ */
+export interface ErrorMessageFailedToDial {
+  type: "FailedToDial";
+  cause: string;
+}
+
+/**
+ * Network error
+ */
+export interface ErrorMessageNetwork {
+  type: "Network";
+  cause: string;
+}
+
+/**
+ * Invalid message was passed
+ */
+export interface ErrorMessageMessageValidation {
+  type: "MessageValidation";
+  cause: string;
+  timestamp: number;
+}
+
+/**
+ * Peer exceeded the limits (frame size, name or message length, handshake timeout) and was disconnected
+ */
+export interface ErrorMessageLimitExceeded {
+  type: "LimitExceeded";
+  cause: string;
+}
+
+/**
+ * Peer sent a message that couldn't be parsed
+ */
+export interface ErrorMessageMalformedMessage {
+  type: "MalformedMessage";
+  cause: string;
+}
+
+/**
+ * Mailbox refused the request or couldn't be reached
+ */
+export interface ErrorMessageMailbox {
+  type: "Mailbox";
+  cause: string;
+}
+
+/**
+ * Rendezvous server refused the request
+ */
+export interface ErrorMessageRendezvous {
+  type: "Rendezvous";
+  cause: string;
+}
+
+/**
+ * Other
+ */
+export interface ErrorMessageOther {
+  type: "Other";
+  cause: string;
+}
+
+/**
+ * Event generated by network layer
+ */
+export type Event =
+  | EventReceivedPlainTextMessage
+  | EventReceivedMetadata
+  | EventSentPlainTextMessage
+  | EventMessageDeposited
+  | EventRelayedMessageDelivered
+  | EventContactRequestReceived
+  | EventContactRequestAccepted
+  | EventContactRequestDeclined
+  | EventPeerDiscovered
+  | EventPeerGone
+  | EventPeerBanned
+  | EventPeerUnbanned
+  | EventPresenceChanged
+  | EventPeerInfo
+  | EventPeerRtt
+  | EventListenAddressAdded
+  | EventListenAddressExpired
+  | EventEventsDropped
+  | EventError;
+
+/**
+ * Received plain text message sent by peer
+ */
+export interface EventReceivedPlainTextMessage {
+  type: "ReceivedPlainTextMessage";
+  message: PlainTextMessage;
+}
+
+/**
+ * Received metadata from peer
+ */
+export interface EventReceivedMetadata {
+  type: "ReceivedMetadata";
+  metadata: Metadata;
+}
+
+/**
+ * Sent plain text message with timestamp
+ */
+export interface EventSentPlainTextMessage {
+  type: "SentPlainTextMessage";
+  timestamp: number;
+}
+
+/**
+ * The peer is offline, the message with timestamp is stored in the mailbox and will be delivered once the peer fetches it
+ */
+export interface EventMessageDeposited {
+  type: "MessageDeposited";
+  timestamp: number;
+}
+
+/**
+ * The message with timestamp was relayed through other peers and the peer confirmed the delivery
+ */
+export interface EventRelayedMessageDelivered {
+  type: "RelayedMessageDelivered";
+  timestamp: number;
+}
+
+/**
+ * Peer asks to become a contact. Messages are accepted only from contacts.
+ */
+export interface EventContactRequestReceived {
+  type: "ContactRequestReceived";
+  metadata: Metadata;
+  note: string;
+}
+
+/**
+ * Contact request was accepted, the peers are contacts now
+ */
+export interface EventContactRequestAccepted {
+  type: "ContactRequestAccepted";
+}
+
+/**
+ * Contact request was declined
+ */
+export interface EventContactRequestDeclined {
+  type: "ContactRequestDeclined";
+}
+
+/**
+ * A new peer discovered
+ */
+export interface EventPeerDiscovered {
+  type: "PeerDiscovered";
+}
+
+/**
+ * A peer is gone
+ */
+export interface EventPeerGone {
+  type: "PeerGone";
+}
+
+/**
+ * Peer is muted and disconnected for `duration_secs` because of misbehaviour, e.g. spam. The `reason` is human readable.
+ */
+export interface EventPeerBanned {
+  type: "PeerBanned";
+  duration_secs: number;
+  reason: string;
+}
+
+/**
+ * Ban of the peer is expired
+ */
+export interface EventPeerUnbanned {
+  type: "PeerUnbanned";
+}
+
+/**
+ * Presence of a contact is changed. `last_seen` is a unix timestamp in milliseconds of the last presence update received from the contact.
+ */
+export interface EventPresenceChanged {
+  type: "PresenceChanged";
+  last_seen: number;
+  status: PresenceStatus;
+}
+
+/**
+ * Identification of the connected peer: its client, supported protocols and the addresses it listens on. `observed_address` is our own address as seen by the peer.
+ */
+export interface EventPeerInfo {
+  type: "PeerInfo";
+  agent_version: string;
+  listen_addresses: string[];
+  observed_address: string;
+  protocol_version: string;
+  protocols: string[];
+}
+
+/**
+ * Round-trip time of a ping to the connected peer in milliseconds
+ */
+export interface EventPeerRtt {
+  type: "PeerRtt";
+  rtt_ms: number;
+}
+
+/**
+ * The node started listening on the address. `peer_id` of the event is the local peer id.
+ */
+export interface EventListenAddressAdded {
+  type: "ListenAddressAdded";
+  address: string;
+}
+
+/**
+ * The node stopped listening on the address. `peer_id` of the event is the local peer id.
+ */
+export interface EventListenAddressExpired {
+  type: "ListenAddressExpired";
+  address: string;
+}
+
+/**
+ * The host didn't keep up and `count` events were dropped at this point. `peer_id` of the event is the local peer id.
+ */
+export interface EventEventsDropped {
+  type: "EventsDropped";
+  count: number;
+}
+
+/**
+ * Error
+ */
+export interface EventError {
+  type: "Error";
+  error: ErrorMessage;
+}
+
+/**
+ * Metadata for p2p exchange
+ */
+export interface Metadata {
+  /**
+   * Hash of the avatar image
+   */
+  avatar_hash?: string | null;
+  /**
+   * Optional features supported by the peer
+   */
+  capabilities?: Capability[];
+  /**
+   * Client name and version, e.g. `tata-core/0.1.0`
+   */
+  client_version?: string;
+  name: string;
+  /**
+   * Optional status text
+   */
+  status?: string | null;
+}
+
+/**
+ * Event generated in a process of communication with a certain peer
+ */
+export interface PeerEvent {
+  event: Event;
+  peer_id: string;
+}
+
+/**
+ * Plain text message sent by peer
+ */
+export interface PlainTextMessage {
+  text: string;
+  timestamp: number;
+  to: string;
+}
+
+/**
+ * Presence status of a peer
+ */
+export type PresenceStatus = "Online" | "Away" | "Busy" | "Offline";
+
+/**
+ * Event delivered to the host with its sequence number. Sequence numbers start from 1 and increase monotonically, the events can be replayed from a given sequence number.
+ */
+export interface SequencedEvent {
+  event: Event;
+  peer_id: string;
+  seq: number;
+}
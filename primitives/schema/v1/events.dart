@@ -0,0 +1,487 @@
+// Generated by schema-gen, do not edit.
+
+const int wireVersion = 1;
+
+/// Optional feature supported by a peer
+enum Capability {
+  receipts,
+  fileTransfer,
+  groups,
+  encryption,
+}
+
+Capability capabilityFromJson(dynamic json) {
+  switch (json) {
+    case 'Receipts':
+      return Capability.receipts;
+    case 'FileTransfer':
+      return Capability.fileTransfer;
+    case 'Groups':
+      return Capability.groups;
+    case 'Encryption':
+      return Capability.encryption;
+    default:
+      throw ArgumentError('Unknown Capability: $json');
+  }
+}
+
+/// Error at the network level
+abstract class ErrorMessage {
+  factory ErrorMessage.fromJson(Map<String, dynamic> json) {
+    switch (json['type']) {
+      case 'FailedToDial':
+        return ErrorMessageFailedToDial.fromJson(json);
+      case 'Network':
+        return ErrorMessageNetwork.fromJson(json);
+      case 'MessageValidation':
+        return ErrorMessageMessageValidation.fromJson(json);
+      case 'LimitExceeded':
+        return ErrorMessageLimitExceeded.fromJson(json);
+      case 'MalformedMessage':
+        return ErrorMessageMalformedMessage.fromJson(json);
+      case 'Mailbox':
+        return ErrorMessageMailbox.fromJson(json);
+      case 'Rendezvous':
+        return ErrorMessageRendezvous.fromJson(json);
+      case 'Other':
+        return ErrorMessageOther.fromJson(json);
+      default:
+        throw ArgumentError('Unknown ErrorMessage: ${json['type']}');
+    }
+  }
+}
+
+/// Dial failed for some reason
+class ErrorMessageFailedToDial implements ErrorMessage {
+  final String cause;
+
+  ErrorMessageFailedToDial(this.cause);
+
+  ErrorMessageFailedToDial.fromJson(Map<String, dynamic> json)
+      : cause = json['cause']
+      ;
+}
+
+/// Network error
+class ErrorMessageNetwork implements ErrorMessage {
+  final String cause;
+
+  ErrorMessageNetwork(this.cause);
+
+  ErrorMessageNetwork.fromJson(Map<String, dynamic> json)
+      : cause = json['cause']
+      ;
+}
+
+/// Invalid message was passed
+class ErrorMessageMessageValidation implements ErrorMessage {
+  final String cause;
+  final int timestamp;
+
+  ErrorMessageMessageValidation(this.cause, this.timestamp);
+
+  ErrorMessageMessageValidation.fromJson(Map<String, dynamic> json)
+      : cause = json['cause']
+      , timestamp = json['timestamp']
+      ;
+}
+
+/// Peer exceeded the limits (frame size, name or message length, handshake timeout) and was disconnected
+class ErrorMessageLimitExceeded implements ErrorMessage {
+  final String cause;
+
+  ErrorMessageLimitExceeded(this.cause);
+
+  ErrorMessageLimitExceeded.fromJson(Map<String, dynamic> json)
+      : cause = json['cause']
+      ;
+}
+
+/// Peer sent a message that couldn't be parsed
+class ErrorMessageMalformedMessage implements ErrorMessage {
+  final String cause;
+
+  ErrorMessageMalformedMessage(this.cause);
+
+  ErrorMessageMalformedMessage.fromJson(Map<String, dynamic> json)
+      : cause = json['cause']
+      ;
+}
+
+/// Mailbox refused the request or couldn't be reached
+class ErrorMessageMailbox implements ErrorMessage {
+  final String cause;
+
+  ErrorMessageMailbox(this.cause);
+
+  ErrorMessageMailbox.fromJson(Map<String, dynamic> json)
+      : cause = json['cause']
+      ;
+}
+
+/// Rendezvous server refused the request
+class ErrorMessageRendezvous implements ErrorMessage {
+  final String cause;
+
+  ErrorMessageRendezvous(this.cause);
+
+  ErrorMessageRendezvous.fromJson(Map<String, dynamic> json)
+      : cause = json['cause']
+      ;
+}
+
+/// Other
+class ErrorMessageOther implements ErrorMessage {
+  final String cause;
+
+  ErrorMessageOther(this.cause);
+
+  ErrorMessageOther.fromJson(Map<String, dynamic> json)
+      : cause = json['cause']
+      ;
+}
+
+/// Event generated by network layer
+abstract class Event {
+  factory Event.fromJson(Map<String, dynamic> json) {
+    switch (json['type']) {
+      case 'ReceivedPlainTextMessage':
+        return EventReceivedPlainTextMessage.fromJson(json);
+      case 'ReceivedMetadata':
+        return EventReceivedMetadata.fromJson(json);
+      case 'SentPlainTextMessage':
+        return EventSentPlainTextMessage.fromJson(json);
+      case 'MessageDeposited':
+        return EventMessageDeposited.fromJson(json);
+      case 'RelayedMessageDelivered':
+        return EventRelayedMessageDelivered.fromJson(json);
+      case 'ContactRequestReceived':
+        return EventContactRequestReceived.fromJson(json);
+      case 'ContactRequestAccepted':
+        return EventContactRequestAccepted.fromJson(json);
+      case 'ContactRequestDeclined':
+        return EventContactRequestDeclined.fromJson(json);
+      case 'PeerDiscovered':
+        return EventPeerDiscovered.fromJson(json);
+      case 'PeerGone':
+        return EventPeerGone.fromJson(json);
+      case 'PeerBanned':
+        return EventPeerBanned.fromJson(json);
+      case 'PeerUnbanned':
+        return EventPeerUnbanned.fromJson(json);
+      case 'PresenceChanged':
+        return EventPresenceChanged.fromJson(json);
+      case 'PeerInfo':
+        return EventPeerInfo.fromJson(json);
+      case 'PeerRtt':
+        return EventPeerRtt.fromJson(json);
+      case 'ListenAddressAdded':
+        return EventListenAddressAdded.fromJson(json);
+      case 'ListenAddressExpired':
+        return EventListenAddressExpired.fromJson(json);
+      case 'EventsDropped':
+        return EventEventsDropped.fromJson(json);
+      case 'Error':
+        return EventError.fromJson(json);
+      default:
+        throw ArgumentError('Unknown Event: ${json['type']}');
+    }
+  }
+}
+
+/// Received plain text message sent by peer
+class EventReceivedPlainTextMessage implements Event {
+  final PlainTextMessage message;
+
+  EventReceivedPlainTextMessage(this.message);
+
+  EventReceivedPlainTextMessage.fromJson(Map<String, dynamic> json)
+      : message = PlainTextMessage.fromJson(json['message'])
+      ;
+}
+
+/// Received metadata from peer
+class EventReceivedMetadata implements Event {
+  final Metadata metadata;
+
+  EventReceivedMetadata(this.metadata);
+
+  EventReceivedMetadata.fromJson(Map<String, dynamic> json)
+      : metadata = Metadata.fromJson(json['metadata'])
+      ;
+}
+
+/// Sent plain text message with timestamp
+class EventSentPlainTextMessage implements Event {
+  final int timestamp;
+
+  EventSentPlainTextMessage(this.timestamp);
+
+  EventSentPlainTextMessage.fromJson(Map<String, dynamic> json)
+      : timestamp = json['timestamp']
+      ;
+}
+
+/// The peer is offline, the message with timestamp is stored in the mailbox and will be delivered once the peer fetches it
+class EventMessageDeposited implements Event {
+  final int timestamp;
+
+  EventMessageDeposited(this.timestamp);
+
+  EventMessageDeposited.fromJson(Map<String, dynamic> json)
+      : timestamp = json['timestamp']
+      ;
+}
+
+/// The message with timestamp was relayed through other peers and the peer confirmed the delivery
+class EventRelayedMessageDelivered implements Event {
+  final int timestamp;
+
+  EventRelayedMessageDelivered(this.timestamp);
+
+  EventRelayedMessageDelivered.fromJson(Map<String, dynamic> json)
+      : timestamp = json['timestamp']
+      ;
+}
+
+/// Peer asks to become a contact. Messages are accepted only from contacts.
+class EventContactRequestReceived implements Event {
+  final Metadata metadata;
+  final String note;
+
+  EventContactRequestReceived(this.metadata, this.note);
+
+  EventContactRequestReceived.fromJson(Map<String, dynamic> json)
+      : metadata = Metadata.fromJson(json['metadata'])
+      , note = json['note']
+      ;
+}
+
+/// Contact request was accepted, the peers are contacts now
+class EventContactRequestAccepted implements Event {
+  EventContactRequestAccepted();
+
+  EventContactRequestAccepted.fromJson(Map<String, dynamic> json)
+      ;
+}
+
+/// Contact request was declined
+class EventContactRequestDeclined implements Event {
+  EventContactRequestDeclined();
+
+  EventContactRequestDeclined.fromJson(Map<String, dynamic> json)
+      ;
+}
+
+/// A new peer discovered
+class EventPeerDiscovered implements Event {
+  EventPeerDiscovered();
+
+  EventPeerDiscovered.fromJson(Map<String, dynamic> json)
+      ;
+}
+
+/// A peer is gone
+class EventPeerGone implements Event {
+  EventPeerGone();
+
+  EventPeerGone.fromJson(Map<String, dynamic> json)
+      ;
+}
+
+/// Peer is muted and disconnected for `duration_secs` because of misbehaviour, e.g. spam. The `reason` is human readable.
+class EventPeerBanned implements Event {
+  final int durationSecs;
+  final String reason;
+
+  EventPeerBanned(this.durationSecs, this.reason);
+
+  EventPeerBanned.fromJson(Map<String, dynamic> json)
+      : durationSecs = json['duration_secs']
+      , reason = json['reason']
+      ;
+}
+
+/// Ban of the peer is expired
+class EventPeerUnbanned implements Event {
+  EventPeerUnbanned();
+
+  EventPeerUnbanned.fromJson(Map<String, dynamic> json)
+      ;
+}
+
+/// Presence of a contact is changed. `last_seen` is a unix timestamp in milliseconds of the last presence update received from the contact.
+class EventPresenceChanged implements Event {
+  final int lastSeen;
+  final PresenceStatus status;
+
+  EventPresenceChanged(this.lastSeen, this.status);
+
+  EventPresenceChanged.fromJson(Map<String, dynamic> json)
+      : lastSeen = json['last_seen']
+      , status = PresenceStatus.fromJson(json['status'])
+      ;
+}
+
+/// Identification of the connected peer: its client, supported protocols and the addresses it listens on. `observed_address` is our own address as seen by the peer.
+class EventPeerInfo implements Event {
+  final String agentVersion;
+  final List<String> listenAddresses;
+  final String observedAddress;
+  final String protocolVersion;
+  final List<String> protocols;
+
+  EventPeerInfo(this.agentVersion, this.listenAddresses, this.observedAddress, this.protocolVersion, this.protocols);
+
+  EventPeerInfo.fromJson(Map<String, dynamic> json)
+      : agentVersion = json['agent_version']
+      , listenAddresses = (json['listen_addresses'] as List).map((e) => e).toList()
+      , observedAddress = json['observed_address']
+      , protocolVersion = json['protocol_version']
+      , protocols = (json['protocols'] as List).map((e) => e).toList()
+      ;
+}
+
+/// Round-trip time of a ping to the connected peer in milliseconds
+class EventPeerRtt implements Event {
+  final int rttMs;
+
+  EventPeerRtt(this.rttMs);
+
+  EventPeerRtt.fromJson(Map<String, dynamic> json)
+      : rttMs = json['rtt_ms']
+      ;
+}
+
+/// The node started listening on the address. `peer_id` of the event is the local peer id.
+class EventListenAddressAdded implements Event {
+  final String address;
+
+  EventListenAddressAdded(this.address);
+
+  EventListenAddressAdded.fromJson(Map<String, dynamic> json)
+      : address = json['address']
+      ;
+}
+
+/// The node stopped listening on the address. `peer_id` of the event is the local peer id.
+class EventListenAddressExpired implements Event {
+  final String address;
+
+  EventListenAddressExpired(this.address);
+
+  EventListenAddressExpired.fromJson(Map<String, dynamic> json)
+      : address = json['address']
+      ;
+}
+
+/// The host didn't keep up and `count` events were dropped at this point. `peer_id` of the event is the local peer id.
+class EventEventsDropped implements Event {
+  final int count;
+
+  EventEventsDropped(this.count);
+
+  EventEventsDropped.fromJson(Map<String, dynamic> json)
+      : count = json['count']
+      ;
+}
+
+/// Error
+class EventError implements Event {
+  final ErrorMessage error;
+
+  EventError(this.error);
+
+  EventError.fromJson(Map<String, dynamic> json)
+      : error = ErrorMessage.fromJson(json['error'])
+      ;
+}
+
+/// Metadata for p2p exchange
+class Metadata {
+  /// Hash of the avatar image
+  final String avatarHash;
+  /// Optional features supported by the peer
+  final List<Capability> capabilities;
+  /// Client name and version, e.g. `tata-core/0.1.0`
+  final String clientVersion;
+  final String name;
+  /// Optional status text
+  final String status;
+
+  Metadata(this.avatarHash, this.capabilities, this.clientVersion, this.name, this.status);
+
+  Metadata.fromJson(Map<String, dynamic> json)
+      : avatarHash = json['avatar_hash'] == null ? null : json['avatar_hash'] == null ? null : json['avatar_hash']
+      , capabilities = json['capabilities'] == null ? <Capability>[] : (json['capabilities'] as List).map((e) => Capability.fromJson(e)).toList()
+      , clientVersion = json['client_version'] == null ? '' : json['client_version']
+      , name = json['name']
+      , status = json['status'] == null ? null : json['status'] == null ? null : json['status']
+      ;
+}
+
+/// Event generated in a process of communication with a certain peer
+class PeerEvent {
+  final Event event;
+  final String peerId;
+
+  PeerEvent(this.event, this.peerId);
+
+  PeerEvent.fromJson(Map<String, dynamic> json)
+      : event = Event.fromJson(json['event'])
+      , peerId = json['peer_id']
+      ;
+}
+
+/// Plain text message sent by peer
+class PlainTextMessage {
+  final String text;
+  final int timestamp;
+  final String to;
+
+  PlainTextMessage(this.text, this.timestamp, this.to);
+
+  PlainTextMessage.fromJson(Map<String, dynamic> json)
+      : text = json['text']
+      , timestamp = json['timestamp']
+      , to = json['to']
+      ;
+}
+
+/// Presence status of a peer
+enum PresenceStatus {
+  online,
+  away,
+  busy,
+  offline,
+}
+
+PresenceStatus presenceStatusFromJson(dynamic json) {
+  switch (json) {
+    case 'Online':
+      return PresenceStatus.online;
+    case 'Away':
+      return PresenceStatus.away;
+    case 'Busy':
+      return PresenceStatus.busy;
+    case 'Offline':
+      return PresenceStatus.offline;
+    default:
+      throw ArgumentError('Unknown PresenceStatus: $json');
+  }
+}
+
+/// Event delivered to the host with its sequence number. Sequence numbers start from 1 and increase monotonically, the events can be replayed from a given sequence number.
+class SequencedEvent {
+  final Event event;
+  final String peerId;
+  final int seq;
+
+  SequencedEvent(this.event, this.peerId, this.seq);
+
+  SequencedEvent.fromJson(Map<String, dynamic> json)
+      : event = Event.fromJson(json['event'])
+      , peerId = json['peer_id']
+      , seq = json['seq']
+      ;
+}
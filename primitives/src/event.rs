@@ -1,9 +1,66 @@
 use crate::ffi::ByteArray;
+use crate::{Metadata, PresenceStatus};
 
-use serde::{Deserialize, Serialize};
+use derive_more::{Display, From};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::convert::{TryFrom, TryInto};
 
+/// Version of the wire shape of the events. Bump it on any change of the json shape
+/// of the events, see `schema-gen`.
+pub const WIRE_VERSION: u32 = 1;
+
+/// Encoding of the events delivered to the host
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventEncoding {
+    #[default]
+    Json,
+    Cbor,
+    /// MessagePack with the struct fields encoded as maps, i.e. same shape as json.
+    /// The values go through `serde_json::Value`, since `rmp_serde` encodes unit enum
+    /// variants as maps and can't encode flattened fields.
+    MessagePack,
+}
+
+impl EventEncoding {
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, EncodingError> {
+        let bytes = match self {
+            EventEncoding::Json => serde_json::to_vec(value)?,
+            EventEncoding::Cbor => serde_cbor::to_vec(value)?,
+            EventEncoding::MessagePack => rmp_serde::to_vec_named(&serde_json::to_value(value)?)?,
+        };
+        Ok(bytes)
+    }
+
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, EncodingError> {
+        let value = match self {
+            EventEncoding::Json => serde_json::from_slice(bytes)?,
+            EventEncoding::Cbor => serde_cbor::from_slice(bytes)?,
+            EventEncoding::MessagePack => {
+                let value: serde_json::Value = rmp_serde::from_read_ref(bytes)?;
+                serde_json::from_value(value)?
+            }
+        };
+        Ok(value)
+    }
+}
+
+/// Error of encoding or decoding an event
+#[derive(Debug, Display, From)]
+pub enum EncodingError {
+    #[display(fmt = "Json error: {}", _0)]
+    Json(serde_json::Error),
+    #[display(fmt = "Cbor error: {}", _0)]
+    Cbor(serde_cbor::Error),
+    #[display(fmt = "MessagePack encode error: {}", _0)]
+    MessagePackEncode(rmp_serde::encode::Error),
+    #[display(fmt = "MessagePack decode error: {}", _0)]
+    MessagePackDecode(rmp_serde::decode::Error),
+}
+
+impl std::error::Error for EncodingError {}
+
 /// Plain text message sent by peer
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlainTextMessage {
     pub to: String,
@@ -12,6 +69,7 @@ pub struct PlainTextMessage {
 }
 
 /// Error at the network level
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum ErrorMessage {
@@ -21,35 +79,124 @@ pub enum ErrorMessage {
     Network { cause: String },
     /// Invalid message was passed
     MessageValidation { timestamp: u64, cause: String },
+    /// Peer exceeded the limits (frame size, name or message length, handshake timeout)
+    /// and was disconnected
+    LimitExceeded { cause: String },
+    /// Peer sent a message that couldn't be parsed
+    MalformedMessage { cause: String },
+    /// Mailbox refused the request or couldn't be reached
+    Mailbox { cause: String },
+    /// Rendezvous server refused the request
+    Rendezvous { cause: String },
     /// Other
     Other { cause: String },
 }
 
 /// Event generated in a process of communication with a certain peer
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PeerEvent {
     pub peer_id: String,
     pub event: Event,
 }
 
+/// Event delivered to the host with its sequence number. Sequence numbers start from 1
+/// and increase monotonically, the events can be replayed from a given sequence number.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequencedEvent {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub event: PeerEvent,
+}
+
 /// Event generated by network layer
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Event {
     /// Received plain text message sent by peer
     ReceivedPlainTextMessage { message: PlainTextMessage },
     /// Received metadata from peer
-    ReceivedMetadata { name: String },
+    ReceivedMetadata { metadata: Metadata },
     /// Sent plain text message with timestamp
     SentPlainTextMessage { timestamp: u64 },
+    /// The peer is offline, the message with timestamp is stored in the mailbox
+    /// and will be delivered once the peer fetches it
+    MessageDeposited { timestamp: u64 },
+    /// The message with timestamp was relayed through other peers and the peer
+    /// confirmed the delivery
+    RelayedMessageDelivered { timestamp: u64 },
+    /// Peer asks to become a contact. Messages are accepted only from contacts.
+    ContactRequestReceived { metadata: Metadata, note: String },
+    /// Contact request was accepted, the peers are contacts now
+    ContactRequestAccepted,
+    /// Contact request was declined
+    ContactRequestDeclined,
     /// A new peer discovered
     PeerDiscovered,
     /// A peer is gone
     PeerGone,
+    /// Peer is muted and disconnected for `duration_secs` because of misbehaviour,
+    /// e.g. spam. The `reason` is human readable.
+    PeerBanned { reason: String, duration_secs: u64 },
+    /// Ban of the peer is expired
+    PeerUnbanned,
+    /// Presence of a contact is changed. `last_seen` is a unix timestamp in milliseconds
+    /// of the last presence update received from the contact.
+    PresenceChanged {
+        status: PresenceStatus,
+        last_seen: u64,
+    },
+    /// Identification of the connected peer: its client, supported protocols and the
+    /// addresses it listens on. `observed_address` is our own address as seen by the peer.
+    PeerInfo {
+        agent_version: String,
+        protocol_version: String,
+        protocols: Vec<String>,
+        listen_addresses: Vec<String>,
+        observed_address: String,
+    },
+    /// Round-trip time of a ping to the connected peer in milliseconds
+    PeerRtt { rtt_ms: u64 },
+    /// The node started listening on the address. `peer_id` of the event is the local peer id.
+    ListenAddressAdded { address: String },
+    /// The node stopped listening on the address. `peer_id` of the event is the local peer id.
+    ListenAddressExpired { address: String },
+    /// The host didn't keep up and `count` events were dropped at this point.
+    /// `peer_id` of the event is the local peer id.
+    EventsDropped { count: u64 },
     /// Error
     Error { error: ErrorMessage },
 }
 
+impl Event {
+    /// Name of the event kind, same as the `type` tag in json
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::ReceivedPlainTextMessage { .. } => "ReceivedPlainTextMessage",
+            Event::ReceivedMetadata { .. } => "ReceivedMetadata",
+            Event::SentPlainTextMessage { .. } => "SentPlainTextMessage",
+            Event::MessageDeposited { .. } => "MessageDeposited",
+            Event::RelayedMessageDelivered { .. } => "RelayedMessageDelivered",
+            Event::ContactRequestReceived { .. } => "ContactRequestReceived",
+            Event::ContactRequestAccepted => "ContactRequestAccepted",
+            Event::ContactRequestDeclined => "ContactRequestDeclined",
+            Event::PeerDiscovered => "PeerDiscovered",
+            Event::PeerGone => "PeerGone",
+            Event::PeerBanned { .. } => "PeerBanned",
+            Event::PeerUnbanned => "PeerUnbanned",
+            Event::PresenceChanged { .. } => "PresenceChanged",
+            Event::PeerInfo { .. } => "PeerInfo",
+            Event::PeerRtt { .. } => "PeerRtt",
+            Event::ListenAddressAdded { .. } => "ListenAddressAdded",
+            Event::ListenAddressExpired { .. } => "ListenAddressExpired",
+            Event::EventsDropped { .. } => "EventsDropped",
+            Event::Error { .. } => "Error",
+        }
+    }
+}
+
 impl TryInto<ByteArray> for PeerEvent {
     type Error = serde_json::error::Error;
     fn try_into(self) -> Result<ByteArray, serde_json::error::Error> {
@@ -66,3 +213,251 @@ impl TryFrom<ByteArray> for PeerEvent {
         Ok(event)
     }
 }
+
+impl TryInto<ByteArray> for SequencedEvent {
+    type Error = serde_json::error::Error;
+    fn try_into(self) -> Result<ByteArray, serde_json::error::Error> {
+        let bytes = serde_json::to_vec(&self)?;
+        Ok(bytes.into())
+    }
+}
+
+impl TryFrom<ByteArray> for SequencedEvent {
+    type Error = serde_json::error::Error;
+    fn try_from(array: ByteArray) -> Result<SequencedEvent, serde_json::error::Error> {
+        let bytes: Vec<u8> = array.into();
+        let event = serde_json::from_slice(&bytes)?;
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Capability;
+
+    const ENCODINGS: [EventEncoding; 3] = [
+        EventEncoding::Json,
+        EventEncoding::Cbor,
+        EventEncoding::MessagePack,
+    ];
+    /// Numbers that don't fit smaller integers or signed integers of the formats
+    const NUMBERS: [u64; 5] = [
+        0,
+        u32::MAX as u64,
+        u32::MAX as u64 + 1,
+        i64::MAX as u64 + 1,
+        u64::MAX,
+    ];
+
+    fn cause() -> String {
+        "cause".to_string()
+    }
+
+    fn errors(number: u64) -> Vec<ErrorMessage> {
+        vec![
+            ErrorMessage::FailedToDial { cause: cause() },
+            ErrorMessage::Network { cause: cause() },
+            ErrorMessage::MessageValidation {
+                timestamp: number,
+                cause: cause(),
+            },
+            ErrorMessage::LimitExceeded { cause: cause() },
+            ErrorMessage::MalformedMessage { cause: cause() },
+            ErrorMessage::Mailbox { cause: cause() },
+            ErrorMessage::Rendezvous { cause: cause() },
+            ErrorMessage::Other { cause: cause() },
+        ]
+    }
+
+    /// Index of the variant, fails to compile once a variant is added
+    fn error_index(error: &ErrorMessage) -> usize {
+        match error {
+            ErrorMessage::FailedToDial { .. } => 0,
+            ErrorMessage::Network { .. } => 1,
+            ErrorMessage::MessageValidation { .. } => 2,
+            ErrorMessage::LimitExceeded { .. } => 3,
+            ErrorMessage::MalformedMessage { .. } => 4,
+            ErrorMessage::Mailbox { .. } => 5,
+            ErrorMessage::Rendezvous { .. } => 6,
+            ErrorMessage::Other { .. } => 7,
+        }
+    }
+
+    fn events(number: u64) -> Vec<Event> {
+        let metadata = Metadata {
+            name: "alice".to_string(),
+            client_version: "tata-core/0.1.0".to_string(),
+            status: Some("here".to_string()),
+            avatar_hash: None,
+            capabilities: vec![Capability::Receipts, Capability::Groups],
+        };
+        let mut events = vec![
+            Event::ReceivedPlainTextMessage {
+                message: PlainTextMessage {
+                    to: "bob".to_string(),
+                    timestamp: number,
+                    text: "hi".to_string(),
+                },
+            },
+            Event::ReceivedMetadata {
+                metadata: metadata.clone(),
+            },
+            Event::SentPlainTextMessage { timestamp: number },
+            Event::MessageDeposited { timestamp: number },
+            Event::RelayedMessageDelivered { timestamp: number },
+            Event::ContactRequestReceived {
+                metadata,
+                note: "it's alice".to_string(),
+            },
+            Event::ContactRequestAccepted,
+            Event::ContactRequestDeclined,
+            Event::PeerDiscovered,
+            Event::PeerGone,
+            Event::PeerBanned {
+                reason: "spam".to_string(),
+                duration_secs: number,
+            },
+            Event::PeerUnbanned,
+            Event::PresenceChanged {
+                status: PresenceStatus::Away,
+                last_seen: number,
+            },
+            Event::PeerInfo {
+                agent_version: "tata-core/0.1.0".to_string(),
+                protocol_version: "/tata/1.0.0".to_string(),
+                protocols: vec!["/ipfs/ping/1.0.0".to_string()],
+                listen_addresses: vec![],
+                observed_address: "/memory/1".to_string(),
+            },
+            Event::PeerRtt { rtt_ms: number },
+            Event::ListenAddressAdded {
+                address: "/memory/1".to_string(),
+            },
+            Event::ListenAddressExpired {
+                address: "/memory/1".to_string(),
+            },
+            Event::EventsDropped { count: number },
+        ];
+        events.extend(
+            errors(number)
+                .into_iter()
+                .map(|error| Event::Error { error }),
+        );
+        events
+    }
+
+    /// Index of the variant, fails to compile once a variant is added
+    fn event_index(event: &Event) -> usize {
+        match event {
+            Event::ReceivedPlainTextMessage { .. } => 0,
+            Event::ReceivedMetadata { .. } => 1,
+            Event::SentPlainTextMessage { .. } => 2,
+            Event::MessageDeposited { .. } => 3,
+            Event::RelayedMessageDelivered { .. } => 4,
+            Event::ContactRequestReceived { .. } => 5,
+            Event::ContactRequestAccepted => 6,
+            Event::ContactRequestDeclined => 7,
+            Event::PeerDiscovered => 8,
+            Event::PeerGone => 9,
+            Event::PeerBanned { .. } => 10,
+            Event::PeerUnbanned => 11,
+            Event::PresenceChanged { .. } => 12,
+            Event::PeerInfo { .. } => 13,
+            Event::PeerRtt { .. } => 14,
+            Event::ListenAddressAdded { .. } => 15,
+            Event::ListenAddressExpired { .. } => 16,
+            Event::EventsDropped { .. } => 17,
+            Event::Error { .. } => 18,
+        }
+    }
+
+    fn sequenced(seq: u64, event: Event) -> SequencedEvent {
+        SequencedEvent {
+            seq,
+            event: PeerEvent {
+                peer_id: "peer".to_string(),
+                event,
+            },
+        }
+    }
+
+    /// Decodes the encoded value and compares it with the original by the json shape
+    fn assert_round_trip<T: Serialize + DeserializeOwned>(encoding: EventEncoding, value: &T) {
+        let bytes = encoding.encode(value).unwrap();
+        let decoded: T = encoding.decode(&bytes).unwrap_or_else(|e| {
+            panic!(
+                "Error decoding {} from {:?}: {}",
+                serde_json::to_string(value).unwrap(),
+                encoding,
+                e
+            )
+        });
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(value).unwrap(),
+            "{:?}",
+            encoding
+        );
+    }
+
+    #[test]
+    fn samples_cover_all_variants() {
+        let mut events = events(0).iter().map(event_index).collect::<Vec<_>>();
+        events.dedup();
+        assert_eq!(events, (0..=18).collect::<Vec<_>>());
+        let errors = errors(0).iter().map(error_index).collect::<Vec<_>>();
+        assert_eq!(errors, (0..=7).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn events_round_trip_in_all_encodings() {
+        for encoding in ENCODINGS.iter() {
+            for number in NUMBERS.iter() {
+                for event in events(*number) {
+                    assert_round_trip(*encoding, &sequenced(*number, event.clone()));
+                    assert_round_trip(
+                        *encoding,
+                        &PeerEvent {
+                            peer_id: "peer".to_string(),
+                            event,
+                        },
+                    );
+                }
+                for error in errors(*number) {
+                    assert_round_trip(*encoding, &error);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn message_pack_has_the_json_shape() {
+        let event = sequenced(
+            u64::MAX,
+            Event::Error {
+                error: ErrorMessage::MessageValidation {
+                    timestamp: u64::MAX,
+                    cause: cause(),
+                },
+            },
+        );
+        let bytes = EventEncoding::MessagePack.encode(&event).unwrap();
+        let value: serde_json::Value = rmp_serde::from_read_ref(&bytes).unwrap();
+        assert_eq!(value, serde_json::to_value(&event).unwrap());
+        assert_eq!(value["event"]["type"], "Error");
+        assert_eq!(value["event"]["error"]["type"], "MessageValidation");
+        assert_eq!(value["seq"], u64::MAX);
+
+        let event = sequenced(
+            1,
+            Event::PresenceChanged {
+                status: PresenceStatus::Busy,
+                last_seen: 1,
+            },
+        );
+        let bytes = EventEncoding::MessagePack.encode(&event).unwrap();
+        let value: serde_json::Value = rmp_serde::from_read_ref(&bytes).unwrap();
+        assert_eq!(value["event"]["status"], "Busy");
+    }
+}
@@ -2,6 +2,8 @@
 
 mod bytearray;
 mod keypair;
+mod send_result;
 
 pub use bytearray::*;
 pub use keypair::*;
+pub use send_result::*;
@@ -12,7 +12,8 @@ pub struct ByteArray {
 
 impl From<Vec<u8>> for ByteArray {
     fn from(v: Vec<u8>) -> Self {
-        let mut v = ManuallyDrop::new(v);
+        // Boxed slice has no spare capacity, so it's freed knowing the length only
+        let mut v = ManuallyDrop::new(v.into_boxed_slice());
         ByteArray {
             data: v.as_mut_ptr(),
             len: v.len(),
@@ -20,11 +21,11 @@ impl From<Vec<u8>> for ByteArray {
     }
 }
 
-impl Into<Vec<u8>> for ByteArray {
-    fn into(self) -> Vec<u8> {
+impl From<ByteArray> for Vec<u8> {
+    fn from(array: ByteArray) -> Vec<u8> {
         unsafe {
-            let res = std::slice::from_raw_parts(self.data, self.len).to_vec();
-            self.free();
+            let res = std::slice::from_raw_parts(array.data, array.len).to_vec();
+            array.free();
             res
         }
     }
@@ -47,9 +48,13 @@ impl TryInto<String> for ByteArray {
 }
 
 impl ByteArray {
+    /// Free the memory of the array
+    ///
+    /// # Safety
+    ///
+    /// The array must be created from `Vec<u8>` by this library and not freed yet.
     pub unsafe fn free(self) {
-        let s = std::slice::from_raw_parts_mut(self.data, self.len);
-        let s = s.as_mut_ptr();
-        Box::from_raw(s);
+        let s = std::ptr::slice_from_raw_parts_mut(self.data, self.len);
+        drop(Box::from_raw(s));
     }
 }
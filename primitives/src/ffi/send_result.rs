@@ -0,0 +1,15 @@
+/// FFI representation of the outcome of a sent message
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendResult {
+    /// Sent directly to the peer
+    Sent,
+    /// Stored in the mailbox until the peer fetches it
+    Deposited,
+    /// Relayed through other peers, the peer confirmed the delivery
+    Relayed,
+    /// The message was refused or the peer couldn't be reached
+    Failed,
+    /// No confirmation of the delivery in time
+    Timeout,
+}
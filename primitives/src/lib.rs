@@ -4,9 +4,11 @@ mod event;
 pub mod ffi;
 mod log;
 mod metadata;
+mod presence;
 mod ring_vec;
 
 pub use crate::log::*;
 pub use event::*;
 pub use metadata::*;
+pub use presence::*;
 pub use ring_vec::*;
@@ -1,6 +1,121 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 /// Metadata for p2p exchange
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Metadata {
     pub name: String,
+    /// Client name and version, e.g. `tata-core/0.1.0`
+    #[serde(default)]
+    pub client_version: String,
+    /// Optional status text
+    #[serde(default)]
+    pub status: Option<String>,
+    /// Hash of the avatar image
+    #[serde(default)]
+    pub avatar_hash: Option<String>,
+    /// Optional features supported by the peer
+    #[serde(default, deserialize_with = "deserialize_capabilities")]
+    #[cfg_attr(feature = "schema", schemars(with = "Vec<Capability>"))]
+    pub capabilities: Vec<Capability>,
+}
+
+impl Metadata {
+    /// Capabilities supported by both peers. Features outside of this list
+    /// shouldn't be used with the peer.
+    pub fn common_capabilities(&self, other: &Metadata) -> Vec<Capability> {
+        self.capabilities
+            .iter()
+            .filter(|capability| other.capabilities.contains(capability))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Optional feature supported by a peer
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Capability {
+    /// Delivery and read receipts
+    Receipts,
+    /// File transfer
+    FileTransfer,
+    /// Group chats
+    Groups,
+    /// End-to-end encryption
+    Encryption,
+}
+
+impl Capability {
+    /// Parse the capability from its name. Returns `None` for unknown capabilities.
+    pub fn from_name(name: &str) -> Option<Capability> {
+        match name {
+            "Receipts" => Some(Capability::Receipts),
+            "FileTransfer" => Some(Capability::FileTransfer),
+            "Groups" => Some(Capability::Groups),
+            "Encryption" => Some(Capability::Encryption),
+            _ => None,
+        }
+    }
+}
+
+/// Newer clients can advertise capabilities we don't know about yet, skip them
+/// instead of failing the whole metadata.
+pub fn deserialize_capabilities<'de, D>(deserializer: D) -> Result<Vec<Capability>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let names = Vec::<String>::deserialize(deserializer)?;
+    Ok(names
+        .iter()
+        .filter_map(|name| Capability::from_name(name))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_of_old_clients_has_defaults() {
+        let metadata: Metadata = serde_json::from_str(r#"{"name":"alice"}"#).unwrap();
+        assert_eq!(metadata.name, "alice");
+        assert_eq!(metadata.client_version, "");
+        assert_eq!(metadata.status, None);
+        assert_eq!(metadata.avatar_hash, None);
+        assert!(metadata.capabilities.is_empty());
+    }
+
+    #[test]
+    fn unknown_capabilities_are_skipped() {
+        let metadata: Metadata = serde_json::from_str(
+            r#"{"name":"alice","capabilities":["Receipts","Teleport","Groups"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            metadata.capabilities,
+            vec![Capability::Receipts, Capability::Groups]
+        );
+    }
+
+    #[test]
+    fn common_capabilities_are_supported_by_both_peers() {
+        let metadata = |capabilities| Metadata {
+            name: "peer".to_string(),
+            client_version: String::new(),
+            status: None,
+            avatar_hash: None,
+            capabilities,
+        };
+        let alice = metadata(vec![
+            Capability::Receipts,
+            Capability::FileTransfer,
+            Capability::Encryption,
+        ]);
+        let bob = metadata(vec![Capability::Encryption, Capability::Receipts]);
+        assert_eq!(
+            alice.common_capabilities(&bob),
+            vec![Capability::Receipts, Capability::Encryption]
+        );
+        assert!(alice.common_capabilities(&metadata(vec![])).is_empty());
+    }
 }
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+/// Presence status of a peer
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresenceStatus {
+    /// Peer is online
+    #[default]
+    Online,
+    /// Peer is online but inactive
+    Away,
+    /// Peer is online but doesn't want to be disturbed
+    Busy,
+    /// Peer is disconnected
+    Offline,
+}
+
+impl FromStr for PresenceStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "online" => Ok(PresenceStatus::Online),
+            "away" => Ok(PresenceStatus::Away),
+            "busy" => Ok(PresenceStatus::Busy),
+            "offline" => Ok(PresenceStatus::Offline),
+            _ => Err(format!("Unknown presence status: {}", s)),
+        }
+    }
+}
+
+impl TryFrom<u32> for PresenceStatus {
+    type Error = String;
+
+    /// Converts the discriminant received over FFI
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(PresenceStatus::Online),
+            1 => Ok(PresenceStatus::Away),
+            2 => Ok(PresenceStatus::Busy),
+            3 => Ok(PresenceStatus::Offline),
+            _ => Err(format!("Unknown presence status: {}", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_ffi_discriminants() {
+        for status in &[
+            PresenceStatus::Online,
+            PresenceStatus::Away,
+            PresenceStatus::Busy,
+            PresenceStatus::Offline,
+        ] {
+            assert_eq!(PresenceStatus::try_from(*status as u32), Ok(*status));
+        }
+        assert!(PresenceStatus::try_from(4).is_err());
+        assert!(PresenceStatus::try_from(u32::MAX).is_err());
+    }
+}
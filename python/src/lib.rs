@@ -0,0 +1,238 @@
+//! Native part of the `tata` Python package. The methods block the calling thread with
+//! the GIL released, the async API on top of them is in `tata/__init__.py`.
+//!
+//! Build and test it on loopback:
+//!
+//! ```text
+//! cd python
+//! maturin develop
+//! pytest tests
+//! ```
+
+use async_std::future::timeout;
+use async_std::task::block_on;
+use futures::{Future, StreamExt};
+use primitives::PresenceStatus;
+use pyo3::exceptions::{PyRuntimeError, PyTimeoutError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use pyo3::wrap_pyfunction;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tata_core::{decode_peer_id, Config, DeliveryError, EventReceiver, PeerId, SecretKey};
+
+/// Handle of the node running in the background
+#[pyclass]
+struct Node {
+    node: tata_core::Node,
+    /// Taken out while a call of `next_event` waits for an event
+    events: Arc<Mutex<Option<EventReceiver>>>,
+}
+
+#[pymethods]
+impl Node {
+    /// Start the node. `config` is json of `tata_core::Config`, missing fields take
+    /// default values.
+    #[new]
+    #[args(config = "None")]
+    fn new(secret: &[u8], name: String, config: Option<&str>) -> PyResult<Self> {
+        let config = match config {
+            Some(config) => serde_json::from_str(config).map_err(value_error)?,
+            None => Config::default(),
+        };
+        let secret = SecretKey::from_bytes(secret.to_vec()).map_err(value_error)?;
+        let (node, events) = tata_core::Node::start(secret, name, config).map_err(runtime_error)?;
+        Ok(Node {
+            node,
+            events: Arc::new(Mutex::new(Some(events))),
+        })
+    }
+
+    /// Base58 peer id of the node
+    #[getter]
+    fn peer_id(&self) -> String {
+        self.node.local_peer_id().to_base58()
+    }
+
+    /// Send the message to the contact and wait for the delivery. Returns `Sent`,
+    /// `Deposited` or `Relayed`, raises `TimeoutError` if the delivery isn't confirmed in time.
+    fn send(&self, py: Python, peer_id: &str, text: String) -> PyResult<String> {
+        let peer_id = parse_peer_id(peer_id)?;
+        let node = self.node.clone();
+        let delivery = py.allow_threads(move || {
+            block_on(async move {
+                let delivery = node.send(&peer_id, text).await.map_err(runtime_error)?;
+                delivery.await.map_err(|e| match e {
+                    DeliveryError::Timeout => PyTimeoutError::new_err(e.to_string()),
+                    DeliveryError::Failed(_) => PyRuntimeError::new_err(e.to_string()),
+                })
+            })
+        })?;
+        Ok(format!("{:?}", delivery))
+    }
+
+    /// Wait for the next event up to `timeout_secs`, forever by default. Returns json
+    /// of the event (`primitives::PeerEvent`) or `None` on timeout. The events have
+    /// a single consumer: raises `RuntimeError` if another call is already waiting.
+    #[args(timeout_secs = "None")]
+    fn next_event(&self, py: Python, timeout_secs: Option<f64>) -> PyResult<Option<String>> {
+        let mut receiver = self
+            .events
+            .lock()
+            .map_err(runtime_error)?
+            .take()
+            .ok_or_else(|| PyRuntimeError::new_err("Events are consumed by another call"))?;
+        let event = py.allow_threads(|| {
+            let next = receiver.next();
+            match timeout_secs {
+                Some(secs) => block_on(timeout(Duration::from_secs_f64(secs), next)).ok(),
+                None => Some(block_on(next)),
+            }
+        });
+        *self.events.lock().map_err(runtime_error)? = Some(receiver);
+        match event {
+            None => Ok(None),
+            Some(None) => Err(PyRuntimeError::new_err("Node is stopped")),
+            Some(Some(event)) => serde_json::to_string(&event)
+                .map(Some)
+                .map_err(runtime_error),
+        }
+    }
+
+    /// Add the peer to the contacts
+    fn allow_peer(&self, py: Python, peer_id: &str) -> PyResult<()> {
+        let (node, peer_id) = (self.node.clone(), parse_peer_id(peer_id)?);
+        run(py, async move { node.allow_peer(peer_id).await })
+    }
+
+    /// Remove the peer from the contacts
+    fn disallow_peer(&self, py: Python, peer_id: &str) -> PyResult<()> {
+        let (node, peer_id) = (self.node.clone(), parse_peer_id(peer_id)?);
+        run(py, async move { node.disallow_peer(peer_id).await })
+    }
+
+    /// Block the peer
+    fn block_peer(&self, py: Python, peer_id: &str) -> PyResult<()> {
+        let (node, peer_id) = (self.node.clone(), parse_peer_id(peer_id)?);
+        run(py, async move { node.block_peer(peer_id).await })
+    }
+
+    /// Unblock previously blocked peer
+    fn unblock_peer(&self, py: Python, peer_id: &str) -> PyResult<()> {
+        let (node, peer_id) = (self.node.clone(), parse_peer_id(peer_id)?);
+        run(py, async move { node.unblock_peer(peer_id).await })
+    }
+
+    /// Ask the peer to become a contact
+    fn send_contact_request(&self, py: Python, peer_id: &str, note: String) -> PyResult<()> {
+        let (node, peer_id) = (self.node.clone(), parse_peer_id(peer_id)?);
+        run(
+            py,
+            async move { node.send_contact_request(peer_id, note).await },
+        )
+    }
+
+    /// Accept the contact request of the peer
+    fn accept_contact_request(&self, py: Python, peer_id: &str) -> PyResult<()> {
+        let (node, peer_id) = (self.node.clone(), parse_peer_id(peer_id)?);
+        run(
+            py,
+            async move { node.accept_contact_request(peer_id).await },
+        )
+    }
+
+    /// Decline the contact request of the peer
+    fn decline_contact_request(&self, py: Python, peer_id: &str) -> PyResult<()> {
+        let (node, peer_id) = (self.node.clone(), parse_peer_id(peer_id)?);
+        run(
+            py,
+            async move { node.decline_contact_request(peer_id).await },
+        )
+    }
+
+    /// Remember the address of the peer, e.g. `/ip4/127.0.0.1/tcp/4001`
+    fn add_address(&self, py: Python, peer_id: &str, address: &str) -> PyResult<()> {
+        let (node, peer_id) = (self.node.clone(), parse_peer_id(peer_id)?);
+        let address = address.parse().map_err(value_error)?;
+        run(py, async move { node.add_address(peer_id, address).await })
+    }
+
+    /// Set the presence status: `online`, `away`, `busy` or `offline`
+    fn set_presence(&self, py: Python, status: &str) -> PyResult<()> {
+        let node = self.node.clone();
+        let status: PresenceStatus = status.parse().map_err(value_error)?;
+        run(py, async move { node.set_presence(status).await })
+    }
+
+    /// Use the mailbox node for messages to offline contacts
+    fn set_mailbox(&self, py: Python, peer_id: &str, address: &str) -> PyResult<()> {
+        let (node, peer_id) = (self.node.clone(), parse_peer_id(peer_id)?);
+        let address = address.parse().map_err(value_error)?;
+        run(py, async move { node.set_mailbox(peer_id, address).await })
+    }
+
+    /// Addresses the node listens on
+    fn listen_addresses(&self, py: Python) -> PyResult<Vec<String>> {
+        let node = self.node.clone();
+        let addresses = run(py, async move { node.listen_addresses().await })?;
+        Ok(addresses.iter().map(ToString::to_string).collect())
+    }
+
+    /// Base58 peer ids of the peers with an open chat connection
+    fn connected_peers(&self, py: Python) -> PyResult<Vec<String>> {
+        let node = self.node.clone();
+        let peers = run(py, async move { node.connected_peers().await })?;
+        Ok(peers.iter().map(PeerId::to_base58).collect())
+    }
+
+    /// Base58 peer ids of the contacts
+    fn contacts(&self, py: Python) -> PyResult<Vec<String>> {
+        let node = self.node.clone();
+        let peers = run(py, async move { node.contacts().await })?;
+        Ok(peers.iter().map(PeerId::to_base58).collect())
+    }
+}
+
+/// Generate a secret key and return it with the base58 peer id derived from it
+#[pyfunction]
+fn generate_keypair(py: Python) -> (PyObject, String) {
+    let (secret, peer_id) = tata_core::generate_secret();
+    let secret = PyBytes::new(py, &secret.to_bytes()).into();
+    (secret, peer_id.to_base58())
+}
+
+/// Generate a pre-shared key for a private network
+#[pyfunction]
+fn generate_psk() -> String {
+    tata_core::generate_psk()
+}
+
+#[pymodule]
+fn _native(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<Node>()?;
+    m.add_function(wrap_pyfunction!(generate_keypair, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_psk, m)?)?;
+    Ok(())
+}
+
+/// Run the future to completion with the GIL released
+fn run<T: Send>(
+    py: Python,
+    future: impl Future<Output = tata_core::Result<T>> + Send,
+) -> PyResult<T> {
+    py.allow_threads(move || block_on(future))
+        .map_err(runtime_error)
+}
+
+fn parse_peer_id(peer_id: &str) -> PyResult<PeerId> {
+    decode_peer_id(peer_id).map_err(value_error)
+}
+
+fn value_error(e: impl Display) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn runtime_error(e: impl Display) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
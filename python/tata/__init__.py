@@ -0,0 +1,109 @@
+"""Python bindings for the tata core.
+
+Every method of the node runs in the default executor of the event loop, so the
+loop is never blocked::
+
+    secret, peer_id = tata.generate_keypair()
+    node = tata.Node(secret, "bot")
+    await node.allow_peer(friend)
+    delivery = await node.send(friend, "Hello")
+    async for event in node.events():
+        print(event["peer_id"], event["event"]["type"])
+"""
+
+import asyncio
+import functools
+import json
+
+from . import _native
+
+__all__ = ["Node", "generate_keypair", "generate_psk"]
+
+
+def generate_keypair():
+    """Generate a secret key, returns the key bytes and the base58 peer id."""
+    return _native.generate_keypair()
+
+
+def generate_psk():
+    """Generate a pre-shared key for a private network."""
+    return _native.generate_psk()
+
+
+class Node:
+    """Node running in the background.
+
+    `config` is a dict of `tata_core::Config`, e.g.
+    `{"transport": {"listen_addresses": ["/ip4/127.0.0.1/tcp/0"]}}`.
+    Missing fields take default values.
+    """
+
+    def __init__(self, secret, name, config=None):
+        config = json.dumps(config) if config is not None else None
+        self._node = _native.Node(secret, name, config)
+
+    @property
+    def peer_id(self):
+        """Base58 peer id of the node."""
+        return self._node.peer_id
+
+    async def events(self, poll_interval=1.0):
+        """Iterate over the events (dicts of `primitives::PeerEvent`).
+
+        The events have a single consumer, iterate over them in one task only.
+        """
+        while True:
+            event = await self._run(self._node.next_event, poll_interval)
+            if event is not None:
+                yield json.loads(event)
+
+    async def send(self, peer_id, text):
+        """Send the message to the contact and wait for the delivery.
+
+        Returns `"Sent"`, `"Deposited"` or `"Relayed"`, raises `TimeoutError`
+        if the delivery isn't confirmed in time.
+        """
+        return await self._run(self._node.send, peer_id, text)
+
+    async def allow_peer(self, peer_id):
+        await self._run(self._node.allow_peer, peer_id)
+
+    async def disallow_peer(self, peer_id):
+        await self._run(self._node.disallow_peer, peer_id)
+
+    async def block_peer(self, peer_id):
+        await self._run(self._node.block_peer, peer_id)
+
+    async def unblock_peer(self, peer_id):
+        await self._run(self._node.unblock_peer, peer_id)
+
+    async def send_contact_request(self, peer_id, note=""):
+        await self._run(self._node.send_contact_request, peer_id, note)
+
+    async def accept_contact_request(self, peer_id):
+        await self._run(self._node.accept_contact_request, peer_id)
+
+    async def decline_contact_request(self, peer_id):
+        await self._run(self._node.decline_contact_request, peer_id)
+
+    async def add_address(self, peer_id, address):
+        await self._run(self._node.add_address, peer_id, address)
+
+    async def set_presence(self, status):
+        await self._run(self._node.set_presence, status)
+
+    async def set_mailbox(self, peer_id, address):
+        await self._run(self._node.set_mailbox, peer_id, address)
+
+    async def listen_addresses(self):
+        return await self._run(self._node.listen_addresses)
+
+    async def connected_peers(self):
+        return await self._run(self._node.connected_peers)
+
+    async def contacts(self):
+        return await self._run(self._node.contacts)
+
+    async def _run(self, method, *args):
+        loop = asyncio.get_event_loop()
+        return await loop.run_in_executor(None, functools.partial(method, *args))
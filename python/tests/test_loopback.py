@@ -0,0 +1,97 @@
+"""Two nodes on loopback exchanging messages through the bindings::
+
+    maturin develop
+    pytest tests
+"""
+
+import asyncio
+import threading
+
+import pytest
+
+import tata
+
+CONFIG = {
+    "transport": {"listen_addresses": ["/ip4/127.0.0.1/tcp/0"]},
+    "discovery": {"visible": False, "discover": False},
+}
+TIMEOUT = 30
+
+
+def run(coroutine):
+    return asyncio.new_event_loop().run_until_complete(
+        asyncio.wait_for(coroutine, timeout=TIMEOUT)
+    )
+
+
+def start(name):
+    return tata.Node(tata.generate_keypair()[0], name, CONFIG)
+
+
+async def listen_address(node):
+    while True:
+        addresses = await node.listen_addresses()
+        if addresses:
+            return addresses[0]
+        await asyncio.sleep(0.1)
+
+
+async def next_event(node, event_type):
+    async for event in node.events(poll_interval=0.1):
+        if event["event"]["type"] == event_type:
+            return event
+
+
+async def connect(alice, bob):
+    await alice.allow_peer(bob.peer_id)
+    await bob.allow_peer(alice.peer_id)
+    await alice.add_address(bob.peer_id, await listen_address(bob))
+
+
+def test_keypair_has_peer_id():
+    secret, peer_id = tata.generate_keypair()
+    assert len(secret) == 32
+    assert tata.Node(secret, "alice", CONFIG).peer_id == peer_id
+
+
+def test_message_is_delivered():
+    async def main():
+        alice, bob = start("alice"), start("bob")
+        await connect(alice, bob)
+
+        received = asyncio.ensure_future(next_event(bob, "ReceivedPlainTextMessage"))
+        assert await alice.send(bob.peer_id, "Hello, Bob") == "Sent"
+        event = await received
+        assert event["peer_id"] == alice.peer_id
+        assert event["event"]["message"]["text"] == "Hello, Bob"
+        assert bob.peer_id in await alice.connected_peers()
+        assert await bob.contacts() == [alice.peer_id]
+
+    run(main())
+
+
+def test_message_to_stranger_is_refused():
+    async def main():
+        alice, bob = start("alice"), start("bob")
+        await alice.add_address(bob.peer_id, await listen_address(bob))
+
+        with pytest.raises(RuntimeError):
+            await alice.send(bob.peer_id, "Hello, stranger")
+
+    run(main())
+
+
+def test_events_have_single_consumer():
+    node = start("alice")
+    while node._node.next_event(0.5) is not None:
+        pass
+    waiting = threading.Thread(target=node._node.next_event, args=(2.0,))
+    waiting.start()
+    try:
+        threading.Event().wait(0.2)
+        with pytest.raises(RuntimeError):
+            node._node.next_event(0.1)
+    finally:
+        waiting.join()
+    # The receiver is back once the waiting call returns
+    node._node.next_event(0.1)
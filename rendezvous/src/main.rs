@@ -0,0 +1,84 @@
+//! Headless rendezvous server. Peers register their addresses under a namespace
+//! and discover the other peers registered in it.
+//!
+//! Everything can be tried on loopback:
+//!
+//! ```text
+//! cargo run -p rendezvous -- --listen /ip4/127.0.0.1/tcp/4002
+//! ```
+//!
+//! then in two cli instances
+//!
+//! ```text
+//! rendezvous --peer <server peer id> --address /ip4/127.0.0.1/tcp/4002
+//! join my-team
+//! ```
+//!
+//! Each instance receives `PeerDiscovered` for the other one.
+
+use libp2p::identity::{
+    secp256k1::{Keypair, SecretKey},
+    PublicKey,
+};
+use libp2p::PeerId;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use tata_core::{create_rendezvous_swarm, RendezvousServerConfig};
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "rendezvous")]
+struct Opt {
+    /// Multiaddress to listen on
+    #[structopt(short, long, default_value = "/ip4/0.0.0.0/tcp/4002")]
+    listen: String,
+    /// File with the secret key of the node. A new key is generated if the file doesn't exist.
+    #[structopt(short, long, default_value = "rendezvous.key", parse(from_os_str))]
+    key_file: PathBuf,
+    /// Max time a registration is kept without being refreshed, in seconds
+    #[structopt(long, default_value = "259200")]
+    max_ttl: u64,
+    /// Pre-shared key of the private network, 64 hex characters. Only the nodes
+    /// with the same key can connect.
+    #[structopt(long)]
+    psk: Option<String>,
+}
+
+fn load_or_generate_secret(path: &Path) -> Result<SecretKey, Box<dyn std::error::Error>> {
+    if path.exists() {
+        return Ok(SecretKey::from_bytes(fs::read(path)?)?);
+    }
+    let secret: SecretKey = Keypair::generate().into();
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    // Only the owner can read the key
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(&secret.to_bytes())?;
+    Ok(secret)
+}
+
+#[async_std::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let opt = Opt::from_args();
+    let secret = load_or_generate_secret(&opt.key_file)?;
+    let keypair: Keypair = secret.clone().into();
+    let peer_id = PeerId::from_public_key(PublicKey::Secp256k1(keypair.public().clone()));
+    let config = RendezvousServerConfig {
+        listen_address: opt.listen.clone(),
+        max_ttl_secs: opt.max_ttl,
+        psk: opt.psk,
+        ..Default::default()
+    };
+    let mut swarm = create_rendezvous_swarm(secret, config)?;
+    log::info!(
+        "Rendezvous server {} is listening on {}",
+        peer_id,
+        opt.listen
+    );
+    loop {
+        swarm.next().await;
+    }
+}
@@ -0,0 +1,205 @@
+//! Dart classes of the events with `fromJson` constructors
+use crate::model::{Field, Ty, TypeDef};
+use primitives::WIRE_VERSION;
+use std::fmt::Write;
+
+pub fn render(defs: &[TypeDef]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "// Generated by schema-gen, do not edit.\n");
+    let _ = writeln!(out, "const int wireVersion = {};", WIRE_VERSION);
+    for def in defs {
+        out.push('\n');
+        match def {
+            TypeDef::Struct {
+                name,
+                description,
+                fields,
+            } => {
+                doc(&mut out, "", description);
+                render_class(&mut out, name, None, fields);
+            }
+            TypeDef::Union {
+                name,
+                description,
+                tag,
+                variants,
+            } => {
+                doc(&mut out, "", description);
+                let _ = writeln!(out, "abstract class {} {{", name);
+                let _ = writeln!(
+                    out,
+                    "  factory {}.fromJson(Map<String, dynamic> json) {{",
+                    name
+                );
+                let _ = writeln!(out, "    switch (json['{}']) {{", tag);
+                for variant in variants {
+                    let _ = writeln!(out, "      case '{}':", variant.name);
+                    let _ = writeln!(
+                        out,
+                        "        return {}{}.fromJson(json);",
+                        name, variant.name
+                    );
+                }
+                out.push_str("      default:\n");
+                let _ = writeln!(
+                    out,
+                    "        throw ArgumentError('Unknown {}: ${{json['{}']}}');",
+                    name, tag
+                );
+                out.push_str("    }\n  }\n}\n");
+                for variant in variants {
+                    out.push('\n');
+                    doc(&mut out, "", &variant.description);
+                    render_class(
+                        &mut out,
+                        &format!("{}{}", name, variant.name),
+                        Some(name),
+                        &variant.fields,
+                    );
+                }
+            }
+            TypeDef::StringEnum {
+                name,
+                description,
+                values,
+            } => {
+                doc(&mut out, "", description);
+                let _ = writeln!(out, "enum {} {{", name);
+                for value in values {
+                    let _ = writeln!(out, "  {},", lower_camel(value));
+                }
+                out.push_str("}\n\n");
+                let _ = writeln!(
+                    out,
+                    "{} {}FromJson(dynamic json) {{",
+                    name,
+                    lower_camel(name)
+                );
+                out.push_str("  switch (json) {\n");
+                for value in values {
+                    let _ = writeln!(out, "    case '{}':", value);
+                    let _ = writeln!(out, "      return {}.{};", name, lower_camel(value));
+                }
+                out.push_str("    default:\n");
+                let _ = writeln!(out, "      throw ArgumentError('Unknown {}: $json');", name);
+                out.push_str("  }\n}\n");
+            }
+        }
+    }
+    out
+}
+
+fn render_class(out: &mut String, name: &str, parent: Option<&str>, fields: &[Field]) {
+    match parent {
+        Some(parent) => {
+            let _ = writeln!(out, "class {} implements {} {{", name, parent);
+        }
+        None => {
+            let _ = writeln!(out, "class {} {{", name);
+        }
+    }
+    for field in fields {
+        doc(out, "  ", &field.description);
+        let _ = writeln!(
+            out,
+            "  final {} {};",
+            ty(&field.ty),
+            lower_camel(&field.name)
+        );
+    }
+    if !fields.is_empty() {
+        out.push('\n');
+    }
+    let params = fields
+        .iter()
+        .map(|field| format!("this.{}", lower_camel(&field.name)))
+        .collect::<Vec<_>>();
+    let _ = writeln!(out, "  {}({});\n", name, params.join(", "));
+    let _ = writeln!(out, "  {}.fromJson(Map<String, dynamic> json)", name);
+    if fields.is_empty() {
+        out.push_str("      ;\n}\n");
+        return;
+    }
+    for (i, field) in fields.iter().enumerate() {
+        let value = format!("json['{}']", field.name);
+        let mut parsed = from_json(&field.ty, &value);
+        if !field.required {
+            parsed = format!("{} == null ? {} : {}", value, default(&field.ty), parsed);
+        }
+        let separator = if i == 0 { ":" } else { "," };
+        let _ = writeln!(
+            out,
+            "      {} {} = {}",
+            separator,
+            lower_camel(&field.name),
+            parsed
+        );
+    }
+    out.push_str("      ;\n}\n");
+}
+
+fn ty(ty: &Ty) -> String {
+    match ty {
+        Ty::String => "String".to_string(),
+        Ty::Integer => "int".to_string(),
+        Ty::Number => "double".to_string(),
+        Ty::Boolean => "bool".to_string(),
+        Ty::Ref(name) => name.clone(),
+        Ty::Array(inner) => format!("List<{}>", self::ty(inner)),
+        Ty::Nullable(inner) => self::ty(inner),
+        Ty::Any => "dynamic".to_string(),
+    }
+}
+
+fn from_json(ty: &Ty, value: &str) -> String {
+    match ty {
+        Ty::Number => format!("({} as num).toDouble()", value),
+        Ty::Ref(name) => format!("{}.fromJson({})", name, value),
+        Ty::Array(inner) => format!(
+            "({} as List).map((e) => {}).toList()",
+            value,
+            from_json(inner, "e")
+        ),
+        Ty::Nullable(inner) => format!("{} == null ? null : {}", value, from_json(inner, value)),
+        _ => value.to_string(),
+    }
+}
+
+/// Value of a field missing in json, matches `#[serde(default)]` on the Rust side
+fn default(ty: &Ty) -> String {
+    match ty {
+        Ty::String => "''".to_string(),
+        Ty::Integer => "0".to_string(),
+        Ty::Number => "0.0".to_string(),
+        Ty::Boolean => "false".to_string(),
+        Ty::Array(inner) => format!("<{}>[]", self::ty(inner)),
+        _ => "null".to_string(),
+    }
+}
+
+/// `client_version` -> `clientVersion`, `FileTransfer` -> `fileTransfer`
+fn lower_camel(name: &str) -> String {
+    let mut out = String::new();
+    let mut upper = false;
+    for (i, c) in name.chars().enumerate() {
+        if c == '_' {
+            upper = true;
+        } else if i == 0 {
+            out.extend(c.to_lowercase());
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn doc(out: &mut String, indent: &str, description: &Option<String>) {
+    if let Some(description) = description {
+        for line in description.lines() {
+            let _ = writeln!(out, "{}/// {}", indent, line);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Generates JSON Schema, TypeScript and Dart type definitions and golden fixtures
+//! for the events delivered to the hosts (`primitives::SequencedEvent` and the types in it).
+//!
+//! The files are written to `<out-dir>/v<WIRE_VERSION>`. Existing files are never
+//! overwritten with a different content: a change of the wire shape requires bumping
+//! `primitives::WIRE_VERSION`. Run with `--check` in CI to fail on such changes.
+//!
+//! ```text
+//! cargo run -p schema-gen
+//! cargo run -p schema-gen -- --check
+//! ```
+
+mod dart;
+mod model;
+mod samples;
+mod typescript;
+
+use primitives::{
+    Capability, ErrorMessage, Event, Metadata, PeerEvent, PlainTextMessage, PresenceStatus,
+    SequencedEvent, WIRE_VERSION,
+};
+use schemars::gen::SchemaGenerator;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "schema-gen")]
+struct Opt {
+    /// Directory the versioned schema directories are written to
+    #[structopt(long, default_value = "primitives/schema", parse(from_os_str))]
+    out_dir: PathBuf,
+    /// Only check that the files are up to date, exit with an error otherwise
+    #[structopt(long)]
+    check: bool,
+    /// Overwrite the files of the current version, e.g. after changing the doc comments only
+    #[structopt(long)]
+    force: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let opt = Opt::from_args();
+    let files = generate()?;
+    let dir = opt.out_dir.join(format!("v{}", WIRE_VERSION));
+    let changed = changed(&dir, &files);
+    if changed.is_empty() {
+        println!("Schema v{} is up to date", WIRE_VERSION);
+        return Ok(());
+    }
+    if opt.check {
+        eprintln!(
+            "Schema v{} is out of date: {}. Run `cargo run -p schema-gen`, bump \
+             `primitives::WIRE_VERSION` if the wire shape changed.",
+            WIRE_VERSION,
+            changed.join(", ")
+        );
+        std::process::exit(1);
+    }
+    if dir.exists() && !opt.force {
+        eprintln!(
+            "Wire shape of v{} changed: {}. Bump `primitives::WIRE_VERSION` \
+             or use `--force` if the change is compatible.",
+            WIRE_VERSION,
+            changed.join(", ")
+        );
+        std::process::exit(1);
+    }
+    fs::create_dir_all(&dir)?;
+    for (name, content) in files.iter() {
+        fs::write(dir.join(name), content)?;
+    }
+    println!("Schema v{} is written to {}", WIRE_VERSION, dir.display());
+    Ok(())
+}
+
+/// Names of the files that differ from the ones in the directory
+fn changed<'a>(dir: &Path, files: &'a [(String, String)]) -> Vec<&'a str> {
+    files
+        .iter()
+        .filter(|(name, content)| {
+            fs::read_to_string(dir.join(name)).ok().as_deref() != Some(content.as_str())
+        })
+        .map(|(name, _)| name.as_str())
+        .collect()
+}
+
+/// Names and contents of the generated files
+fn generate() -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let mut generator = SchemaGenerator::default();
+    generator.subschema_for::<PeerEvent>();
+    generator.subschema_for::<Event>();
+    generator.subschema_for::<ErrorMessage>();
+    generator.subschema_for::<PlainTextMessage>();
+    generator.subschema_for::<Metadata>();
+    generator.subschema_for::<Capability>();
+    generator.subschema_for::<PresenceStatus>();
+    let schema = serde_json::to_value(generator.into_root_schema_for::<SequencedEvent>())?;
+    let definitions = model::type_defs(&schema)?;
+    Ok(vec![
+        (
+            "events.schema.json".to_string(),
+            serde_json::to_string_pretty(&schema)? + "\n",
+        ),
+        ("events.d.ts".to_string(), typescript::render(&definitions)),
+        ("events.dart".to_string(), dart::render(&definitions)),
+        (
+            "fixtures.json".to_string(),
+            serde_json::to_string_pretty(&samples::samples())? + "\n",
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Same as `cargo run -p schema-gen -- --check`
+    #[test]
+    fn committed_schema_is_up_to_date() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../primitives/schema")
+            .join(format!("v{}", WIRE_VERSION));
+        let files = generate().unwrap();
+        assert_eq!(
+            changed(&dir, &files),
+            Vec::<&str>::new(),
+            "Schema v{} is out of date. Run `cargo run -p schema-gen`, bump \
+             `primitives::WIRE_VERSION` if the wire shape changed.",
+            WIRE_VERSION
+        );
+    }
+}
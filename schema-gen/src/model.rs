@@ -0,0 +1,234 @@
+//! Type definitions extracted from the JSON Schema, rendered by the language generators.
+//!
+//! Only the shapes produced by `schemars` for the primitives are supported: structs,
+//! internally tagged enums and enums of unit variants.
+use serde_json::{Map, Value};
+
+pub enum TypeDef {
+    Struct {
+        name: String,
+        description: Option<String>,
+        fields: Vec<Field>,
+    },
+    /// Internally tagged enum
+    Union {
+        name: String,
+        description: Option<String>,
+        tag: String,
+        variants: Vec<Variant>,
+    },
+    /// Enum of unit variants, serialized as strings
+    StringEnum {
+        name: String,
+        description: Option<String>,
+        values: Vec<String>,
+    },
+}
+
+pub struct Variant {
+    /// Value of the tag
+    pub name: String,
+    pub description: Option<String>,
+    pub fields: Vec<Field>,
+}
+
+pub struct Field {
+    pub name: String,
+    pub description: Option<String>,
+    pub ty: Ty,
+    /// `false` if the field can be missing
+    pub required: bool,
+}
+
+pub enum Ty {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    Ref(String),
+    Array(Box<Ty>),
+    Nullable(Box<Ty>),
+    Any,
+}
+
+/// Definitions of the root schema and all the schemas it references, sorted by name
+pub fn type_defs(schema: &Value) -> Result<Vec<TypeDef>, String> {
+    let mut defs = vec![];
+    let root_name = schema["title"].as_str().ok_or("Root schema has no title")?;
+    defs.push(type_def(root_name, schema)?);
+    if let Some(definitions) = schema["definitions"].as_object() {
+        for (name, schema) in definitions {
+            defs.push(type_def(name, schema)?);
+        }
+    }
+    defs.sort_by(|a, b| a.name().cmp(b.name()));
+    Ok(defs)
+}
+
+impl TypeDef {
+    pub fn name(&self) -> &str {
+        match self {
+            TypeDef::Struct { name, .. }
+            | TypeDef::Union { name, .. }
+            | TypeDef::StringEnum { name, .. } => name,
+        }
+    }
+}
+
+fn type_def(name: &str, schema: &Value) -> Result<TypeDef, String> {
+    let type_description = description(schema);
+    if let Some(variants) = schema["oneOf"].as_array() {
+        // Unit variants of a plain enum may come as separate string enums
+        if variants.iter().all(|variant| variant["enum"].is_array()) {
+            return Ok(TypeDef::StringEnum {
+                name: name.to_string(),
+                description: type_description,
+                values: variants.iter().flat_map(enum_values).collect(),
+            });
+        }
+        let mut tag = None;
+        let mut parsed = vec![];
+        for variant in variants {
+            let (variant_tag, value) = variant_tag(variant)
+                .ok_or_else(|| format!("Variant of {} has no tag: {}", name, variant))?;
+            tag = Some(variant_tag.to_string());
+            let fields = fields(variant)?
+                .into_iter()
+                .filter(|field| field.name != variant_tag)
+                .collect();
+            parsed.push(Variant {
+                name: value,
+                description: description(variant),
+                fields,
+            });
+        }
+        return Ok(TypeDef::Union {
+            name: name.to_string(),
+            description: type_description,
+            tag: tag.ok_or_else(|| format!("{} has no variants", name))?,
+            variants: parsed,
+        });
+    }
+    if schema["enum"].is_array() {
+        return Ok(TypeDef::StringEnum {
+            name: name.to_string(),
+            description: type_description,
+            values: enum_values(schema),
+        });
+    }
+    if schema["properties"].is_object() {
+        return Ok(TypeDef::Struct {
+            name: name.to_string(),
+            description: type_description,
+            fields: fields(schema)?,
+        });
+    }
+    Err(format!("Unsupported schema of {}: {}", name, schema))
+}
+
+/// Name of the tag property and its value, i.e. the property with a single allowed string
+fn variant_tag(variant: &Value) -> Option<(&str, String)> {
+    variant["properties"]
+        .as_object()?
+        .iter()
+        .find_map(|(name, schema)| match schema["enum"].as_array() {
+            Some(values) if values.len() == 1 => {
+                Some((name.as_str(), values[0].as_str()?.to_string()))
+            }
+            _ => None,
+        })
+}
+
+fn fields(schema: &Value) -> Result<Vec<Field>, String> {
+    let empty = Map::new();
+    let properties = schema["properties"].as_object().unwrap_or(&empty);
+    let required = schema["required"]
+        .as_array()
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(|name| name.as_str())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    Ok(properties
+        .iter()
+        .map(|(name, schema)| Field {
+            name: name.clone(),
+            description: description(schema),
+            ty: ty(schema),
+            required: required.contains(&name.as_str()),
+        })
+        .collect())
+}
+
+fn ty(schema: &Value) -> Ty {
+    if let Some(reference) = schema["$ref"].as_str() {
+        return Ty::Ref(
+            reference
+                .rsplit('/')
+                .next()
+                .unwrap_or(reference)
+                .to_string(),
+        );
+    }
+    if let Some([inner]) = schema["allOf"].as_array().map(Vec::as_slice) {
+        return ty(inner);
+    }
+    if let Some(variants) = schema["anyOf"].as_array() {
+        let not_null = variants
+            .iter()
+            .filter(|variant| variant["type"] != "null")
+            .collect::<Vec<_>>();
+        if let [inner] = not_null.as_slice() {
+            return Ty::Nullable(Box::new(ty(inner)));
+        }
+        return Ty::Any;
+    }
+    match &schema["type"] {
+        Value::String(name) => named_ty(name, schema),
+        Value::Array(names) => {
+            let not_null = names
+                .iter()
+                .filter_map(|name| name.as_str())
+                .filter(|name| *name != "null")
+                .collect::<Vec<_>>();
+            match not_null.as_slice() {
+                [name] if not_null.len() < names.len() => {
+                    Ty::Nullable(Box::new(named_ty(name, schema)))
+                }
+                [name] => named_ty(name, schema),
+                _ => Ty::Any,
+            }
+        }
+        _ => Ty::Any,
+    }
+}
+
+fn named_ty(name: &str, schema: &Value) -> Ty {
+    match name {
+        "string" => Ty::String,
+        "integer" => Ty::Integer,
+        "number" => Ty::Number,
+        "boolean" => Ty::Boolean,
+        "array" => Ty::Array(Box::new(ty(&schema["items"]))),
+        _ => Ty::Any,
+    }
+}
+
+fn enum_values(schema: &Value) -> Vec<String> {
+    schema["enum"]
+        .as_array()
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|value| value.as_str())
+                .map(ToString::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn description(schema: &Value) -> Option<String> {
+    schema["description"].as_str().map(ToString::to_string)
+}
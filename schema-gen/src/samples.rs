@@ -0,0 +1,110 @@
+//! Golden fixtures: a sample of every event as it is sent to the hosts
+use primitives::{
+    Capability, ErrorMessage, Event, Metadata, PeerEvent, PlainTextMessage, PresenceStatus,
+    SequencedEvent,
+};
+
+const PEER_ID: &str = "12D3KooWQYhTNQdmr3ArTeUHRYzFg94BKyTkoWBDWez9kSCVe2Xo";
+const TIMESTAMP: u64 = 1_600_000_000_000;
+
+pub fn samples() -> Vec<SequencedEvent> {
+    let metadata = Metadata {
+        name: "Alice".to_string(),
+        client_version: "tata-core/0.1.0".to_string(),
+        status: Some("Hello".to_string()),
+        avatar_hash: None,
+        capabilities: vec![Capability::Receipts, Capability::FileTransfer],
+    };
+    let errors = vec![
+        ErrorMessage::FailedToDial {
+            cause: "Connection refused".to_string(),
+        },
+        ErrorMessage::Network {
+            cause: "Connection reset".to_string(),
+        },
+        ErrorMessage::MessageValidation {
+            timestamp: TIMESTAMP,
+            cause: "Message is too long".to_string(),
+        },
+        ErrorMessage::LimitExceeded {
+            cause: "Frame is too large".to_string(),
+        },
+        ErrorMessage::MalformedMessage {
+            cause: "Invalid json".to_string(),
+        },
+        ErrorMessage::Mailbox {
+            cause: "Mailbox is full".to_string(),
+        },
+        ErrorMessage::Rendezvous {
+            cause: "Invalid namespace".to_string(),
+        },
+        ErrorMessage::Other {
+            cause: "Other".to_string(),
+        },
+    ];
+    let events = vec![
+        Event::ReceivedPlainTextMessage {
+            message: PlainTextMessage {
+                to: PEER_ID.to_string(),
+                timestamp: TIMESTAMP,
+                text: "Hi".to_string(),
+            },
+        },
+        Event::ReceivedMetadata {
+            metadata: metadata.clone(),
+        },
+        Event::SentPlainTextMessage {
+            timestamp: TIMESTAMP,
+        },
+        Event::MessageDeposited {
+            timestamp: TIMESTAMP,
+        },
+        Event::RelayedMessageDelivered {
+            timestamp: TIMESTAMP,
+        },
+        Event::ContactRequestReceived {
+            metadata,
+            note: "It's Alice".to_string(),
+        },
+        Event::ContactRequestAccepted,
+        Event::ContactRequestDeclined,
+        Event::PeerDiscovered,
+        Event::PeerGone,
+        Event::PeerBanned {
+            reason: "Spam".to_string(),
+            duration_secs: 3600,
+        },
+        Event::PeerUnbanned,
+        Event::PresenceChanged {
+            status: PresenceStatus::Away,
+            last_seen: TIMESTAMP,
+        },
+        Event::PeerInfo {
+            agent_version: "tata-core/0.1.0".to_string(),
+            protocol_version: "/tata/1.0.0".to_string(),
+            protocols: vec!["/ipfs/ping/1.0.0".to_string()],
+            listen_addresses: vec!["/ip4/192.168.0.2/tcp/4001".to_string()],
+            observed_address: "/ip4/192.168.0.3/tcp/53412".to_string(),
+        },
+        Event::PeerRtt { rtt_ms: 42 },
+        Event::ListenAddressAdded {
+            address: "/ip4/127.0.0.1/tcp/4001".to_string(),
+        },
+        Event::ListenAddressExpired {
+            address: "/ip4/127.0.0.1/tcp/4001".to_string(),
+        },
+        Event::EventsDropped { count: 10 },
+    ];
+    events
+        .into_iter()
+        .chain(errors.into_iter().map(|error| Event::Error { error }))
+        .enumerate()
+        .map(|(i, event)| SequencedEvent {
+            seq: i as u64 + 1,
+            event: PeerEvent {
+                peer_id: PEER_ID.to_string(),
+                event,
+            },
+        })
+        .collect()
+}
@@ -0,0 +1,92 @@
+//! TypeScript definitions of the events
+use crate::model::{Field, Ty, TypeDef};
+use primitives::WIRE_VERSION;
+use std::fmt::Write;
+
+pub fn render(defs: &[TypeDef]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "// Generated by schema-gen, do not edit.\n");
+    let _ = writeln!(out, "export const WIRE_VERSION = {};", WIRE_VERSION);
+    for def in defs {
+        out.push('\n');
+        match def {
+            TypeDef::Struct {
+                name,
+                description,
+                fields,
+            } => {
+                doc(&mut out, "", description);
+                let _ = writeln!(out, "export interface {} {{", name);
+                render_fields(&mut out, fields);
+                out.push_str("}\n");
+            }
+            TypeDef::Union {
+                name,
+                description,
+                tag,
+                variants,
+            } => {
+                doc(&mut out, "", description);
+                let names = variants
+                    .iter()
+                    .map(|variant| format!("{}{}", name, variant.name))
+                    .collect::<Vec<_>>();
+                let _ = writeln!(out, "export type {} =\n  | {};", name, names.join("\n  | "));
+                for variant in variants {
+                    out.push('\n');
+                    doc(&mut out, "", &variant.description);
+                    let _ = writeln!(out, "export interface {}{} {{", name, variant.name);
+                    let _ = writeln!(out, "  {}: \"{}\";", tag, variant.name);
+                    render_fields(&mut out, &variant.fields);
+                    out.push_str("}\n");
+                }
+            }
+            TypeDef::StringEnum {
+                name,
+                description,
+                values,
+            } => {
+                doc(&mut out, "", description);
+                let values = values
+                    .iter()
+                    .map(|value| format!("\"{}\"", value))
+                    .collect::<Vec<_>>();
+                let _ = writeln!(out, "export type {} = {};", name, values.join(" | "));
+            }
+        }
+    }
+    out
+}
+
+fn render_fields(out: &mut String, fields: &[Field]) {
+    for field in fields {
+        doc(out, "  ", &field.description);
+        let optional = if field.required { "" } else { "?" };
+        let _ = writeln!(out, "  {}{}: {};", field.name, optional, ty(&field.ty));
+    }
+}
+
+fn ty(ty: &Ty) -> String {
+    match ty {
+        Ty::String => "string".to_string(),
+        Ty::Integer | Ty::Number => "number".to_string(),
+        Ty::Boolean => "boolean".to_string(),
+        Ty::Ref(name) => name.clone(),
+        Ty::Array(inner) => match inner.as_ref() {
+            Ty::Nullable(_) => format!("({})[]", self::ty(inner)),
+            _ => format!("{}[]", self::ty(inner)),
+        },
+        Ty::Nullable(inner) => format!("{} | null", self::ty(inner)),
+        Ty::Any => "unknown".to_string(),
+    }
+}
+
+fn doc(out: &mut String, indent: &str, description: &Option<String>) {
+    if let Some(description) = description {
+        let _ = writeln!(out, "{}/**", indent);
+        for line in description.lines() {
+            let _ = writeln!(out, "{} * {}", indent, line);
+        }
+        let _ = writeln!(out, "{} */", indent);
+    }
+}